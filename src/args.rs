@@ -7,12 +7,14 @@
 //! - `Args` struct defines all CLI arguments, their help text, and parsing rules.
 
 use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Supported project types/languages/IDEs for cleaning.
 ///
 /// Each variant corresponds to a set of build/cache/temp directories that can be cleaned.
-#[derive(ValueEnum, Debug, Clone)]
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum ProjectKind {
     /// Universally safe build and IDE directories (default)
     All,
@@ -36,6 +38,15 @@ pub enum ProjectKind {
     Php,
     /// Ruby projects (.bundle, tmp, etc.)
     Ruby,
+    /// Monorepo build-system caches (Nx, Turborepo, Bazel, etc.)
+    Monorepo,
+    /// OCaml projects (_build, _opam, etc.)
+    #[value(name = "ocaml")]
+    OCaml,
+    /// Erlang projects (_build, ebin, .rebar3, etc.)
+    Erlang,
+    /// Stray tool and OS files (.DS_Store, npm-debug.log, *.pyc, etc.); implies --match-files
+    Cruft,
 }
 
 impl fmt::Display for ProjectKind {
@@ -52,11 +63,72 @@ impl fmt::Display for ProjectKind {
             ProjectKind::Cpp => "cpp",
             ProjectKind::Php => "php",
             ProjectKind::Ruby => "ruby",
+            ProjectKind::Monorepo => "monorepo",
+            ProjectKind::OCaml => "ocaml",
+            ProjectKind::Erlang => "erlang",
+            ProjectKind::Cruft => "cruft",
         };
         write!(f, "{}", s)
     }
 }
 
+/// Unit system used when printing sizes.
+#[derive(ValueEnum, Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    /// Powers of 1024, printed as KiB, MiB, ... (1 KiB = 1024 bytes)
+    #[default]
+    Binary,
+    /// Powers of 1000, printed as KB, MB, ... (1 KB = 1000 bytes)
+    Si,
+}
+
+/// Number formatting of the counts in the summary (--summary-locale).
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Default)]
+pub enum SummaryLocale {
+    /// Comma thousands separator: 1,234,567 (default)
+    #[default]
+    En,
+    /// Dot thousands separator: 1.234.567
+    De,
+    /// Narrow no-break space thousands separator: 1 234 567
+    Fr,
+    /// No separator: 1234567
+    Plain,
+}
+
+/// What --move-to does when the destination already exists, e.g. from a previous run.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Default)]
+pub enum OnConflict {
+    /// Replace the existing destination
+    Overwrite,
+    /// Leave the source in place and warn
+    Skip,
+    /// Move to the destination with a timestamp suffix appended (default)
+    #[default]
+    Rename,
+}
+
+/// Shells supported by --generate-completions.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl From<Shell> for clap_complete::Shell {
+    fn from(shell: Shell) -> Self {
+        match shell {
+            Shell::Bash => clap_complete::Shell::Bash,
+            Shell::Zsh => clap_complete::Shell::Zsh,
+            Shell::Fish => clap_complete::Shell::Fish,
+            Shell::Powershell => clap_complete::Shell::PowerShell,
+        }
+    }
+}
+
 /// Command-line arguments for the Cleaner CLI tool.
 ///
 /// Uses `clap` for parsing and help generation.
@@ -66,32 +138,82 @@ impl fmt::Display for ProjectKind {
     about = "Cleaner: Fast, safe, and flexible build directory cleaner.",
     long_about = "Cleaner v0.3.0\n\
 Fast, safe, and flexible build directory cleaner for Rust, Python, Node, Java, and more.\n\
-Usage: cleaner <PATH> [OPTIONS]\n\
+Usage: cleaner <PATH>... [OPTIONS]\n\
 For more info, see https://github.com/yarenty/cleaner",
-    override_usage = "cleaner <PATH> [OPTIONS]\n\nOptions: --dry-run, --force, --exclude, --max-depth, --config, --log-file, --ci, ..."
+    override_usage = "cleaner <PATH>... [OPTIONS]\n\nOptions: --dry-run, --force, --exclude, --max-depth, --config, --log-file, --ci, ..."
 )]
 pub struct Args {
-    /// The root directory to start cleaning from. All subdirectories will be searched recursively.
+    /// The root directories to start cleaning from. All subdirectories will be searched recursively.
     /// Example: /home/user/projects or .
-    #[clap(value_parser)]
-    pub path: String,
+    #[clap(
+        value_parser,
+        value_name = "PATH",
+        required_unless_present_any = ["list_kinds", "generate_completions", "clear_cache", "roots_stdin", "print_config"]
+    )]
+    pub paths: Vec<String>,
+
+    /// Read further root directories from stdin, one per line, e.g. from `find`. Blank lines,
+    /// duplicates and paths that are not directories are skipped. Since stdin is taken, combine
+    /// it with --force, --dry-run or --ci.
+    /// Example: find ~ -name Cargo.toml -printf '%h\n' | cleaner --roots-stdin --force
+    #[clap(long, action, conflicts_with_all = ["review", "confirm_per_project_with_sizes"])]
+    pub roots_stdin: bool,
 
     /// Comma-separated list of directory names to clean. If not provided, uses defaults for the selected kind(s).
+    /// `@name` stands for the patterns of alias `name` in the config's `[aliases]` section, and
+    /// brace groups expand to one pattern per alternative (`{target,build}-cache`).
     /// Example: --dirs target,out,build,node_modules
     #[clap(short, long)]
     pub dirs: Option<String>,
 
-    /// Comma-separated list of directory names or patterns to exclude from cleaning.
+    /// Also clean directories whose name ends with SUFFIX, without glob syntax. Repeatable and
+    /// combined with --dirs; on its own it replaces the kind's default directories.
+    /// Example: --dirs-suffix -cache
+    #[clap(
+        long,
+        value_name = "SUFFIX",
+        allow_hyphen_values = true,
+        conflicts_with = "literal"
+    )]
+    pub dirs_suffix: Vec<String>,
+
+    /// Also clean directories whose name starts with PREFIX, without glob syntax. Repeatable and
+    /// combined with --dirs; on its own it replaces the kind's default directories.
+    /// Example: --dirs-prefix tmp
+    #[clap(
+        long,
+        value_name = "PREFIX",
+        allow_hyphen_values = true,
+        conflicts_with = "literal"
+    )]
+    pub dirs_prefix: Vec<String>,
+
+    /// Comma-separated list of directory names or patterns to exclude from cleaning. Brace groups
+    /// expand as for --dirs.
     /// Example: --exclude .git,docs
     #[clap(short, long)]
     pub exclude: Option<String>,
 
-    /// Project type/kind to target for cleaning. Supported values: all (default), ide, rust, python, java, node, go, csharp, cpp, php, ruby.
-    /// If not specified, only universally safe build and IDE directories will be cleaned.
+    /// Project type/kind to target for cleaning. Supported values: all (default), ide, rust, python, java, node, go, csharp, cpp, php, ruby, monorepo, ocaml, erlang, cruft.
+    /// If not specified, the config's `[general] default_kind` is used, falling back to only universally safe build and IDE directories.
     /// Example: --kind python
-    #[clap(short, long, value_enum, default_value = "all")]
+    #[clap(short, long, value_enum)]
     pub kind: Option<ProjectKind>,
 
+    /// Clean the default directories of every built-in kind at once (rust, python, node, ...),
+    /// not just the universally safe ones of `all`. Meant for shared machines with mixed projects;
+    /// review with --dry-run first.
+    /// Example: --all-kinds
+    #[clap(long, action, conflicts_with = "kind")]
+    pub all_kinds: bool,
+
+    /// With --all-kinds, leave the directories of these kinds alone, e.g. while a long
+    /// `npm install` is in progress. Directories shared with another kind (like `build`) are
+    /// left alone as well.
+    /// Example: --all-kinds --except-kind node
+    #[clap(long, value_enum, requires = "all_kinds", value_delimiter = ',')]
+    pub except_kind: Vec<ProjectKind>,
+
     /// Skip confirmation prompt and force deletion of directories.
     /// Example: --force
     #[clap(short, long, action)]
@@ -102,16 +224,351 @@ pub struct Args {
     #[clap(short = 'n', long, action)]
     pub dry_run: bool,
 
-    /// Prompt for confirmation before deleting each directory.
+    /// Instead of y/N, require typing WORD exactly (case-sensitive) to confirm the deletion.
+    /// Anything else aborts the run.
+    /// Example: --confirm-phrase DELETE
+    #[clap(long, value_name = "WORD")]
+    pub confirm_phrase: Option<String>,
+
+    /// Require typing `DELETE` to confirm once more than N entries match, even without
+    /// --confirm-phrase.
+    /// Example: --confirm-phrase-above 20
+    #[clap(long, value_name = "N", default_value_t = 100)]
+    pub confirm_phrase_above: usize,
+
+    /// Prompt for confirmation before deleting each directory, showing its size. Answering `a`
+    /// (all) removes it and every remaining match without further prompts.
     /// Example: --interactive
     #[clap(short, long, action)]
     pub interactive: bool,
 
+    /// Abort on the first directory that cannot be removed instead of continuing (best-effort).
+    /// Example: --fail-fast
+    #[clap(long, action)]
+    pub fail_fast: bool,
+
+    /// With several roots, a root that cannot be locked or scanned (missing, unreadable, in use
+    /// by another run) no longer stops the others: its error is reported in the summary, and
+    /// the run only fails if every root failed.
+    /// Example: --keep-going-across-roots ~/src /mnt/usb/src
+    #[clap(long, action, conflicts_with = "fail_fast")]
+    pub keep_going_across_roots: bool,
+
+    /// Name of the sentinel file that protects a matched directory: any matched directory
+    /// containing a file with this name is skipped.
+    /// Example: --keep-file .do-not-clean
+    #[clap(long, default_value = ".cleaner-keep")]
+    pub keep_file: String,
+
+    /// Do not descend into git submodules: paths listed in `.gitmodules` files are left out of
+    /// the scan, so build output inside them is not touched.
+    /// Example: --skip-submodules
+    #[clap(long, action)]
+    pub skip_submodules: bool,
+
+    /// Stay on the filesystem of each root, like `find -xdev` or `du -x`: directories where
+    /// another filesystem is mounted (network shares, USB drives) are neither scanned nor
+    /// removed. Device IDs are only available on Unix; elsewhere a warning is logged and the
+    /// whole tree is scanned.
+    /// Example: --one-file-system
+    #[clap(long, visible_alias = "no-cross-device-traversal", action)]
+    pub one_file_system: bool,
+
+    /// Skip a matched directory if it directly contains a file or directory named NAME, e.g. a
+    /// `build` holding a `package.json` is a project, not build output. Repeatable.
+    /// Example: --exclude-if-contains package.json --exclude-if-contains .keep
+    #[clap(long, value_name = "NAME")]
+    pub exclude_if_contains: Vec<String>,
+
+    /// Only remove a matched directory if it directly contains a marker named NAME, such as the
+    /// standard `CACHEDIR.TAG` of cache directories (which must carry its signature). Matches
+    /// without a marker are skipped. Repeatable: any one of the markers is enough.
+    /// Example: --dirs cache --require-marker CACHEDIR.TAG
+    #[clap(long, value_name = "NAME")]
+    pub require_marker: Vec<String>,
+
+    /// Instead of prompting, write every directory that would be removed (with its size) to FILE
+    /// and wait for Enter. Delete lines for anything you want to keep; only paths still listed
+    /// when you continue are removed.
+    /// Example: --review /tmp/cleaner-review.txt
+    #[clap(long, value_name = "FILE", conflicts_with_all = ["interactive", "only_empty", "find_duplicates"])]
+    pub review: Option<String>,
+
+    /// For GUI frontends: instead of prompting, print one JSON line listing the matches grouped by
+    /// project with sizes, then read one JSON line `{"delete": [<group ids>]}` from stdin. Only
+    /// the listed groups are removed.
+    /// Example: --confirm-per-project-with-sizes
+    #[clap(long, action, conflicts_with_all = ["interactive", "review", "only_empty", "find_duplicates"])]
+    pub confirm_per_project_with_sizes: bool,
+
+    /// Treat each path as a Cargo workspace root and clean the selected directories (--dirs or
+    /// --kind) inside every workspace member listed in its Cargo.toml, e.g. per-crate `doc` or
+    /// `criterion` output that escapes the shared target directory.
+    /// Example: --workspace-members --dirs doc,criterion
+    #[clap(long, action)]
+    pub workspace_members: bool,
+
+    /// After removing a directory, also remove its parent directories that are left empty,
+    /// walking upwards until a non-empty one or the scan root is reached.
+    /// Example: --prune-empty-parents
+    #[clap(long, action)]
+    pub prune_empty_parents: bool,
+
+    /// Also clean inside shared package-manager caches (~/.cargo, ~/.npm, ~/.m2, ~/.gradle, ...)
+    /// and the `[general] always_exclude` directories, which are skipped by default.
+    /// Example: --allow-package-caches
+    #[clap(long, action)]
+    pub allow_package_caches: bool,
+
+    /// Move matched directories below DIR instead of deleting them, keeping their path relative
+    /// to the scanned root, so they can be reviewed and deleted by hand later. DIR must be on the
+    /// same filesystem as the roots unless --allow-cross-device is given.
+    /// Example: --move-to /mnt/scratch/cleaner-staging
+    #[clap(long, value_name = "DIR")]
+    pub move_to: Option<String>,
+
+    /// Before removing each target, archive it as a `.tar.gz` below DIR, keeping its path
+    /// relative to the scanned root (`target` becomes `DIR/<path>/target.tar.gz`). A target
+    /// whose archive cannot be written is left in place and reported as a failure. Archives are
+    /// written with the system `tar`, and extracting one in the target's parent restores it.
+    /// Example: --archive-to ~/archives/builds
+    #[clap(long, value_name = "DIR", conflicts_with_all = ["move_to", "no_op_delete"])]
+    pub archive_to: Option<String>,
+
+    /// How counts are written in the summary: with the thousands separator of `en` (1,234,567,
+    /// default), `de` (1.234.567) or `fr` (1 234 567), or `plain` without one. The --ci JSON
+    /// always has plain numbers.
+    /// Example: --summary-locale de
+    #[clap(long, value_enum, default_value_t = SummaryLocale::En, value_name = "LOCALE")]
+    pub summary_locale: SummaryLocale,
+
+    /// Transfer each target all or nothing with --move-to or --archive-to. A move onto another
+    /// filesystem is copied to a staging path next to the destination and renamed into place
+    /// once complete, and archives are likewise only renamed into place once written; the
+    /// original is then renamed aside before it is deleted. A transfer that fails midway leaves
+    /// the original intact and nothing at the destination.
+    /// Example: --move-to /mnt/usb/staging --allow-cross-device --atomic
+    #[clap(long, action)]
+    pub atomic: bool,
+
+    /// Allow --move-to onto another filesystem, where each directory is copied and then deleted
+    /// (much slower than a rename). A warning with the amount to copy is still logged.
+    /// Example: --move-to /mnt/usb/staging --allow-cross-device
+    #[clap(long, action, requires = "move_to")]
+    pub allow_cross_device: bool,
+
+    /// What to do when a --move-to destination already exists: overwrite it, skip the source,
+    /// or rename (append a timestamp suffix, default).
+    /// Example: --on-conflict skip
+    #[clap(long, value_enum, default_value_t = OnConflict::Rename, requires = "move_to")]
+    pub on_conflict: OnConflict,
+
+    /// Match directory and exclude patterns ignoring case. This is the default on Windows and
+    /// macOS, whose filesystems ignore case; on Linux matching is case-sensitive by default.
+    /// Overrides `case_insensitive` in the config.
+    /// Example: --ignore-case
+    #[clap(long, action, conflicts_with = "case_sensitive")]
+    pub ignore_case: bool,
+
+    /// Match directory and exclude patterns case-sensitively, also on Windows and macOS.
+    /// Overrides `case_insensitive` in the config.
+    /// Example: --case-sensitive
+    #[clap(long, action)]
+    pub case_sensitive: bool,
+
+    /// Treat --dirs and --exclude entries as exact directory names instead of glob patterns, for
+    /// names containing `[`, `?` or `*`.
+    /// Example: --literal --dirs 'build[1]'
+    #[clap(long, action)]
+    pub literal: bool,
+
+    /// Let --dirs and --kind patterns match files as well as directories, e.g. the `*.o`,
+    /// `*.iml` or `Makefile` entries of the cpp and ide kinds. Matched files are removed one by
+    /// one, directories recursively. Off by default, as file names like `Makefile` are often
+    /// source rather than build output.
+    /// Example: --match-files --dirs 'build,*.o'
+    #[clap(long, visible_alias = "dirs-match-files-too", action)]
+    pub match_files: bool,
+
+    /// Keep the N most recently modified subdirectories of each matched directory and remove
+    /// the rest, instead of removing the matched directory itself. Useful for caches made of
+    /// hash- or timestamp-named entries.
+    /// Example: --dirs build/cache --keep-recent 2
+    #[clap(long, value_name = "N")]
+    pub keep_recent: Option<usize>,
+
+    /// Instead of removing each matched directory, remove only the files inside it that were
+    /// last modified longer ago than DURATION, keeping the directory and recent files, so
+    /// incremental builds and compiler caches stay warm.
+    /// Example: --dirs target --prune-contents-older-than 14d
+    #[clap(long, value_name = "DURATION", conflicts_with = "keep_recent")]
+    pub prune_contents_older_than: Option<String>,
+
+    /// When several paths are given (or read with --roots-stdin), clean them concurrently (e.g. on independent filesystems)
+    /// instead of one after another. Ignored in interactive mode.
+    /// Example: --parallel-roots
+    #[clap(long, action)]
+    pub parallel_roots: bool,
+
+    /// Skip directories containing files currently held open by a running process (e.g. a dev
+    /// server or IDE indexer). Supported on Linux and macOS; ignored elsewhere.
+    /// Example: --skip-in-use
+    #[clap(long, action)]
+    pub skip_in_use: bool,
+
+    /// Read every file of each match before removing it, and skip (with a warning) matches that
+    /// cannot be read completely, rather than deleting a partially damaged directory. Slow: all
+    /// contents are read.
+    /// Example: --verify-readable
+    #[clap(long, action)]
+    pub verify_readable: bool,
+
+    /// For Rust projects, ask `cargo metadata` for the real target directory (honouring
+    /// CARGO_TARGET_DIR and `[build] target-dir`) and clean that instead of any directory named
+    /// `target`. Falls back to name matching when cargo is not available.
+    /// Example: --respect-cargo-metadata
+    #[clap(long, action)]
+    pub respect_cargo_metadata: bool,
+
+    /// Ignore the directory names and kind, and instead remove every directory that contains no
+    /// files (recursively), deepest first. The root itself is never removed.
+    /// Example: --only-empty
+    #[clap(long, action)]
+    pub only_empty: bool,
+
+    /// Report groups of matched directories with identical content and the space deduplicating
+    /// them would save. Read-only: nothing is deleted.
+    /// Example: --find-duplicates
+    #[clap(long, action)]
+    pub find_duplicates: bool,
+
+    /// Process and report in a deterministic order: directories are walked sorted by name and
+    /// failures are listed by path, so the output of two runs can be diffed. Deletion itself
+    /// stays parallel.
+    /// Example: --sorted
+    #[clap(long, action)]
+    pub sorted: bool,
+
+    /// In a dry run, list at most N matches per root, then summarize the rest as
+    /// "... and M more (total SIZE)". 0 means unlimited.
+    /// Example: --dry-run --preview-limit 50
+    #[clap(long, value_name = "N", default_value_t = 0)]
+    pub preview_limit: usize,
+
+    /// Report how much space the matches take, bucketed by age since last modification (< 1 week,
+    /// 1-4 weeks, > 4 weeks), as a text bar chart (JSON with --ci). Read-only: nothing is deleted.
+    /// Example: --age-histogram
+    #[clap(long, action, conflicts_with_all = ["find_duplicates", "only_empty", "review"])]
+    pub age_histogram: bool,
+
+    /// Report how many matches there are at each depth below the root ("depth 2: 40"), to help
+    /// pick a --max-depth. JSON with --ci. Read-only: nothing is deleted.
+    /// Example: --depth-report
+    #[clap(long, action, conflicts_with_all = ["find_duplicates", "only_empty", "review", "age_histogram"])]
+    pub depth_report: bool,
+
+    /// Write the matched paths to FILE, one per line, instead of deleting anything (`-` for
+    /// stdout), e.g. to feed `xargs` or another tool. Honours --relative.
+    /// Example: --list-to matches.txt
+    #[clap(long, value_name = "FILE", conflicts_with_all = ["find_duplicates", "only_empty", "review", "age_histogram", "depth_report"])]
+    pub list_to: Option<String>,
+
     /// Enable CI/CD mode: suppress prompts, force deletion, and output JSON summary.
     /// Example: --ci
     #[clap(long, action)]
     pub ci: bool,
 
+    /// Print, for every directory visited, why it was selected or skipped (matched pattern,
+    /// excluded by, symlink, recently modified, in use, ...).
+    /// Example: --explain
+    #[clap(long, action)]
+    pub explain: bool,
+
+    /// Print paths relative to the scan root instead of in full.
+    /// Example: --relative
+    #[clap(long, action)]
+    pub relative: bool,
+
+    /// Print every supported project kind and the directories it cleans, then exit.
+    /// Kinds defined in the config file (--config) are listed too.
+    /// Example: --list-kinds
+    #[clap(long, action)]
+    pub list_kinds: bool,
+
+    /// Print the effective configuration after merging the command line, the config file and
+    /// the defaults (directory patterns, excludes, depth, main switches), with where each setting
+    /// came from, then exit without cleaning. TOML by default, JSON with --ci.
+    /// Example: --config cleaner.toml --dirs target --print-config
+    #[clap(long, visible_alias = "config-print-effective", action)]
+    pub print_config: bool,
+
+    /// POST a JSON summary (directories, bytes, errors, duration) to URL when the run ends, e.g. a
+    /// Slack or Discord incoming webhook. https URLs are sent through `curl`.
+    /// Example: --webhook https://hooks.slack.com/services/T000/B000/XXXX
+    #[clap(long, value_name = "URL")]
+    pub webhook: Option<String>,
+
+    /// Run with the lowest CPU niceness and, on Linux, the idle I/O class (like `nice -n 19
+    /// ionice -c 3`), so a background cleanup does not slow down foreground work.
+    /// Example: --low-priority
+    #[clap(long, action)]
+    pub low_priority: bool,
+
+    /// Show a desktop notification with the number of directories removed and the space freed
+    /// when the run ends (or a failure notification if some could not be removed). Does
+    /// nothing on headless systems.
+    /// Example: --notify
+    #[clap(long, action)]
+    pub notify: bool,
+
+    /// After the run, write Prometheus metrics (directories removed, bytes freed, errors,
+    /// duration) to FILE, e.g. for node-exporter's textfile collector.
+    /// Example: --metrics-file /var/lib/node_exporter/textfile/cleaner.prom
+    #[clap(long, value_name = "FILE")]
+    pub metrics_file: Option<String>,
+
+    /// After the run, write a Markdown summary (a table of space freed per project, plus totals)
+    /// to FILE, for pasting into a PR or chat.
+    /// Example: --report-md cleanup.md
+    #[clap(long, value_name = "FILE")]
+    pub report_md: Option<String>,
+
+    /// Cache recursive directory sizes between runs (in `$XDG_DATA_HOME/cleaner/sizes.json`).
+    /// A cached size is reused while the directory's modification time is unchanged, which
+    /// speeds up repeated --dry-run scans of large trees. Only the top-level mtime is checked:
+    /// files that change or grow in nested subdirectories (`target/debug/deps/...`) leave it
+    /// unchanged, so reported sizes can be stale. --free-target never uses cached sizes.
+    /// Example: --cache
+    #[clap(long, action)]
+    pub cache: bool,
+
+    /// Append every removed path to FILE as soon as it is removed, so an interrupted cleanup can
+    /// be continued with --resume. Without --resume the file is started afresh.
+    /// Example: --checkpoint /tmp/cleanup.checkpoint
+    #[clap(long, value_name = "FILE")]
+    pub checkpoint: Option<String>,
+
+    /// Skip the targets already listed in the --checkpoint file by an earlier, interrupted run.
+    /// Example: --checkpoint /tmp/cleanup.checkpoint --resume
+    #[clap(long, action, requires = "checkpoint")]
+    pub resume: bool,
+
+    /// Delete the size cache used by --cache. Without PATH arguments, exit afterwards.
+    /// Example: --clear-cache
+    #[clap(long, action)]
+    pub clear_cache: bool,
+
+    /// Print a shell completion script for SHELL to stdout, then exit.
+    /// Example: --generate-completions bash > /etc/bash_completion.d/cleaner
+    #[clap(long, value_name = "SHELL", value_enum, hide = true)]
+    pub generate_completions: Option<Shell>,
+
+    /// Unit system for printed sizes: binary (1024, printed as KiB, MiB, ..., default) or si
+    /// (1000, printed as KB, MB, ...). Overrides `[general] units` in the config.
+    /// Example: --units si
+    #[clap(long, value_enum)]
+    pub units: Option<Units>,
+
     /// Set custom log level for output verbosity. Supported: info, debug, trace.
     /// Example: --log debug
     #[clap(short, long, default_value = "info")]
@@ -122,13 +579,178 @@ pub struct Args {
     #[clap(long)]
     pub log_file: Option<String>,
 
+    /// Protect directories modified within this duration (likely an active build); only older
+    /// ones are cleaned. Accepts durations such as 12h, 3days or 2weeks.
+    /// Example: --exclude-newer-than 7days
+    #[clap(long)]
+    pub exclude_newer_than: Option<String>,
+
     /// Maximum recursion depth for directory search. 0 means unlimited.
     /// Example: --max-depth 2
     #[clap(long, default_value = "0")]
     pub max_depth: usize,
 
-    /// Path to a custom config file (TOML) for directory and exclusion settings.
+    /// Only clean matches at most this many levels below their nearest project root (a directory
+    /// with a Cargo.toml, package.json, pyproject.toml, ... marker), however deep that project
+    /// sits. Matches outside any project are left alone.
+    /// Example: --project-relative-depth 2
+    #[clap(long)]
+    pub project_relative_depth: Option<usize>,
+
+    /// Narrow the directories to clean to those of each project's detected kind: a match is only
+    /// removed if its pattern is a default directory of the kind detected for its nearest project
+    /// root (Cargo.toml means rust, package.json node, ...). Matches outside any project are left
+    /// alone.
+    /// Example: --kind all --detected-only
+    #[clap(long, action)]
+    pub detected_only: bool,
+
+    /// Attribute every match to its nearest enclosing project root (a directory with a
+    /// Cargo.toml, package.json, ... or `.git`) instead of the directory containing it. Used to
+    /// group the --report-md table and the JSON confirmation, and as the directory post_command
+    /// runs in.
+    /// Example: --project-boundaries --report-md cleanup.md
+    #[clap(long, action)]
+    pub project_boundaries: bool,
+
+    /// Measure the free space of the roots' filesystems before and after removing, and
+    /// report it next to the computed size of what was removed, warning when they differ a lot
+    /// (hardlinks, sparse files, block rounding or other writers).
+    /// Example: --force --audit
+    #[clap(long, action, conflicts_with = "dry_run")]
+    pub audit: bool,
+
+    /// Pick the number of worker threads for the storage of the first root: few for spinning
+    /// disks and network mounts, more for SSDs, one per CPU when it cannot be told. An explicit
+    /// RAYON_NUM_THREADS still wins.
+    /// Example: --auto-concurrency
+    #[clap(long, action)]
+    pub auto_concurrency: bool,
+
+    /// Maximum number of file descriptors to keep open at once. Bounds parallel scanning and
+    /// deletion on systems with a low `ulimit -n`. 0 means unlimited.
+    /// Example: --max-open-fds 256
+    #[clap(long, default_value = "0")]
+    pub max_open_fds: usize,
+
+    /// Testing hook: sleep this many milliseconds before each removal, so concurrency limits,
+    /// --max-runtime and cancellation can be observed without huge fixtures.
+    /// Example: --simulate-delete-latency 200
+    #[clap(long, value_name = "MS", hide = true)]
+    pub simulate_delete_latency: Option<u64>,
+
+    /// Testing hook: pretend the local time is HH:MM when checking --only-between.
+    /// Example: --clock 03:00
+    #[clap(long, value_name = "HH:MM", hide = true)]
+    pub clock: Option<String>,
+
+    /// Benchmark hook: do everything a real run does (scan, size, iterate the targets) except
+    /// the removal itself, to measure scan and sizing overhead on real trees.
+    /// Example: --no-op-delete
+    #[clap(long, action, hide = true, conflicts_with_all = ["dry_run", "only_empty"])]
+    pub no_op_delete: bool,
+
+    /// Only delete while the local time is inside this daily window; outside it the run is a
+    /// dry run that only reports. The window may span midnight (`22:00-06:00`).
+    /// Example: --only-between 02:00-05:00
+    #[clap(long, value_name = "HH:MM-HH:MM")]
+    pub only_between: Option<String>,
+
+    /// Number of times to retry a removal that failed with a transient I/O error (e.g. EIO or
+    /// EBUSY on NFS/SMB mounts), with exponential backoff. Permanent errors are not retried.
+    /// Example: --retries 5
+    #[clap(long, default_value = "2")]
+    pub retries: u32,
+
+    /// Write every match, relative to its root and with its size, to FILE as a JSON baseline
+    /// for a later --baseline comparison, instead of deleting anything.
+    /// Example: --write-baseline baseline.json
+    #[clap(long, value_name = "FILE", conflicts_with_all = ["baseline", "only_empty", "find_duplicates", "review", "list_to"])]
+    pub write_baseline: Option<String>,
+
+    /// Compare the matches with a baseline written by --write-baseline and report which are new
+    /// and which recur, instead of deleting anything.
+    /// Example: --baseline baseline.json
+    #[clap(long, value_name = "FILE", conflicts_with_all = ["only_empty", "find_duplicates", "review", "list_to"])]
+    pub baseline: Option<String>,
+
+    /// With --baseline, exit with an error when the new matches add up to more than SIZE, e.g.
+    /// to catch build artifacts committed to a repository.
+    /// Example: --baseline baseline.json --fail-if-new 10MB
+    #[clap(long, value_name = "SIZE", value_parser = crate::utils::parse_size, requires = "baseline")]
+    pub fail_if_new: Option<u64>,
+
+    /// Stop starting new deletions once this much time has passed since startup, and report
+    /// partial progress. Accepts durations such as 90s, 5m or 1h.
+    /// Example: --max-runtime 5m
+    #[clap(long)]
+    pub max_runtime: Option<String>,
+
+    /// Remove matches largest first, only until the filesystem of the first root has at least
+    /// SIZE free (or nothing is left), then report whether the goal was met. Matches on other
+    /// filesystems are left alone, as removing them frees nothing there.
+    /// Example: --free-target 50GB
+    #[clap(long, value_name = "SIZE", value_parser = crate::utils::parse_size)]
+    pub free_target: Option<u64>,
+
+    /// In a dry run, exit with an error if anything matched, e.g. to gate CI on artifacts
+    /// having been cleaned already.
+    /// Example: --dry-run --exit-nonzero-if-found
+    #[clap(long, action, requires = "dry_run")]
+    pub exit_nonzero_if_found: bool,
+
+    /// If another cleaner run is already cleaning one of the roots, wait for it to finish instead
+    /// of exiting with an "already running" error. Locks are per user: runs by different users
+    /// (e.g. a root cron job and a user's) on the same tree do not see each other.
+    /// Example: --wait
+    #[clap(long, action)]
+    pub wait: bool,
+
+    /// Exit with an error if nothing matched, to catch pipelines that no longer produce the
+    /// expected build output.
+    /// Example: --fail-on-nothing
+    #[clap(long, action)]
+    pub fail_on_nothing: bool,
+
+    /// Print no summary when less than SIZE was freed (e.g. nothing found), to keep cron mail
+    /// quiet. Larger results and errors are still reported. Sizes read like they are printed:
+    /// `KB`, `MB`, `GB` are powers of 1000 (as with --units si) and `KiB`, `MiB`, `GiB` (or `K`,
+    /// `M`, `G`) powers of 1024 (as with the default binary units), so a size copied from a
+    /// summary means the same amount here.
+    /// Example: --quiet-below 100MB
+    #[clap(long, value_name = "SIZE", value_parser = crate::utils::parse_size)]
+    pub quiet_below: Option<u64>,
+
+    /// Path to a custom config file (TOML, or JSON if the name ends in `.json`) for directory and
+    /// exclusion settings.
     /// Example: --config cleaner.toml
     #[clap(long)]
     pub config: Option<String>,
+
+    /// Also clean the directories listed in `.gitignore` and `.dockerignore` files under the
+    /// roots that look like build output: entries ending in `/` (such as `out/` or
+    /// `generated/`) and names of known build directories.
+    /// Example: --infer-from-ignores --dry-run
+    #[clap(long, action)]
+    pub infer_from_ignores: bool,
+
+    /// Only clean dependency directories (`node_modules`, `.venv`, `venv`, `env`) whose lockfile
+    /// (`package-lock.json`, `yarn.lock`, `poetry.lock`, ...) was modified after them: such
+    /// installs are out of date and will be reinstalled anyway. Every other match is skipped.
+    /// Example: --stale-deps-only
+    #[clap(long, action)]
+    pub stale_deps_only: bool,
+
+    /// Break the summary down by language: each removed entry is attributed to the kind whose
+    /// directory name it matched, with the nearest project's marker files settling names shared
+    /// by several kinds (such as `target` or `build`).
+    /// Example: --group-by-language
+    #[clap(long, action)]
+    pub group_by_language: bool,
+
+    /// Fail on config mistakes, such as a `[kinds.X]` section for an unknown kind, instead of
+    /// warning about them.
+    /// Example: --config cleaner.toml --strict
+    #[clap(long, action)]
+    pub strict: bool,
 }
@@ -0,0 +1,138 @@
+//! Error types for the Cleaner project.
+//!
+//! `CleanerError` describes every failure the cleaning logic can report, each variant carrying
+//! enough context (paths, patterns, underlying causes) to be handled programmatically.
+//! The binary renders them through `color_eyre`.
+
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Errors returned by the cleaning functions.
+#[derive(Debug, Error)]
+pub enum CleanerError {
+    /// The root path to clean does not exist.
+    #[error("path not found: {}", .0.display())]
+    PathNotFound(PathBuf),
+
+    /// The config file could not be read.
+    #[error("failed to read config {}: {source}", .path.display())]
+    ConfigRead {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The config file is not valid TOML or does not match the expected structure.
+    #[error("failed to parse config {}: {message}", .path.display())]
+    ConfigParse { path: PathBuf, message: String },
+
+    /// A Cargo manifest could not be read or is missing required sections.
+    #[error("invalid cargo manifest {}: {message}", .path.display())]
+    Manifest { path: PathBuf, message: String },
+
+    /// A directory could not be removed.
+    #[error("failed to remove {}: {source}", .path.display())]
+    Removal {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// A target could not be archived with --archive-to, so it was left in place.
+    #[error("failed to archive {} to {}: {message}", .path.display(), .destination.display())]
+    Archive {
+        path: PathBuf,
+        destination: PathBuf,
+        message: String,
+    },
+
+    /// A directory could not be moved to the --move-to staging directory.
+    #[error("failed to move {} to {}: {source}", .path.display(), .destination.display())]
+    Move {
+        path: PathBuf,
+        destination: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The --review preview file could not be written or read back.
+    #[error("failed to access review file {}: {source}", .path.display())]
+    ReviewFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The decision read for --confirm-per-project-with-sizes is not valid JSON.
+    #[error("invalid confirmation decision: {message}")]
+    InvalidDecision { message: String },
+
+    /// The --metrics-file could not be written.
+    #[error("failed to write metrics file {}: {source}", .path.display())]
+    MetricsFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The --checkpoint file could not be read or appended to.
+    #[error("failed to access checkpoint {}: {source}", .path.display())]
+    Checkpoint {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The --report-md file could not be written.
+    #[error("failed to write report {}: {source}", .path.display())]
+    ReportFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The --baseline file could not be read or is not a baseline written by --write-baseline.
+    #[error("invalid baseline {}: {message}", .path.display())]
+    Baseline { path: PathBuf, message: String },
+
+    /// Another cleaner run holds the lock for this root.
+    #[error("another cleaner run is already cleaning {} (lock {}); use --wait to wait for it", .root.display(), .lock.display())]
+    AlreadyRunning { root: PathBuf, lock: PathBuf },
+
+    /// The per-root lock file could not be created or locked.
+    #[error("failed to lock {}: {source}", .path.display())]
+    Lock {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// A duration option (e.g. --max-runtime) could not be parsed.
+    #[error("invalid duration '{value}': {message}")]
+    InvalidDuration { value: String, message: String },
+
+    /// An --only-between window (or a time of day) could not be parsed.
+    #[error("invalid time window '{value}': {message}")]
+    InvalidTimeWindow { value: String, message: String },
+
+    /// A size option (e.g. --quiet-below) could not be parsed.
+    #[error("invalid size '{value}': {message}")]
+    InvalidSize { value: String, message: String },
+
+    /// `--dirs @name` names an alias the config does not define.
+    #[error("unknown alias '@{name}': define it in the config's [aliases] section")]
+    UnknownAlias { name: String },
+
+    /// A `[kinds.X]` section of the config names no known project kind (with --strict).
+    #[error("unknown kind '{name}' in config {}: expected one of {known}", .path.display())]
+    UnknownKind {
+        path: PathBuf,
+        name: String,
+        known: String,
+    },
+
+    /// A directory or exclude pattern is not a valid glob.
+    #[error("invalid pattern '{pattern}': {message}")]
+    InvalidPattern { pattern: String, message: String },
+}
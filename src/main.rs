@@ -10,282 +10,4738 @@
 //! - Recursively walk the directory tree and remove matching directories
 //! - Log all actions and errors
 //!
-//! Supports cleaning for Rust, Python, Java, Node.js, Go, C#, C++, PHP, Ruby, and common IDEs.
+//! Supports cleaning for Rust, Python, Java, Node.js, Go, C#, C++, PHP, Ruby, OCaml, Erlang, common IDEs, and stray tool files.
 
 mod args;
+mod error;
+mod platform;
 mod utils;
-use clap::Parser;
-use color_eyre::eyre::Result;
-use glob::Pattern;
-use log::info;
+mod webhook;
+use chrono::Local;
+use clap::{CommandFactory, Parser};
+use color_eyre::eyre::{eyre, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use glob::{MatchOptions, Pattern};
+use log::{debug, error, info, warn};
 use rayon::prelude::*;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::fs;
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 use walkdir::WalkDir;
 
-use crate::args::Args;
-use crate::utils::{default_dirs_for_kind, setup_logger};
+use crate::args::{Args, OnConflict, ProjectKind, SummaryLocale, Units};
+use crate::error::CleanerError;
+use crate::utils::{
+    all_kinds, available_space, cargo_target_dir, default_dirs_for_kind, detect_project_kind,
+    device_id, dir_fingerprint, dir_size, dirs_for_kinds, dirs_from_ignore_files, expand_braces,
+    fd_limiter, first_unreadable, format_count, format_size, git_submodules, has_marker,
+    language_of, lock_root, move_dir, move_dir_atomic, parse_duration, parse_time_of_day,
+    protected_roots, remove_aside, retry_with_backoff, same_device, setup_logger, sibling_path,
+    size_cache_path, split_patterns, stale_lockfile, timestamped_path, workspace_members,
+    Checkpoint, SizeCache, TimeWindow,
+};
 
 #[derive(Debug, Deserialize)]
 struct Config {
-    kinds: Option<std::collections::HashMap<String, KindConfig>>,
+    general: Option<GeneralConfig>,
+    kinds: Option<HashMap<String, KindConfig>>,
     exclude: Option<ExcludeConfig>,
+    /// Named pattern groups, used on the command line as `--dirs @name`.
+    aliases: Option<HashMap<String, Vec<String>>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeneralConfig {
+    /// Kind used when neither --kind nor --dirs is given (defaults to `all`).
+    default_kind: Option<ProjectKind>,
+    /// Unit system for printed sizes (defaults to `binary`).
+    units: Option<Units>,
+    /// Match directory and exclude patterns case-insensitively (defaults to true on Windows and
+    /// macOS, false elsewhere).
+    case_insensitive: Option<bool>,
+    /// Extra directories (e.g. `~/.conan`) that are never cleaned, on top of the built-in
+    /// package-manager caches. Relative entries are relative to the config file.
+    always_exclude: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
 struct KindConfig {
-    dirs: Option<Vec<String>>,
+    dirs: Option<Vec<DirEntry>>,
+    /// Shell command run in each project directory after its matches were removed, e.g.
+    /// `npm ci` for node.
+    post_command: Option<String>,
+}
+
+/// A `dirs` entry in a kind's config: a plain pattern, or a table limiting how deep below the
+/// root the pattern may match, e.g. `{ name = "node_modules", max_depth = 2 }`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum DirEntry {
+    Name(String),
+    Scoped {
+        name: String,
+        max_depth: Option<usize>,
+    },
+}
+
+impl DirEntry {
+    fn name(&self) -> &str {
+        match self {
+            DirEntry::Name(name) | DirEntry::Scoped { name, .. } => name,
+        }
+    }
+
+    fn max_depth(&self) -> Option<usize> {
+        match self {
+            DirEntry::Name(_) => None,
+            DirEntry::Scoped { max_depth, .. } => *max_depth,
+        }
+    }
+}
+
+impl fmt::Display for DirEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max_depth() {
+            Some(depth) => write!(f, "{} (max depth {})", self.name(), depth),
+            None => write!(f, "{}", self.name()),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct ExcludeConfig {
     patterns: Option<Vec<String>>,
+    /// File with more patterns, one per line (`#` starts a comment), relative to the config file.
+    from: Option<String>,
 }
 
 #[derive(Serialize)]
 struct Summary {
     directories: usize,
+    files: usize,
     total_bytes: u64,
     total_mb: f64,
     dry_run: bool,
+    /// Set when --no-op-delete left every target in place.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    no_op_delete: bool,
+    failed: usize,
+    unfinished: usize,
+    gone: usize,
+    skipped: Skipped,
+    /// Entries and bytes per language, with --group-by-language.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    languages: BTreeMap<String, LanguageTotals>,
+    /// Roots that failed as a whole, with --keep-going-across-roots.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    root_failures: Vec<RootFailureSummary>,
+    /// Claimed and measured space freed, with --audit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audit: Option<Audit>,
+    /// Size of the archives written, with --archive-to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archived_bytes: Option<u64>,
+    /// What each directory pattern would remove, in a dry run.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    patterns: BTreeMap<String, PatternTotals>,
+    /// Duration of the removal phase; throughput is only reported for real removals.
+    removal_seconds: f64,
+    bytes_per_second: Option<f64>,
+    directories_per_second: Option<f64>,
 }
 
-/// Load config from a TOML file path, if provided.
-fn load_config(path: &str) -> Option<Config> {
-    let mut file = File::open(path).ok()?;
+/// The matches of a run, written by --write-baseline and compared against by --baseline.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Baseline {
+    entries: Vec<BaselineEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BaselineEntry {
+    /// Path relative to its scan root, so checkouts in different places compare equal.
+    path: String,
+    bytes: u64,
+}
+
+#[derive(Serialize)]
+struct RootFailureSummary {
+    root: String,
+    error: String,
+}
+
+#[derive(Clone, Copy, Serialize)]
+struct Audit {
+    claimed_bytes: u64,
+    free_before: u64,
+    free_after: u64,
+    /// Growth of the free space; negative if something else wrote more than was removed.
+    measured_bytes: i64,
+}
+
+impl Audit {
+    /// Whether the measured delta is off from the claimed size by more than 10% (and at least
+    /// 1 MiB, so small runs are not flagged for block rounding).
+    fn discrepant(&self) -> bool {
+        let difference = (self.measured_bytes as i128 - self.claimed_bytes as i128).unsigned_abs();
+        difference > (self.claimed_bytes as u128 / 10).max(1 << 20)
+    }
+}
+
+#[derive(Serialize)]
+struct LanguageTotals {
+    entries: usize,
+    bytes: u64,
+}
+
+/// Load config from a TOML file path, or from JSON if the file name ends in `.json`.
+///
+/// Relative paths inside the config (`[exclude] from`, `[general] always_exclude`) are resolved
+/// against the config file's directory rather than the working directory, and the patterns of
+/// the `from` file are appended to `[exclude] patterns`.
+fn load_config(path: &str) -> Result<Config, CleanerError> {
+    let read_error = |source| CleanerError::ConfigRead {
+        path: PathBuf::from(path),
+        source,
+    };
+    let mut file = File::open(path).map_err(read_error)?;
     let mut contents = String::new();
-    file.read_to_string(&mut contents).ok()?;
-    toml::from_str(&contents).ok()
+    file.read_to_string(&mut contents).map_err(read_error)?;
+    let is_json = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let parsed = if is_json {
+        serde_json::from_str(&contents).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(&contents).map_err(|e| e.to_string())
+    };
+    let mut config: Config = parsed.map_err(|message| CleanerError::ConfigParse {
+        path: PathBuf::from(path),
+        message,
+    })?;
+    let base = Path::new(path).parent().unwrap_or(Path::new(""));
+    let always_exclude = config
+        .general
+        .as_mut()
+        .and_then(|general| general.always_exclude.as_mut());
+    for entry in always_exclude.into_iter().flatten() {
+        if !entry.starts_with('~') && Path::new(entry.as_str()).is_relative() {
+            *entry = base.join(&entry).to_string_lossy().into_owned();
+        }
+    }
+    if let Some(exclude) = &mut config.exclude {
+        if let Some(from) = &exclude.from {
+            let from = base.join(from);
+            let listed = fs::read_to_string(&from).map_err(|source| CleanerError::ConfigRead {
+                path: from.clone(),
+                source,
+            })?;
+            let patterns = listed
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from);
+            exclude
+                .patterns
+                .get_or_insert_with(Vec::new)
+                .extend(patterns);
+        }
+    }
+    Ok(config)
+}
+
+/// The `[kinds.X]` keys of the config that name no built-in kind (such as a misspelled
+/// `[kinds.rsut]`), sorted. Their settings would never apply.
+fn unknown_kind_keys(config: &Config) -> Vec<&str> {
+    let known: HashSet<String> = all_kinds().iter().map(ToString::to_string).collect();
+    let mut unknown: Vec<&str> = config
+        .kinds
+        .iter()
+        .flat_map(|kinds| kinds.keys())
+        .filter(|key| !known.contains(*key))
+        .map(String::as_str)
+        .collect();
+    unknown.sort_unstable();
+    unknown
+}
+
+/// Warn about unknown `[kinds]` keys in the config, or fail on them with --strict.
+fn validate_kind_keys(args: &Args, config: &Option<Config>) -> Result<(), CleanerError> {
+    let (Some(path), Some(config)) = (&args.config, config) else {
+        return Ok(());
+    };
+    let known = all_kinds()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    for name in unknown_kind_keys(config) {
+        if args.strict {
+            return Err(CleanerError::UnknownKind {
+                path: PathBuf::from(path),
+                name: name.to_string(),
+                known,
+            });
+        }
+        warn!(
+            "config {}: unknown kind '{}' in [kinds] is ignored (known kinds: {})",
+            path, name, known
+        );
+    }
+    Ok(())
+}
+
+/// Whether patterns match ignoring case: --ignore-case or --case-sensitive, then the config,
+/// then the platform default (see [`platform::CASE_INSENSITIVE_BY_DEFAULT`]).
+fn case_insensitive(args: &Args, config: &Option<Config>) -> bool {
+    if args.ignore_case || args.case_sensitive {
+        return args.ignore_case;
+    }
+    config
+        .as_ref()
+        .and_then(|cfg| cfg.general.as_ref())
+        .and_then(|general| general.case_insensitive)
+        .unwrap_or(platform::CASE_INSENSITIVE_BY_DEFAULT)
+}
+
+/// The kind whose directories are cleaned: --kind, then the configured default kind, then all.
+fn effective_kind(args: &Args, config: &Option<Config>) -> ProjectKind {
+    args.kind
+        .or_else(|| {
+            config
+                .as_ref()
+                .and_then(|cfg| cfg.general.as_ref())
+                .and_then(|general| general.default_kind)
+        })
+        .unwrap_or(ProjectKind::All)
+}
+
+/// The --dirs patterns, with every `@name` replaced by the patterns of the config alias `name`
+/// (the alias key may be written with or without the `@`) and brace groups expanded unless
+/// --literal is set. `None` without --dirs.
+fn cli_dirs(args: &Args, config: &Option<Config>) -> Result<Option<Vec<String>>, CleanerError> {
+    let Some(dirs) = &args.dirs else {
+        return Ok(None);
+    };
+    let aliases = config.as_ref().and_then(|cfg| cfg.aliases.as_ref());
+    let mut expanded = Vec::new();
+    for dir in split_patterns(dirs) {
+        let Some(name) = dir.strip_prefix('@') else {
+            expanded.extend(brace_patterns(args, dir));
+            continue;
+        };
+        let patterns = aliases
+            .and_then(|aliases| aliases.get(name).or_else(|| aliases.get(dir)))
+            .ok_or_else(|| CleanerError::UnknownAlias {
+                name: name.to_string(),
+            })?;
+        expanded.extend(patterns.iter().cloned());
+    }
+    Ok(Some(expanded))
+}
+
+/// The config's `dirs` entries for the effective kind, unless --dirs overrides them.
+fn configured_dirs<'c>(args: &Args, config: &'c Option<Config>) -> Option<&'c [DirEntry]> {
+    if args.dirs.is_some() {
+        return None;
+    }
+    let kind_key = effective_kind(args, config).to_string();
+    config
+        .as_ref()?
+        .kinds
+        .as_ref()?
+        .get(&kind_key)?
+        .dirs
+        .as_deref()
+}
+
+/// The `post_command` configured for the kind being cleaned.
+fn configured_post_command<'c>(args: &Args, config: &'c Option<Config>) -> Option<&'c str> {
+    let kind_key = effective_kind(args, config).to_string();
+    config
+        .as_ref()?
+        .kinds
+        .as_ref()?
+        .get(&kind_key)?
+        .post_command
+        .as_deref()
 }
 
 /// Determine which directories to clean based on kind or user override, deduplicated.
-fn determine_dirs_to_clean(args: &Args, config: &Option<Config>) -> Vec<String> {
+fn determine_dirs_to_clean(
+    args: &Args,
+    config: &Option<Config>,
+) -> Result<Vec<String>, CleanerError> {
     // CLI takes precedence, then config, then default
-    if let Some(dirs) = &args.dirs {
-        return dirs.split(',').map(|s| s.to_string()).collect();
+    let affixes = affix_patterns(&args.dirs_suffix, &args.dirs_prefix);
+    if let Some(mut dirs) = cli_dirs(args, config)? {
+        dirs.extend(affixes);
+        return Ok(dirs);
     }
-    if let Some(cfg) = config {
-        if let Some(kinds) = &cfg.kinds {
-            let kind_key = args
-                .kind
-                .as_ref()
-                .map(|k| format!("{}", k).to_lowercase())
-                .unwrap_or("all".to_string());
-            if let Some(kind_cfg) = kinds.get(&kind_key) {
-                if let Some(dirs) = &kind_cfg.dirs {
-                    return dirs.clone();
-                }
-            }
-        }
+    if !affixes.is_empty() {
+        return Ok(affixes);
+    }
+    if args.all_kinds {
+        let excluded: HashSet<String> = dirs_for_kinds(&args.except_kind).into_iter().collect();
+        return Ok(dirs_for_kinds(all_kinds())
+            .into_iter()
+            .filter(|dir| !excluded.contains(dir))
+            .collect());
+    }
+    if let Some(entries) = configured_dirs(args, config) {
+        return Ok(entries.iter().map(|e| e.name().to_string()).collect());
     }
     // Fallback to built-in logic
-    match &args.kind {
-        Some(kind) => default_dirs_for_kind(kind),
-        None => default_dirs_for_kind(&args::ProjectKind::All),
+    Ok(dirs_for_kinds(&[effective_kind(args, config)]))
+}
+
+/// Translate --dirs-suffix and --dirs-prefix into globs, escaping the given text so it is
+/// matched literally.
+fn affix_patterns(suffixes: &[String], prefixes: &[String]) -> Vec<String> {
+    let suffixes = suffixes.iter().map(|s| format!("*{}", Pattern::escape(s)));
+    let prefixes = prefixes.iter().map(|p| format!("{}*", Pattern::escape(p)));
+    suffixes.chain(prefixes).collect()
+}
+
+/// Per-pattern depth limits from the config's `{ name, max_depth }` dirs entries.
+fn determine_pattern_depths(args: &Args, config: &Option<Config>) -> HashMap<String, usize> {
+    configured_dirs(args, config)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|entry| Some((entry.name().to_string(), entry.max_depth()?)))
+        .collect()
+}
+
+/// Expand the brace groups of a --dirs or --exclude pattern, e.g. `{target,build}`, unless
+/// --literal asks for exact names.
+fn brace_patterns(args: &Args, pattern: &str) -> Vec<String> {
+    if args.literal {
+        vec![pattern.to_string()]
+    } else {
+        expand_braces(pattern)
     }
-    .into_iter()
-    .map(|s| s.to_string())
-    .collect()
 }
 
 fn determine_exclude(args: &Args, config: &Option<Config>) -> Vec<String> {
     // CLI takes precedence, then config, then empty
     if let Some(ex) = &args.exclude {
-        return ex
-            .split(',')
+        return split_patterns(ex)
+            .into_iter()
             .filter(|s| !s.is_empty())
-            .map(|s| s.to_string())
+            .flat_map(|s| brace_patterns(args, s))
             .collect();
     }
     if let Some(cfg) = config {
         if let Some(exclude) = &cfg.exclude {
             if let Some(patterns) = &exclude.patterns {
-                return patterns.clone();
+                return patterns
+                    .iter()
+                    .flat_map(|pattern| brace_patterns(args, pattern))
+                    .collect();
             }
         }
     }
     vec![]
 }
 
-/// Prompt the user for confirmation unless force is set. Returns true if confirmed.
-fn confirm_deletion(dirs: &[&str], force: bool, dry_run: bool, ci: bool) -> bool {
-    if force || dry_run || ci {
-        return true;
+/// Where an effective setting came from, for --print-config.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Source {
+    /// Given on the command line.
+    Cli,
+    /// Read from the --config file.
+    Config,
+    /// Neither: the built-in default.
+    Default,
+}
+
+/// One effective setting and its source.
+#[derive(Debug, Serialize)]
+struct Setting<T> {
+    value: T,
+    source: Source,
+}
+
+impl<T> Setting<T> {
+    fn new(value: T, source: Source) -> Self {
+        Setting { value, source }
+    }
+}
+
+/// The settings a run would use, as printed by --print-config.
+#[derive(Debug, Serialize)]
+struct EffectiveConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config_file: Option<String>,
+    kind: Setting<String>,
+    dirs: Setting<Vec<String>>,
+    /// Depth limits of the config's `{ name, max_depth }` dirs entries.
+    pattern_depths: BTreeMap<String, usize>,
+    exclude: Setting<Vec<String>>,
+    max_depth: Setting<usize>,
+    case_insensitive: Setting<bool>,
+    units: Setting<Units>,
+    /// The main switches, whether given or not.
+    flags: BTreeMap<&'static str, bool>,
+}
+
+/// Resolve the effective configuration, mirroring the precedence the run applies: the command
+/// line, then the config file, then the defaults. `dirs` and `exclude` are the resolved patterns.
+fn effective_config(
+    args: &Args,
+    config: &Option<Config>,
+    dirs: Vec<String>,
+    exclude: Vec<String>,
+) -> EffectiveConfig {
+    let general = config.as_ref().and_then(|cfg| cfg.general.as_ref());
+    let source = |cli: bool, configured: bool| match (cli, configured) {
+        (true, _) => Source::Cli,
+        (false, true) => Source::Config,
+        (false, false) => Source::Default,
+    };
+    let kind_source = source(
+        args.kind.is_some(),
+        general.and_then(|g| g.default_kind).is_some(),
+    );
+    let dirs_from_cli = args.dirs.is_some()
+        || !args.dirs_suffix.is_empty()
+        || !args.dirs_prefix.is_empty()
+        || args.all_kinds;
+    let dirs_source = source(dirs_from_cli, configured_dirs(args, config).is_some());
+    let exclude_source = source(
+        args.exclude.is_some(),
+        config
+            .as_ref()
+            .and_then(|cfg| cfg.exclude.as_ref())
+            .is_some_and(|exclude| exclude.patterns.is_some()),
+    );
+    let case_source = source(
+        args.ignore_case || args.case_sensitive,
+        general.and_then(|g| g.case_insensitive).is_some(),
+    );
+    let units_source = source(
+        args.units.is_some(),
+        general.and_then(|g| g.units).is_some(),
+    );
+    let flags = BTreeMap::from([
+        ("dry_run", args.dry_run),
+        ("force", args.force),
+        ("ci", args.ci),
+        ("interactive", args.interactive),
+        ("literal", args.literal),
+        ("match_files", args.match_files),
+        ("one_file_system", args.one_file_system),
+        ("skip_submodules", args.skip_submodules),
+        ("infer_from_ignores", args.infer_from_ignores),
+        ("strict", args.strict),
+    ]);
+    EffectiveConfig {
+        config_file: args.config.clone(),
+        kind: Setting::new(effective_kind(args, config).to_string(), kind_source),
+        dirs: Setting::new(dirs, dirs_source),
+        pattern_depths: determine_pattern_depths(args, config).into_iter().collect(),
+        exclude: Setting::new(exclude, exclude_source),
+        max_depth: Setting::new(args.max_depth, source(args.max_depth > 0, false)),
+        case_insensitive: Setting::new(case_insensitive(args, config), case_source),
+        units: Setting::new(
+            args.units
+                .or_else(|| general.and_then(|g| g.units))
+                .unwrap_or_default(),
+            units_source,
+        ),
+        flags,
+    }
+}
+
+/// Print every built-in project kind with the directories it cleans, followed by kinds from the config.
+fn list_kinds(config: &Option<Config>) {
+    for kind in all_kinds() {
+        println!(
+            "{:<8} {}",
+            kind.to_string(),
+            default_dirs_for_kind(kind).join(", ")
+        );
+    }
+    if let Some(kinds) = config.as_ref().and_then(|cfg| cfg.kinds.as_ref()) {
+        println!();
+        println!("From config:");
+        let mut names: Vec<_> = kinds.keys().collect();
+        names.sort();
+        for name in names {
+            let dirs: Vec<String> = kinds[name]
+                .dirs
+                .iter()
+                .flatten()
+                .map(|entry| entry.to_string())
+                .collect();
+            println!("{:<8} {}", name, dirs.join(", "));
+        }
     }
+}
+
+/// Show what is about to be deleted and ask once for confirmation. Returns true if confirmed.
+///
+/// `items` are the lines to list, `summary` a closing line such as the total size. With a
+/// `phrase`, only typing exactly that phrase confirms.
+fn confirm_deletion(items: &[String], summary: &str, phrase: Option<&str>) -> bool {
     use std::io::{self, Write};
-    println!("WARNING: The following directories will be deleted recursively:");
-    for d in dirs {
-        println!("  - {}", d);
+    println!("WARNING: The following will be deleted recursively:");
+    for item in items {
+        println!("  - {}", item);
+    }
+    if !summary.is_empty() {
+        println!("{}", summary);
+    }
+    match phrase {
+        Some(phrase) => print!("Type '{}' to proceed: ", phrase),
+        None => print!("Are you sure you want to proceed? [y/N]: "),
     }
-    print!("Are you sure you want to proceed? [y/N]: ");
     io::stdout().flush().unwrap();
     let mut input = String::new();
     io::stdin().read_line(&mut input).unwrap();
-    let input = input.trim().to_lowercase();
-    input == "y" || input == "yes"
+    match phrase {
+        Some(phrase) => input.trim() == phrase,
+        None => {
+            let input = input.trim().to_lowercase();
+            input == "y" || input == "yes"
+        }
+    }
 }
 
-/// Recursively walk the directory tree and remove matching directories, or just print if dry_run is true.
-/// Returns (number of directories, total bytes that would be or were deleted)
-fn clean_directories(
-    path: &str,
-    dirs: &[&str],
+/// The phrase to type at the confirmation prompt: --confirm-phrase, or `DELETE` once more than
+/// --confirm-phrase-above entries matched. `None` means a plain y/N prompt.
+fn confirm_phrase(args: &Args, matches: usize) -> Option<&str> {
+    match &args.confirm_phrase {
+        Some(phrase) => Some(phrase),
+        None => (matches > args.confirm_phrase_above).then_some("DELETE"),
+    }
+}
+
+/// Options controlling how matched directories are handled during a cleaning run.
+struct CleanOptions<'a> {
+    /// Only print what would be removed.
     dry_run: bool,
-    exclude: &[&str],
+    /// Directory name patterns to skip.
+    exclude: &'a [&'a str],
+    /// Maximum recursion depth (0 means unlimited).
     max_depth: usize,
+    /// Maximum depth of a match below its nearest project root.
+    project_relative_depth: Option<usize>,
+    /// Only clean matches whose pattern is a default directory of the kind detected for their
+    /// nearest project root.
+    detected_only: bool,
+    /// Prompt before each deletion.
     interactive: bool,
+    /// Set once `a` (all) was answered at an interactive prompt; the remaining targets of every
+    /// root are then removed without asking.
+    confirm_all: AtomicBool,
+    /// Skip all prompts.
     force: bool,
-) -> (usize, u64) {
-    info!(
-        "Cleaning all directories that finished with either: {:?}, excluding: {:?}, max_depth: {}",
-        dirs, exclude, max_depth
-    );
-    let mut walkdir = WalkDir::new(path);
-    if max_depth > 0 {
-        walkdir = walkdir.max_depth(max_depth);
-    }
-    // Compile glob patterns for dirs and exclude
-    let dir_patterns: Vec<Pattern> = dirs.iter().filter_map(|p| Pattern::new(p).ok()).collect();
-    let exclude_patterns: Vec<Pattern> = exclude
-        .iter()
-        .filter_map(|p| Pattern::new(p).ok())
-        .collect();
-    // Collect all target directories first
-    let targets: Vec<_> = walkdir
-        .into_iter()
-        .filter_map(|file| {
-            let f = file.unwrap();
-            let file_path = f.path();
-            let file_name = file_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            if f.file_type().is_dir()
-                && dir_patterns.iter().any(|pat| pat.matches(file_name))
-                && !exclude_patterns.iter().any(|pat| pat.matches(file_name))
-            {
-                Some(file_path.to_path_buf())
-            } else {
-                None
-            }
-        })
-        .collect();
-    let count = targets.len();
-    let mut total_bytes = 0u64;
-    if dry_run {
-        for path in &targets {
-            println!("Would remove: {}", path.display());
-            if let Ok(meta) = fs::metadata(path) {
-                total_bytes += meta.len();
-            }
-        }
-    } else if interactive && !force {
-        use std::io::{self, Write};
-        for path in &targets {
-            print!("Delete {}? [y/N]: ", path.display());
-            io::stdout().flush().unwrap();
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
-            let input = input.trim().to_lowercase();
-            if input == "y" || input == "yes" {
-                info!("removing: {}", path.display());
-                let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-                let _ = fs::remove_dir_all(path);
-                total_bytes += size;
-            } else {
-                println!("Skipped: {}", path.display());
-            }
+    /// Abort on the first removal error instead of continuing.
+    fail_fast: bool,
+    /// Skip directories that contain files held open by a running process.
+    skip_in_use: bool,
+    /// Skip targets that cannot be read completely.
+    verify_readable: bool,
+    /// Resolve Rust target directories through `cargo metadata` instead of name matching.
+    respect_cargo_metadata: bool,
+    /// Print paths relative to the scan root.
+    relative: bool,
+    /// Upper bound on open file descriptors (0 means unlimited).
+    max_open_fds: usize,
+    /// Artificial delay before each removal (--simulate-delete-latency, for tests and
+    /// benchmarks).
+    simulate_delete_latency: Option<Duration>,
+    /// Skip the removal itself but report targets as removed (--no-op-delete, for benchmarks).
+    no_op_delete: bool,
+    /// How many times to retry a removal that failed with a transient error.
+    retries: u32,
+    /// No new removals are started after this instant.
+    deadline: Option<Instant>,
+    /// Unit system for printed sizes.
+    units: Units,
+    /// Number formatting of printed counts.
+    locale: SummaryLocale,
+    /// Directories modified after this time are left alone.
+    protect_newer_than: Option<SystemTime>,
+    /// Print the reason each directory was selected or skipped.
+    explain: bool,
+    /// Match directory and exclude patterns case-insensitively.
+    case_insensitive: bool,
+    /// Matched directories containing a file with this name are kept.
+    keep_file: &'a str,
+    /// Walk directories in file name order, so scan output (warnings, --explain) is the same on
+    /// every run.
+    sorted: bool,
+    /// Command run in each project directory after its matches were removed.
+    post_command: Option<&'a str>,
+    /// Maximum number of "Would remove" lines per root in a dry run (0 means unlimited).
+    preview_limit: usize,
+    /// Leave out the subtrees of git submodules declared in `.gitmodules` files.
+    skip_submodules: bool,
+    /// Do not descend into directories on another filesystem than the root (mount points).
+    one_file_system: bool,
+    /// Device lookup for --one-file-system, [`device_id`] outside of tests.
+    device_of: fn(&Path) -> Option<u64>,
+    /// Matched directories directly containing a file or directory with one of these names are
+    /// skipped (but still descended into).
+    exclude_if_contains: &'a [String],
+    /// When not empty, matched directories are only removed if they contain one of these
+    /// markers (skipped, but still descended into, otherwise).
+    require_marker: &'a [String],
+    /// Remove the subdirectories of each matched directory except this many most recent ones,
+    /// instead of the matched directory itself.
+    keep_recent: Option<usize>,
+    /// Remove the files inside each matched directory modified before this instant, instead of
+    /// the matched directory itself.
+    prune_contents_before: Option<SystemTime>,
+    /// Let patterns match files too (removed individually), not only directories.
+    match_files: bool,
+    /// Only keep dependency directories older than their lockfile.
+    stale_deps_only: bool,
+    /// Attribute removed entries to a language for the summary.
+    group_by_language: bool,
+    /// Treat directory and exclude patterns as exact names rather than globs.
+    literal: bool,
+    /// Move targets below this staging directory instead of deleting them.
+    move_to: Option<&'a Path>,
+    /// How to handle a --move-to destination that already exists.
+    on_conflict: OnConflict,
+    /// Archive targets as `.tar.gz` below this directory before removing them.
+    archive_to: Option<&'a Path>,
+    /// Move or archive each target all or nothing (see [`move_dir_atomic`]).
+    atomic: bool,
+    /// Maximum depth below the root at which a given directory pattern may match.
+    pattern_depths: &'a HashMap<String, usize>,
+    /// Canonical directories (package-manager caches) whose contents are never targets.
+    protected_roots: &'a [PathBuf],
+    /// The activated virtualenv (`VIRTUAL_ENV`, canonical), never removed without --force.
+    active_venv: Option<&'a Path>,
+    /// After removal, also remove parents that became empty, up to the scan root.
+    prune_empty_parents: bool,
+    /// Reuse directory sizes from earlier runs (--cache).
+    size_cache: Option<&'a SizeCache>,
+    /// Record of removals for --checkpoint; with --resume, targets listed there are skipped.
+    checkpoint: Option<&'a Checkpoint>,
+    /// How often each directory pattern matched during the scan.
+    pattern_matches: &'a PatternMatches,
+    /// With --project-boundaries, the nearest project root of each target, filled in by the scan.
+    project_roots: Option<&'a ProjectRoots>,
+}
+
+impl CleanOptions<'_> {
+    /// Glob options used for every directory and exclude pattern match.
+    fn match_options(&self) -> MatchOptions {
+        MatchOptions {
+            case_sensitive: !self.case_insensitive,
+            ..MatchOptions::new()
         }
-    } else {
-        total_bytes = targets
-            .par_iter()
-            .map(|path| {
-                info!("removing: {}", path.display());
-                let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-                let _ = fs::remove_dir_all(path);
-                size
-            })
-            .sum();
     }
-    (count, total_bytes)
+
+    /// The project `target` belongs to, used to group it in reports and to run post_command:
+    /// its nearest project root with --project-boundaries, otherwise the directory containing it.
+    fn project_of(&self, target: &Path) -> PathBuf {
+        self.project_roots
+            .and_then(|roots| roots.get(target))
+            .unwrap_or_else(|| target.parent().unwrap_or(Path::new("")).to_path_buf())
+    }
+
+    /// Where `target` is counted in the report: its project, and with --group-by-language its
+    /// language (`other` when none can be told).
+    fn owner_of(&self, target: &Path) -> Owner {
+        let project = self.project_of(target);
+        let language = self.group_by_language.then(|| {
+            language_of(target, &project)
+                .map_or_else(|| "other".to_string(), |kind| kind.to_string())
+        });
+        Owner { project, language }
+    }
+
+    /// True once the --max-runtime deadline has passed.
+    fn deadline_passed(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
 }
 
-/// Main entry point for the Cleaner CLI tool.
-///
-/// Parses command-line arguments, sets up logging, determines which directories to clean,
-/// and recursively removes them from the specified root path.
+/// Per-pattern match counters, shared by the scans of all roots.
 ///
-/// # Returns
-/// * `Result<()>` - Ok on success, error if the path does not exist or a removal fails.
-fn main() {
-    use clap::error::ErrorKind;
-    match Args::try_parse() {
-        Ok(args) => {
-            tokio_main(args).unwrap();
+/// A pattern counts as matched whenever an entry matches it, even if the entry is then left
+/// alone (excluded, kept, ...), so only patterns that match nothing at all stand out. The
+/// pattern each collected target matched is remembered too, for the dry-run breakdown.
+#[derive(Debug, Default)]
+struct PatternMatches {
+    counts: std::sync::Mutex<BTreeMap<String, usize>>,
+    targets: std::sync::Mutex<BTreeMap<PathBuf, String>>,
+}
+
+impl PatternMatches {
+    const fn new() -> Self {
+        PatternMatches {
+            counts: std::sync::Mutex::new(BTreeMap::new()),
+            targets: std::sync::Mutex::new(BTreeMap::new()),
         }
-        Err(e) => match e.kind() {
-            ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => {
-                e.print().expect("Failed to print help/version");
-                std::process::exit(0);
-            }
-            _ => {
-                eprintln!("Cleaner v{}", env!("CARGO_PKG_VERSION"));
-                eprintln!("Build: {}", env!("BUILD_DATE"));
-                e.print().expect("Failed to print error");
-                std::process::exit(1);
-            }
-        },
+    }
+
+    fn record(&self, pattern: &str) {
+        *self
+            .counts
+            .lock()
+            .unwrap()
+            .entry(pattern.to_string())
+            .or_default() += 1;
+    }
+
+    fn count(&self, pattern: &str) -> usize {
+        self.counts
+            .lock()
+            .unwrap()
+            .get(pattern)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Remember that `target` was collected for `pattern`.
+    fn collected(&self, target: &Path, pattern: &str) {
+        self.targets
+            .lock()
+            .unwrap()
+            .insert(target.to_path_buf(), pattern.to_string());
+    }
+
+    /// The pattern that collected `target`, or one of its parents (when --keep-recent or
+    /// --prune-contents-older-than split a match into its contents).
+    fn pattern_of(&self, target: &Path) -> Option<String> {
+        let targets = self.targets.lock().unwrap();
+        target
+            .ancestors()
+            .find_map(|path| targets.get(path).cloned())
+    }
+
+    /// The `patterns` that matched nothing, in the given order.
+    fn unmatched<'p>(&self, patterns: &[&'p str]) -> Vec<&'p str> {
+        patterns
+            .iter()
+            .copied()
+            .filter(|pattern| self.count(pattern) == 0)
+            .collect()
     }
 }
 
-#[tokio::main]
-async fn tokio_main(args: Args) -> Result<()> {
-    // Set up logger with thread info and user-specified log level
-    setup_logger(true, Some(&args.log), args.log_file.as_deref());
-    // Load config if provided
-    let config = args.config.as_deref().and_then(load_config);
-    // Determine which directories to clean
-    let dirs = determine_dirs_to_clean(&args, &config);
-    // Parse exclude list
-    let exclude = determine_exclude(&args, &config);
-    // Confirm deletion unless forced
-    let force = args.force || args.ci;
-    if !confirm_deletion(
-        &dirs.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
-        force,
-        args.dry_run,
-        args.ci,
-    ) {
-        println!("Aborted by user.");
-        return Ok(());
+/// Nearest project root of each target, recorded during the scan with --project-boundaries. A
+/// project root is a directory with a project marker (see [`detect_project_kind`]) or a `.git`.
+#[derive(Debug, Default)]
+struct ProjectRoots(std::sync::Mutex<HashMap<PathBuf, PathBuf>>);
+
+impl ProjectRoots {
+    fn insert(&self, target: &Path, project: &Path) {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(target.to_path_buf(), project.to_path_buf());
     }
-    // Clean the directories
-    let (count, total_bytes) = clean_directories(
-        &args.path,
-        &dirs.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
-        args.dry_run,
-        &exclude.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
-        args.max_depth,
-        args.interactive,
-        force,
-    );
-    if args.ci {
-        let summary = Summary {
-            directories: count,
-            total_bytes,
-            total_mb: total_bytes as f64 / 1_048_576.0,
-            dry_run: args.dry_run,
+
+    fn get(&self, target: &Path) -> Option<PathBuf> {
+        self.0.lock().unwrap().get(target).cloned()
+    }
+}
+
+/// Warn about --dirs patterns that matched nothing, which usually means a typo or the wrong
+/// root. Kind defaults are not checked: most of them are expected to be absent.
+fn warn_unmatched(args: &Args, config: &Option<Config>, matches: &PatternMatches) {
+    let Ok(Some(dirs)) = cli_dirs(args, config) else {
+        return;
+    };
+    let patterns: Vec<&str> = dirs.iter().map(String::as_str).collect();
+    for pattern in matches.unmatched(&patterns) {
+        warn!("pattern '{}' matched 0 directories", pattern);
+    }
+}
+
+/// Number of matched directories that were left alone, by reason.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+struct Skipped {
+    /// Matched an exclude pattern.
+    excluded: usize,
+    /// Matched but is a symlink, which is never followed.
+    symlinks: usize,
+    /// Contained the --keep-file marker.
+    keep_file: usize,
+    /// Contained one of the --exclude-if-contains markers.
+    exclude_marker: usize,
+    /// Contained none of the --require-marker markers.
+    missing_marker: usize,
+    /// Modified more recently than --exclude-newer-than.
+    protected: usize,
+    /// Held open by a running process (--skip-in-use).
+    in_use: usize,
+    /// Among the newest entries kept by --keep-recent.
+    kept_recent: usize,
+    /// Declined at an interactive prompt.
+    declined: usize,
+    /// Inside a protected package-manager cache.
+    package_cache: usize,
+    /// Its --move-to destination already existed (--on-conflict skip).
+    conflicts: usize,
+    /// Git submodule subtrees left out with --skip-submodules.
+    submodules: usize,
+    /// Contained an entry that could not be read (--verify-readable), or could not be listed to
+    /// pick the entries kept by --keep-recent.
+    unreadable: usize,
+    /// Was or contained the activated virtualenv.
+    active_venv: usize,
+    /// Already removed by the interrupted run being resumed (--resume).
+    resumed: usize,
+    /// Not a dependency directory older than its lockfile (--stale-deps-only).
+    not_stale: usize,
+}
+
+impl Skipped {
+    fn merge(mut self, other: Skipped) -> Skipped {
+        self.excluded += other.excluded;
+        self.symlinks += other.symlinks;
+        self.keep_file += other.keep_file;
+        self.exclude_marker += other.exclude_marker;
+        self.missing_marker += other.missing_marker;
+        self.protected += other.protected;
+        self.in_use += other.in_use;
+        self.kept_recent += other.kept_recent;
+        self.declined += other.declined;
+        self.package_cache += other.package_cache;
+        self.conflicts += other.conflicts;
+        self.submodules += other.submodules;
+        self.unreadable += other.unreadable;
+        self.active_venv += other.active_venv;
+        self.resumed += other.resumed;
+        self.not_stale += other.not_stale;
+        self
+    }
+
+    /// `(count, label)` pairs for every reason, in display order.
+    fn counts(&self) -> [(usize, &'static str); 16] {
+        [
+            (self.excluded, "excluded"),
+            (self.symlinks, "symlinks"),
+            (self.keep_file, "keep file"),
+            (self.exclude_marker, "exclude marker"),
+            (self.missing_marker, "no required marker"),
+            (self.protected, "protected"),
+            (self.in_use, "in use"),
+            (self.kept_recent, "kept recent"),
+            (self.declined, "declined"),
+            (self.package_cache, "package cache"),
+            (self.conflicts, "destination exists"),
+            (self.submodules, "submodules"),
+            (self.unreadable, "unreadable"),
+            (self.active_venv, "active virtualenv"),
+            (self.resumed, "done in an earlier run"),
+            (self.not_stale, "not stale"),
+        ]
+    }
+
+    fn total(&self) -> usize {
+        self.counts().iter().map(|(count, _)| count).sum()
+    }
+}
+
+impl fmt::Display for Skipped {
+    /// e.g. "12 excluded, 3 symlinks, 2 protected", leaving out reasons that did not occur.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(SummaryLocale::Plain))
+    }
+}
+
+impl Skipped {
+    /// The reasons that occurred with their counts formatted for `locale`, as in the summary.
+    fn describe(&self, locale: SummaryLocale) -> String {
+        self.counts()
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, label)| format!("{} {}", format_count(*count as u64, locale), label))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// A matched entry to clean. Directories are removed recursively; files only become targets
+/// with --match-files and are removed individually.
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Dir(PathBuf),
+    File(PathBuf),
+}
+
+impl Target {
+    fn path(&self) -> &Path {
+        match self {
+            Target::Dir(path) | Target::File(path) => path,
+        }
+    }
+
+    /// Size in bytes: the whole tree for a directory, the length for a file.
+    fn size(&self, cache: Option<&SizeCache>) -> u64 {
+        match self {
+            Target::Dir(path) => match cache {
+                Some(cache) => cache.dir_size(path),
+                None => dir_size(path),
+            },
+            Target::File(path) => fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        }
+    }
+}
+
+/// Where a removed target is counted in the report (see [`CleanOptions::owner_of`]).
+struct Owner {
+    project: PathBuf,
+    /// Language the target is attributed to, with --group-by-language.
+    language: Option<String>,
+}
+
+/// What happened to one matched target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    /// Removed (or moved, or would be in a dry run), freeing this many bytes.
+    Removed(u64),
+    /// Archived with --archive-to into an archive of `archive` bytes, then removed.
+    Archived { freed: u64, archive: u64 },
+    /// Already gone when its turn came, e.g. deleted by another process since the scan.
+    Gone,
+    /// Not attempted because the --max-runtime deadline passed.
+    Unfinished,
+}
+
+/// Outcome of a cleaning run.
+#[derive(Debug, Default)]
+struct CleanReport {
+    /// Number of directories removed (or that would be removed in dry-run mode).
+    directories: usize,
+    /// Number of files removed (or that would be removed), with --match-files.
+    files: usize,
+    /// Total bytes removed (or that would be removed).
+    total_bytes: u64,
+    /// Directories that could not be removed.
+    failures: Vec<CleanerError>,
+    /// Directories left untouched because the --max-runtime deadline passed.
+    unfinished: usize,
+    /// Matches that no longer existed at removal time. Neither failures nor freed space.
+    gone: usize,
+    /// Matched directories that were deliberately left alone.
+    skipped: Skipped,
+    /// Parent directories removed by --prune-empty-parents.
+    pruned: usize,
+    /// Entries removed and bytes freed per project (see [`CleanOptions::project_of`]).
+    projects: BTreeMap<PathBuf, (usize, u64)>,
+    /// Entries removed and bytes freed per language, with --group-by-language.
+    languages: BTreeMap<String, (usize, u64)>,
+    /// Wall-clock time of the removal phase, after scanning and confirmation.
+    removal_time: Duration,
+    /// Free space before and after the removal phase, with --audit.
+    free_space: Option<(u64, u64)>,
+    /// Roots the run was given, for the --keep-going-across-roots summary.
+    roots: usize,
+    /// Roots that failed as a whole, with --keep-going-across-roots.
+    root_failures: Vec<RootFailure>,
+    /// Size of the archives written with --archive-to.
+    archived_bytes: u64,
+    /// What each directory pattern would remove, in a dry run.
+    patterns: BTreeMap<String, PatternTotals>,
+}
+
+/// Entries and bytes collected by one directory pattern.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+struct PatternTotals {
+    directories: usize,
+    files: usize,
+    bytes: u64,
+}
+
+impl PatternTotals {
+    fn add(&mut self, other: PatternTotals) {
+        self.directories += other.directories;
+        self.files += other.files;
+        self.bytes += other.bytes;
+    }
+}
+
+/// A root that could not be cleaned at all (--keep-going-across-roots).
+#[derive(Debug)]
+struct RootFailure {
+    root: String,
+    error: CleanerError,
+}
+
+impl CleanReport {
+    /// Record the outcome of one removal of a target counted towards `owner`.
+    fn record(&mut self, target: &Target, owner: &Owner, outcome: Outcome) {
+        let outcome = match outcome {
+            Outcome::Archived { freed, archive } => {
+                self.archived_bytes += archive;
+                Outcome::Removed(freed)
+            }
+            outcome => outcome,
         };
-        println!("{}", serde_json::to_string(&summary).unwrap());
-    } else if args.dry_run {
-        println!("Dry run: {} directories would be removed.", count);
-    } else {
-        println!(
-            "Removed {} directories. (Total size: {:.2} MB)",
-            count,
-            total_bytes as f64 / 1_048_576.0
-        );
+        if let Outcome::Removed(size) = outcome {
+            let entry = self.projects.entry(owner.project.clone()).or_default();
+            entry.0 += 1;
+            entry.1 += size;
+            if let Some(language) = &owner.language {
+                let entry = self.languages.entry(language.clone()).or_default();
+                entry.0 += 1;
+                entry.1 += size;
+            }
+        }
+        match (target, outcome) {
+            (Target::Dir(_), Outcome::Removed(size)) => {
+                self.directories += 1;
+                self.total_bytes += size;
+            }
+            (Target::File(_), Outcome::Removed(size)) => {
+                self.files += 1;
+                self.total_bytes += size;
+            }
+            (_, Outcome::Gone) => self.gone += 1,
+            (_, Outcome::Unfinished) => self.unfinished += 1,
+            (_, Outcome::Archived { .. }) => unreachable!("archives are counted as removals"),
+        }
+    }
+
+    /// Bytes freed and entries removed per second of the removal phase, once it took any time.
+    fn throughput(&self) -> Option<(f64, f64)> {
+        let seconds = self.removal_time.as_secs_f64();
+        (seconds > 0.0).then(|| {
+            (
+                self.total_bytes as f64 / seconds,
+                (self.directories + self.files) as f64 / seconds,
+            )
+        })
+    }
+
+    /// "N directories", or "N directories and M files" once files were matched, with the counts
+    /// formatted for `locale`.
+    fn describe(&self, locale: SummaryLocale) -> String {
+        let directories = format_count(self.directories as u64, locale);
+        if self.files > 0 {
+            let files = format_count(self.files as u64, locale);
+            format!("{} directories and {} files", directories, files)
+        } else {
+            format!("{} directories", directories)
+        }
+    }
+
+    /// Combine two partial reports.
+    fn merge(mut self, other: CleanReport) -> CleanReport {
+        self.directories += other.directories;
+        self.files += other.files;
+        self.total_bytes += other.total_bytes;
+        self.failures.extend(other.failures);
+        self.unfinished += other.unfinished;
+        self.gone += other.gone;
+        self.skipped = self.skipped.merge(other.skipped);
+        self.pruned += other.pruned;
+        // Partial reports of one run cover the same removal phase
+        self.removal_time = self.removal_time.max(other.removal_time);
+        self.free_space = self.free_space.or(other.free_space);
+        self.roots = self.roots.max(other.roots);
+        self.root_failures.extend(other.root_failures);
+        self.archived_bytes += other.archived_bytes;
+        for (pattern, totals) in other.patterns {
+            self.patterns.entry(pattern).or_default().add(totals);
+        }
+        for (project, (entries, bytes)) in other.projects {
+            let entry = self.projects.entry(project).or_default();
+            entry.0 += entries;
+            entry.1 += bytes;
+        }
+        for (language, (entries, bytes)) in other.languages {
+            let entry = self.languages.entry(language).or_default();
+            entry.0 += entries;
+            entry.1 += bytes;
+        }
+        self
+    }
+}
+
+/// Format a path for output, relative to the scan root when `relative` is set.
+fn display_path(path: &Path, root: &str, relative: bool) -> String {
+    match path.strip_prefix(root) {
+        Ok(rel) if relative && rel.as_os_str().is_empty() => ".".to_string(),
+        Ok(rel) if relative => rel.display().to_string(),
+        _ => path.display().to_string(),
+    }
+}
+
+/// Delay before the first retry of a failed removal; doubled on every further attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+
+/// Remove a single directory (recursively) or file.
+///
+/// `shown` is the path as it should appear in log output. Transient errors are retried up to
+/// `retries` times with exponential backoff. With `atomic` a directory is renamed aside before
+/// it is deleted, so a failure either leaves it intact or removes it from its place entirely.
+fn remove_target(
+    target: &Target,
+    shown: &str,
+    retries: u32,
+    atomic: bool,
+) -> Result<(), CleanerError> {
+    info!("removing: {}", shown);
+    let path = target.path();
+    let remove = || match target {
+        Target::Dir(path) if atomic => remove_aside(path),
+        Target::Dir(path) => fs::remove_dir_all(path),
+        Target::File(path) => fs::remove_file(path),
+    };
+    retry_with_backoff(retries, RETRY_BASE_DELAY, remove).map_err(|source| {
+        if source.kind() != io::ErrorKind::NotFound {
+            error!("failed to remove {}: {}", shown, source);
+        }
+        CleanerError::Removal {
+            path: path.to_path_buf(),
+            source,
+        }
+    })
+}
+
+/// Where `path`, found under `root`, is moved to below the `staging` directory: its path
+/// relative to the root is kept.
+fn staging_path(path: &Path, root: &str, staging: &Path) -> PathBuf {
+    staging.join(path.strip_prefix(root).unwrap_or(path))
+}
+
+/// Where `path`, found under `root`, is archived to below the --archive-to directory: its path
+/// relative to the root with `.tar.gz` appended.
+fn archive_path(path: &Path, root: &str, archive_dir: &Path) -> PathBuf {
+    let mut archive = staging_path(path, root, archive_dir).into_os_string();
+    archive.push(".tar.gz");
+    PathBuf::from(archive)
+}
+
+/// Stream `target` into a gzipped tarball at `destination`, returning the size of the archive. The archive holds the target under its own name, so extracting it in
+/// the target's parent directory restores it. A partial archive is removed on failure; with
+/// `atomic` it is written next to `destination` and only renamed there once complete.
+fn archive_target(
+    target: &Target,
+    destination: &Path,
+    shown: &str,
+    atomic: bool,
+) -> Result<u64, CleanerError> {
+    info!("archiving: {} -> {}", shown, destination.display());
+    let path = target.path();
+    let written = if atomic {
+        sibling_path(destination, "partial")
+    } else {
+        destination.to_path_buf()
+    };
+    let failed = |message: String| {
+        error!("failed to archive {}: {}", shown, message);
+        let _ = fs::remove_file(&written);
+        CleanerError::Archive {
+            path: path.to_path_buf(),
+            destination: destination.to_path_buf(),
+            message,
+        }
+    };
+    let Some(name) = path.file_name() else {
+        return Err(failed("cannot archive a path without a name".to_string()));
+    };
+    if let Some(dir) = destination.parent() {
+        fs::create_dir_all(dir).map_err(|e| failed(e.to_string()))?;
+    }
+    let file = File::create(&written).map_err(|e| failed(e.to_string()))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    // Links are stored as links, like everywhere else links are never followed
+    builder.follow_symlinks(false);
+    let appended = match target {
+        Target::Dir(_) => builder.append_dir_all(name, path),
+        Target::File(_) => builder.append_path_with_name(path, name),
+    };
+    appended
+        .and_then(|()| builder.into_inner())
+        .and_then(GzEncoder::finish)
+        .and_then(|file| file.sync_all())
+        .map_err(|e| failed(e.to_string()))?;
+    if atomic {
+        fs::rename(&written, destination).map_err(|e| failed(e.to_string()))?;
+    }
+    fs::metadata(destination)
+        .map(|metadata| metadata.len())
+        .map_err(|e| failed(e.to_string()))
+}
+
+/// Move a directory or file into the staging directory, all or nothing with `atomic`.
+fn move_target(
+    target: &Target,
+    destination: &Path,
+    shown: &str,
+    retries: u32,
+    atomic: bool,
+) -> Result<(), CleanerError> {
+    info!("moving: {} -> {}", shown, destination.display());
+    let path = target.path();
+    let transfer = || {
+        if atomic {
+            move_dir_atomic(path, destination)
+        } else {
+            move_dir(path, destination)
+        }
+    };
+    retry_with_backoff(retries, RETRY_BASE_DELAY, transfer).map_err(|source| {
+        if source.kind() != io::ErrorKind::NotFound {
+            error!("failed to move {}: {}", shown, source);
+        }
+        CleanerError::Move {
+            path: path.to_path_buf(),
+            destination: destination.to_path_buf(),
+            source,
+        }
+    })
+}
+
+/// Remove `target`, or move it to the staging directory with --move-to. With --archive-to it is
+/// archived first, and left in place if that fails.
+///
+/// A target that vanished since the scan (another process cleaned it, a rebuild replaced it) is
+/// reported as [`Outcome::Gone`] rather than as a failure.
+fn dispose_target(
+    target: &Target,
+    root: &str,
+    opts: &CleanOptions,
+) -> Result<Outcome, CleanerError> {
+    let shown = display_path(target.path(), root, opts.relative);
+    let gone = || target.path().symlink_metadata().is_err();
+    if gone() {
+        debug!("{} is already gone", shown);
+        return Ok(Outcome::Gone);
+    }
+    let size = target.size(opts.size_cache);
+    if opts.no_op_delete {
+        debug!("--no-op-delete: leaving {} in place", shown);
+        return Ok(Outcome::Removed(size));
+    }
+    let archive = match opts.archive_to {
+        Some(archive_dir) => {
+            let mut destination = archive_path(target.path(), root, archive_dir);
+            if destination.symlink_metadata().is_ok() {
+                destination = archive_path(&timestamped_path(target.path()), root, archive_dir);
+            }
+            Some(archive_target(target, &destination, &shown, opts.atomic)?)
+        }
+        None => None,
+    };
+    let result = match opts.move_to {
+        Some(staging) => {
+            let mut destination = staging_path(target.path(), root, staging);
+            if destination.symlink_metadata().is_ok() {
+                match opts.on_conflict {
+                    OnConflict::Overwrite => {
+                        info!("replacing existing {}", destination.display());
+                        let existing = if destination.is_dir() {
+                            fs::remove_dir_all(&destination)
+                        } else {
+                            fs::remove_file(&destination)
+                        };
+                        existing.map_err(|source| CleanerError::Move {
+                            path: target.path().to_path_buf(),
+                            destination: destination.clone(),
+                            source,
+                        })?;
+                    }
+                    OnConflict::Rename => destination = timestamped_path(&destination),
+                    // Filtered out before removal started; fail rather than clobber on a race
+                    OnConflict::Skip => {}
+                }
+            }
+            move_target(target, &destination, &shown, opts.retries, opts.atomic)
+        }
+        None => remove_target(target, &shown, opts.retries, opts.atomic),
+    };
+    match result {
+        Ok(()) => {
+            if let Some(checkpoint) = opts.checkpoint {
+                checkpoint.record(target.path())?;
+            }
+            Ok(match archive {
+                Some(archive) => Outcome::Archived {
+                    freed: size,
+                    archive,
+                },
+                None => Outcome::Removed(size),
+            })
+        }
+        Err(CleanerError::Removal { source, .. } | CleanerError::Move { source, .. })
+            if source.kind() == io::ErrorKind::NotFound && gone() =>
+        {
+            debug!("{} disappeared during removal", shown);
+            Ok(Outcome::Gone)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Compile glob patterns, failing on the first invalid one. With `literal` every metacharacter
+/// is escaped, so the pattern only matches its exact text.
+fn compile_patterns(patterns: &[&str], literal: bool) -> Result<Vec<Pattern>, CleanerError> {
+    patterns
+        .iter()
+        .map(|p| {
+            let source = if literal {
+                Pattern::escape(p)
+            } else {
+                p.to_string()
+            };
+            Pattern::new(&source).map_err(|e| CleanerError::InvalidPattern {
+                pattern: p.to_string(),
+                message: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Find the first pattern matching `path`.
+///
+/// Plain patterns are matched against the directory name; patterns containing `/` (e.g.
+/// `.nx/cache`) are matched against the same number of trailing path components.
+fn find_match<'p>(
+    patterns: &'p [Pattern],
+    path: &Path,
+    options: MatchOptions,
+) -> Option<&'p Pattern> {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    patterns.iter().find(|pat| {
+        let depth = pat.as_str().split('/').count();
+        if depth == 1 {
+            return pat.matches_with(name, options);
+        }
+        let components: Vec<_> = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        components.len() >= depth
+            && pat.matches_with(&components[components.len() - depth..].join("/"), options)
+    })
+}
+
+/// Describe an entry the directory walk could not read, e.g. "secret: Permission denied".
+fn traversal_error(error: &walkdir::Error, root: &str, opts: &CleanOptions) -> String {
+    let reason = match error.io_error() {
+        Some(io_error) => io_error.to_string(),
+        None => error.to_string(),
+    };
+    match error.path() {
+        Some(path) => format!("{}: {}", display_path(path, root, opts.relative), reason),
+        None => reason,
+    }
+}
+
+/// Print why a directory was or was not selected, when --explain is set.
+fn explain(opts: &CleanOptions, root: &str, path: &Path, reason: &str) {
+    if opts.explain {
+        println!(
+            "explain: {}: {}",
+            display_path(path, root, opts.relative),
+            reason
+        );
+    }
+}
+
+/// Recursively walk the directory tree and collect directories matching `dirs` but not `exclude`.
+///
+/// Matched directories are not descended into, so nested matches (e.g. `target/debug/build`) are
+/// covered by their parent.
+fn collect_targets(
+    path: &str,
+    dirs: &[&str],
+    opts: &CleanOptions,
+    skipped: &mut Skipped,
+) -> Result<Vec<Target>, CleanerError> {
+    if !Path::new(path).exists() {
+        return Err(CleanerError::PathNotFound(PathBuf::from(path)));
+    }
+    info!(
+        "Cleaning all directories that finished with either: {:?}, excluding: {:?}, max_depth: {}",
+        dirs, opts.exclude, opts.max_depth
+    );
+    let mut walkdir = WalkDir::new(path);
+    if opts.sorted {
+        walkdir = walkdir.sort_by_file_name();
+    }
+    if opts.max_depth > 0 {
+        walkdir = walkdir.max_depth(opts.max_depth);
+    }
+    if opts.max_open_fds > 0 {
+        // The walker keeps one handle per open directory level (10 by default)
+        walkdir = walkdir.max_open((opts.max_open_fds / 4).clamp(1, 10));
+    }
+    // Compile glob patterns for dirs and exclude
+    let dir_patterns = compile_patterns(dirs, opts.literal)?;
+    // Depth limits keyed by compiled pattern text (which differs from the entry with --literal)
+    let depth_limits: HashMap<&str, usize> = dir_patterns
+        .iter()
+        .zip(dirs)
+        .filter_map(|(pat, dir)| Some((pat.as_str(), *opts.pattern_depths.get(*dir)?)))
+        .collect();
+    // Counters are keyed by the entry as given, not its compiled text
+    let entries_by_pattern: HashMap<&str, &str> = dir_patterns
+        .iter()
+        .zip(dirs)
+        .map(|(pat, dir)| (pat.as_str(), *dir))
+        .collect();
+    let record = |pat: &Pattern| {
+        opts.pattern_matches
+            .record(entries_by_pattern[pat.as_str()])
+    };
+    let collected = |pat: &Pattern, target: &Path| {
+        opts.pattern_matches
+            .collected(target, entries_by_pattern[pat.as_str()])
+    };
+    let exclude_patterns = compile_patterns(opts.exclude, opts.literal)?;
+    let match_options = opts.match_options();
+    let canonical_root = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    // Device of the root with --one-file-system; directories on any other device are mounts
+    let root_device = opts
+        .one_file_system
+        .then(|| (opts.device_of)(Path::new(path)))
+        .flatten();
+    if opts.one_file_system && root_device.is_none() {
+        warn!(
+            "cannot tell filesystems apart for {}: --one-file-system has no effect",
+            path
+        );
+    }
+    // Cargo projects whose real target directory was resolved through `cargo metadata`
+    let mut cargo_projects: HashSet<PathBuf> = HashSet::new();
+    // Submodule directories declared by the `.gitmodules` files seen so far
+    let mut submodules: HashSet<PathBuf> = HashSet::new();
+    // Project roots enclosing the current entry with their depth and kind, innermost last
+    let mut projects: Vec<(PathBuf, usize, ProjectKind)> = Vec::new();
+    // Every project boundary seen, for --project-boundaries
+    let mut boundaries: Vec<PathBuf> = Vec::new();
+    let mut targets: Vec<Target> = Vec::new();
+    let mut entries = walkdir.into_iter();
+    while let Some(file) = entries.next() {
+        let f = match file {
+            Ok(f) => f,
+            Err(e) => {
+                // e.g. a directory without read permission: leave it out and keep walking
+                warn!("skipping {}", traversal_error(&e, path, opts));
+                continue;
+            }
+        };
+        let file_path = f.path();
+        let file_name = file_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if f.path_is_symlink() {
+            if let Some(pat) = find_match(&dir_patterns, file_path, match_options) {
+                record(pat);
+                // e.g. Bazel's bazel-* links into an output base outside the tree
+                skipped.symlinks += 1;
+                let link_target = fs::read_link(file_path).unwrap_or_default();
+                warn!(
+                    "skipping symlink {} -> {}: links are never followed, clean the target manually",
+                    display_path(file_path, path, opts.relative),
+                    link_target.display()
+                );
+                explain(
+                    opts,
+                    path,
+                    file_path,
+                    &format!("matched pattern '{}' but is a symlink, not followed", pat),
+                );
+            }
+        }
+        if f.file_type().is_file() && opts.match_files {
+            if let Some(pat) = find_match(&dir_patterns, file_path, match_options) {
+                record(pat);
+                match find_match(&exclude_patterns, file_path, match_options) {
+                    Some(ex) => {
+                        skipped.excluded += 1;
+                        explain(
+                            opts,
+                            path,
+                            file_path,
+                            &format!("matched pattern '{}' but excluded by '{}'", pat, ex),
+                        );
+                    }
+                    None => {
+                        explain(
+                            opts,
+                            path,
+                            file_path,
+                            &format!("file matched pattern '{}'", pat),
+                        );
+                        collected(pat, file_path);
+                        targets.push(Target::File(file_path.to_path_buf()));
+                    }
+                }
+            }
+        }
+        if !f.file_type().is_dir() {
+            continue;
+        }
+        if root_device.is_some() && f.depth() > 0 && (opts.device_of)(file_path) != root_device {
+            info!(
+                "not descending into {}: another filesystem is mounted there",
+                display_path(file_path, path, opts.relative)
+            );
+            explain(
+                opts,
+                path,
+                file_path,
+                "on another filesystem (--one-file-system)",
+            );
+            entries.skip_current_dir();
+            continue;
+        }
+        if opts.skip_submodules {
+            if submodules.contains(file_path) {
+                skipped.submodules += 1;
+                info!(
+                    "skipping git submodule {}",
+                    display_path(file_path, path, opts.relative)
+                );
+                explain(opts, path, file_path, "git submodule (--skip-submodules)");
+                entries.skip_current_dir();
+                continue;
+            }
+            // A directory is visited before its children, so its submodules are known in time
+            submodules.extend(git_submodules(file_path));
+        }
+        // Depth and kind of the nearest project root above this directory
+        let mut nearest_project = None;
+        if opts.project_relative_depth.is_some()
+            || opts.detected_only
+            || opts.project_roots.is_some()
+        {
+            while projects
+                .last()
+                .is_some_and(|(project, _, _)| !file_path.starts_with(project))
+            {
+                projects.pop();
+            }
+            nearest_project = projects.last().map(|(_, depth, kind)| (*depth, *kind));
+            let kind = detect_project_kind(file_path);
+            if let Some(kind) = kind {
+                projects.push((file_path.to_path_buf(), f.depth(), kind));
+            }
+            if opts.project_roots.is_some() && (kind.is_some() || file_path.join(".git").exists()) {
+                boundaries.push(file_path.to_path_buf());
+            }
+        }
+        let project_depth = nearest_project.map(|(depth, _)| depth);
+        if f.depth() == 0 {
+            // The root is never a target, even when its own name matches: removing it would
+            // delete the directory the user pointed at rather than something found inside it
+            if let Some(pat) = find_match(&dir_patterns, file_path, match_options) {
+                record(pat);
+                warn!(
+                    "{} matches '{}' but is the root path and is never removed; point cleaner at its parent to remove it",
+                    path, pat
+                );
+                explain(
+                    opts,
+                    path,
+                    file_path,
+                    &format!("matched pattern '{}' but is the root path, kept", pat),
+                );
+            }
+            continue;
+        }
+        if opts.respect_cargo_metadata && targets.iter().any(|t| t.path() == file_path) {
+            // Already collected from cargo metadata
+            entries.skip_current_dir();
+            continue;
+        }
+        if opts.respect_cargo_metadata && file_path.join("Cargo.toml").is_file() {
+            if let Some(target_dir) = cargo_target_dir(file_path) {
+                cargo_projects.insert(file_path.to_path_buf());
+                match target_dir.strip_prefix(&canonical_root) {
+                    Ok(rel) => {
+                        let target = Path::new(path).join(rel);
+                        if target.is_dir() && !targets.iter().any(|t| t.path() == target) {
+                            info!(
+                                "cargo target directory for {}: {}",
+                                file_path.display(),
+                                target.display()
+                            );
+                            explain(
+                                opts,
+                                path,
+                                &target,
+                                "cargo target directory (cargo metadata)",
+                            );
+                            targets.push(Target::Dir(target));
+                        }
+                    }
+                    Err(_) => info!(
+                        "cargo target directory {} is outside {}, not cleaning it",
+                        target_dir.display(),
+                        path
+                    ),
+                }
+            }
+        }
+        // Inside a resolved cargo project a plain `target` is not the build output
+        let shadowed_by_cargo = file_name == "target"
+            && file_path
+                .parent()
+                .is_some_and(|parent| cargo_projects.contains(parent));
+        let matched = find_match(&dir_patterns, file_path, match_options);
+        if let Some(pat) = matched {
+            record(pat);
+        }
+        match matched {
+            Some(_) if shadowed_by_cargo => explain(
+                opts,
+                path,
+                file_path,
+                "not the cargo target directory of its project",
+            ),
+            Some(pat)
+                if depth_limits
+                    .get(pat.as_str())
+                    .is_some_and(|max| f.depth() > *max) =>
+            {
+                explain(
+                    opts,
+                    path,
+                    file_path,
+                    &format!(
+                        "matched pattern '{}' but deeper than its max depth {}",
+                        pat,
+                        depth_limits[pat.as_str()]
+                    ),
+                );
+                // Leave the spared directory whole rather than cleaning inside it
+                entries.skip_current_dir();
+            }
+            Some(pat)
+                if opts
+                    .project_relative_depth
+                    .is_some_and(|max| project_depth.is_none_or(|p| f.depth() - p > max)) =>
+            {
+                let reason = match project_depth {
+                    Some(_) => format!(
+                        "matched pattern '{}' but more than {} levels below its project root",
+                        pat,
+                        opts.project_relative_depth.unwrap()
+                    ),
+                    None => format!("matched pattern '{}' but is not inside a project", pat),
+                };
+                explain(opts, path, file_path, &reason);
+                entries.skip_current_dir();
+            }
+            Some(pat)
+                if opts.detected_only
+                    && !nearest_project.is_some_and(|(_, kind)| {
+                        default_dirs_for_kind(&kind).contains(&entries_by_pattern[pat.as_str()])
+                    }) =>
+            {
+                let reason = match nearest_project {
+                    Some((_, kind)) => format!(
+                        "matched pattern '{}' but it is not a {} directory (--detected-only)",
+                        pat, kind
+                    ),
+                    None => format!(
+                        "matched pattern '{}' but is not inside a detected project (--detected-only)",
+                        pat
+                    ),
+                };
+                explain(opts, path, file_path, &reason);
+                entries.skip_current_dir();
+            }
+            Some(pat) => {
+                let excluded_by = find_match(&exclude_patterns, file_path, match_options);
+                // Only looked up for matches no exclude pattern already ruled out
+                let contained = excluded_by
+                    .is_none()
+                    .then(|| {
+                        opts.exclude_if_contains
+                            .iter()
+                            .find(|marker| file_path.join(marker).exists())
+                    })
+                    .flatten();
+                match (excluded_by, contained) {
+                    (Some(ex), _) => {
+                        skipped.excluded += 1;
+                        explain(
+                            opts,
+                            path,
+                            file_path,
+                            &format!("matched pattern '{}' but excluded by '{}'", pat, ex),
+                        );
+                    }
+                    (None, Some(marker)) => {
+                        skipped.exclude_marker += 1;
+                        explain(
+                            opts,
+                            path,
+                            file_path,
+                            &format!("matched pattern '{}' but contains '{}'", pat, marker),
+                        );
+                    }
+                    (None, None) if file_path.join(opts.keep_file).exists() => {
+                        skipped.keep_file += 1;
+                        info!(
+                            "skipping {}: contains {}",
+                            display_path(file_path, path, opts.relative),
+                            opts.keep_file
+                        );
+                        explain(
+                            opts,
+                            path,
+                            file_path,
+                            &format!(
+                                "matched pattern '{}' but contains keep file '{}'",
+                                pat, opts.keep_file
+                            ),
+                        );
+                        entries.skip_current_dir();
+                    }
+                    (None, None)
+                        if !opts.require_marker.is_empty()
+                            && !opts
+                                .require_marker
+                                .iter()
+                                .any(|marker| has_marker(file_path, marker)) =>
+                    {
+                        skipped.missing_marker += 1;
+                        explain(
+                            opts,
+                            path,
+                            file_path,
+                            &format!(
+                                "matched pattern '{}' but has no marker ({})",
+                                pat,
+                                opts.require_marker.join(", ")
+                            ),
+                        );
+                    }
+                    (None, None) => {
+                        explain(opts, path, file_path, &format!("matched pattern '{}'", pat));
+                        collected(pat, file_path);
+                        targets.push(Target::Dir(file_path.to_path_buf()));
+                        entries.skip_current_dir();
+                    }
+                }
+            }
+            None if opts.max_depth > 0 && f.depth() == opts.max_depth => explain(
+                opts,
+                path,
+                file_path,
+                "no matching pattern, at max depth so not descending",
+            ),
+            None => explain(opts, path, file_path, "no matching pattern"),
+        }
+    }
+    // A cargo target directory may have been collected after some of its children
+    targets.sort_by(|a, b| a.path().cmp(b.path()));
+    targets.dedup();
+    let mut outermost: Vec<Target> = Vec::new();
+    for target in targets {
+        if !outermost
+            .iter()
+            .any(|parent| target.path().starts_with(parent.path()))
+        {
+            outermost.push(target);
+        }
+    }
+    let mut targets = outermost;
+    if let Some(keep) = opts.keep_recent {
+        targets = keep_recent(targets, keep, path, opts, skipped);
+    }
+    if let Some(cutoff) = opts.prune_contents_before {
+        targets = prune_contents(targets, cutoff, path, opts, skipped);
+    }
+    if !opts.protected_roots.is_empty() {
+        targets.retain(|target| {
+            let target = target.path();
+            let canonical = fs::canonicalize(target).unwrap_or_else(|_| target.to_path_buf());
+            let Some(cache) = opts
+                .protected_roots
+                .iter()
+                .find(|r| canonical.starts_with(r))
+            else {
+                return true;
+            };
+            skipped.package_cache += 1;
+            warn!(
+                "skipping {}: inside package cache {} (use --allow-package-caches to clean it)",
+                display_path(target, path, opts.relative),
+                cache.display()
+            );
+            explain(
+                opts,
+                path,
+                target,
+                &format!("inside package cache {}, skipped", cache.display()),
+            );
+            false
+        });
+    }
+    if let Some(checkpoint) = opts.checkpoint {
+        targets.retain(|target| {
+            if !checkpoint.is_done(target.path()) {
+                return true;
+            }
+            skipped.resumed += 1;
+            explain(
+                opts,
+                path,
+                target.path(),
+                "listed in the checkpoint (--resume), skipped",
+            );
+            false
+        });
+    }
+    if let Some(venv) = opts.active_venv.filter(|_| !opts.force) {
+        targets.retain(|target| {
+            let target = target.path();
+            let canonical = fs::canonicalize(target).unwrap_or_else(|_| target.to_path_buf());
+            if !venv.starts_with(&canonical) {
+                return true;
+            }
+            skipped.active_venv += 1;
+            warn!(
+                "NOT removing {}: it holds the active virtualenv {} (VIRTUAL_ENV); deactivate it first or use --force",
+                display_path(target, path, opts.relative),
+                venv.display()
+            );
+            explain(opts, path, target, "holds the active virtualenv, skipped");
+            false
+        });
+    }
+    if let Some(cutoff) = opts.protect_newer_than {
+        targets.retain(|target| {
+            let target = target.path();
+            let modified = fs::metadata(target).and_then(|m| m.modified());
+            let recent = modified.is_ok_and(|m| m > cutoff);
+            if recent {
+                skipped.protected += 1;
+                info!(
+                    "skipping {}: modified recently",
+                    display_path(target, path, opts.relative)
+                );
+                explain(
+                    opts,
+                    path,
+                    target,
+                    "modified recently (--exclude-newer-than), skipped",
+                );
+            }
+            !recent
+        });
+    }
+    if opts.stale_deps_only {
+        targets.retain(|target| {
+            let target = target.path();
+            match stale_lockfile(target) {
+                Some(lockfile) => {
+                    explain(
+                        opts,
+                        path,
+                        target,
+                        &format!("older than {}, selected", lockfile.display()),
+                    );
+                    true
+                }
+                None => {
+                    skipped.not_stale += 1;
+                    explain(
+                        opts,
+                        path,
+                        target,
+                        "not a dependency directory older than its lockfile (--stale-deps-only), skipped",
+                    );
+                    false
+                }
+            }
+        });
+    }
+    if opts.skip_in_use {
+        targets = skip_in_use(targets, path, opts, skipped);
+    }
+    if let Some(project_roots) = opts.project_roots {
+        for target in &targets {
+            let target = target.path();
+            let nearest = boundaries
+                .iter()
+                .filter(|boundary| target.starts_with(boundary) && target != boundary.as_path())
+                .max_by_key(|boundary| boundary.components().count());
+            if let Some(project) = nearest {
+                project_roots.insert(target, project);
+            }
+        }
+    }
+    if opts.verify_readable {
+        targets.retain(|target| {
+            let Some((failed, error)) = first_unreadable(target.path()) else {
+                return true;
+            };
+            skipped.unreadable += 1;
+            warn!(
+                "skipping {}: {} could not be read: {}",
+                display_path(target.path(), path, opts.relative),
+                failed.display(),
+                error
+            );
+            explain(
+                opts,
+                path,
+                target.path(),
+                "not completely readable (--verify-readable), skipped",
+            );
+            false
+        });
+    }
+    Ok(targets)
+}
+
+/// Replace each matched directory by the files inside it (at any depth) last modified before
+/// `cutoff`, so the directory and its recent files stay, e.g. to keep incremental builds warm.
+/// Entries that cannot be read are left in place, warned about and counted as unreadable.
+fn prune_contents(
+    targets: Vec<Target>,
+    cutoff: SystemTime,
+    root: &str,
+    opts: &CleanOptions,
+    skipped: &mut Skipped,
+) -> Vec<Target> {
+    let mut stale = Vec::new();
+    for target in targets {
+        let Target::Dir(dir) = target else {
+            // Matched files are judged by --exclude-newer-than like any other match
+            stale.push(target);
+            continue;
+        };
+        let mut recent = 0;
+        for entry in WalkDir::new(&dir).min_depth(1) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    // Whatever could not be read stays, so the directory is only partly pruned
+                    skipped.unreadable += 1;
+                    warn!("skipping {}", traversal_error(&e, root, opts));
+                    explain(
+                        opts,
+                        root,
+                        &dir,
+                        "not completely readable, only partly pruned (--prune-contents-older-than)",
+                    );
+                    continue;
+                }
+            };
+            if entry.file_type().is_dir() {
+                continue;
+            }
+            let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+            if modified.is_some_and(|modified| modified < cutoff) {
+                stale.push(Target::File(entry.into_path()));
+            } else {
+                recent += 1;
+            }
+        }
+        if recent > 0 {
+            skipped.kept_recent += recent;
+            explain(
+                opts,
+                root,
+                &dir,
+                &format!("{} recent files kept (--prune-contents-older-than)", recent),
+            );
+        }
+    }
+    stale
+}
+
+/// Replace each matched directory by its subdirectories, minus the `keep` most recently
+/// modified ones, e.g. for hash- or timestamp-named cache entries.
+fn keep_recent(
+    targets: Vec<Target>,
+    keep: usize,
+    root: &str,
+    opts: &CleanOptions,
+    skipped: &mut Skipped,
+) -> Vec<Target> {
+    let mut expanded = Vec::new();
+    for target in targets {
+        let Target::Dir(parent) = target else {
+            // Matched files have no entries to keep
+            expanded.push(target);
+            continue;
+        };
+        let read_dir = match fs::read_dir(&parent) {
+            Ok(read_dir) => read_dir,
+            Err(e) => {
+                // Without its entries the most recent ones cannot be told apart: keep it all
+                skipped.unreadable += 1;
+                warn!(
+                    "skipping {}: cannot list it for --keep-recent: {}",
+                    display_path(&parent, root, opts.relative),
+                    e
+                );
+                explain(
+                    opts,
+                    root,
+                    &parent,
+                    &format!("could not be listed for --keep-recent ({}), kept", e),
+                );
+                continue;
+            }
+        };
+        let mut children: Vec<(SystemTime, PathBuf)> = read_dir
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+            .filter_map(|entry| {
+                let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+                Some((modified, entry.path()))
+            })
+            .collect();
+        // Newest first
+        children.sort_by(|a, b| b.cmp(a));
+        for (_, child) in children.iter().take(keep) {
+            skipped.kept_recent += 1;
+            explain(
+                opts,
+                root,
+                child,
+                &format!("one of the {} most recent (--keep-recent), kept", keep),
+            );
+        }
+        expanded.extend(
+            children
+                .into_iter()
+                .skip(keep)
+                .map(|(_, child)| Target::Dir(child)),
+        );
+    }
+    expanded
+}
+
+/// Drop targets that contain a file or working directory held open by a running process.
+fn skip_in_use(
+    targets: Vec<Target>,
+    root: &str,
+    opts: &CleanOptions,
+    skipped: &mut Skipped,
+) -> Vec<Target> {
+    let open = platform::open_paths();
+    targets
+        .into_iter()
+        .filter(|target| {
+            let target = target.path();
+            let canonical = fs::canonicalize(target).unwrap_or_else(|_| target.to_path_buf());
+            let in_use = open.iter().any(|p| p.starts_with(&canonical));
+            if in_use {
+                skipped.in_use += 1;
+                warn!(
+                    "skipping {}: in use by a running process",
+                    display_path(target, root, opts.relative)
+                );
+                explain(opts, root, target, "in use by a running process, skipped");
+            }
+            !in_use
+        })
+        .collect()
+}
+
+/// Remove (or, with dry_run, list) already collected `targets` found under `root`.
+///
+/// With `fail_fast` the first removal error aborts the run and is returned; otherwise every
+/// failure is collected in the returned report.
+fn remove_targets(
+    root: &str,
+    targets: &[Target],
+    opts: &CleanOptions,
+) -> Result<CleanReport, CleanerError> {
+    let shown = |target: &Target| display_path(target.path(), root, opts.relative);
+    let limiter = fd_limiter(opts.max_open_fds);
+    let remove = |target: &Target| {
+        if opts.deadline_passed() {
+            return Ok(Outcome::Unfinished);
+        }
+        let _permit = limiter.as_ref().map(|l| l.acquire());
+        if let Some(latency) = opts.simulate_delete_latency {
+            std::thread::sleep(latency);
+        }
+        dispose_target(target, root, opts)
+    };
+    let mut report = CleanReport::default();
+    let without_conflicts: Vec<Target>;
+    let targets = match opts.move_to {
+        Some(staging) if opts.on_conflict == OnConflict::Skip => {
+            let (existing, free): (Vec<_>, Vec<_>) = targets.iter().cloned().partition(|target| {
+                staging_path(target.path(), root, staging)
+                    .symlink_metadata()
+                    .is_ok()
+            });
+            for target in &existing {
+                warn!(
+                    "skipping {}: {} already exists",
+                    shown(target),
+                    staging_path(target.path(), root, staging).display()
+                );
+            }
+            report.skipped.conflicts = existing.len();
+            without_conflicts = free;
+            &without_conflicts[..]
+        }
+        _ => targets,
+    };
+    if let Some(staging) = opts.move_to.filter(|_| !opts.dry_run) {
+        if same_device(Path::new(root), staging) == Some(false) && !targets.is_empty() {
+            let total: u64 = targets
+                .par_iter()
+                .map(|target| target.size(opts.size_cache))
+                .sum();
+            warn!(
+                "{} is on another filesystem than {}: {} will be copied and then deleted, which is much slower than a rename",
+                staging.display(),
+                root,
+                format_size(total, opts.units)
+            );
+        }
+    }
+    if opts.dry_run {
+        let (mut hidden, mut hidden_bytes) = (0, 0);
+        for (index, target) in targets.iter().enumerate() {
+            let size = target.size(opts.size_cache);
+            report.record(
+                target,
+                &opts.owner_of(target.path()),
+                Outcome::Removed(size),
+            );
+            if let Some(pattern) = opts.pattern_matches.pattern_of(target.path()) {
+                let (directories, files) = match target {
+                    Target::Dir(_) => (1, 0),
+                    Target::File(_) => (0, 1),
+                };
+                report
+                    .patterns
+                    .entry(pattern)
+                    .or_default()
+                    .add(PatternTotals {
+                        directories,
+                        files,
+                        bytes: size,
+                    });
+            }
+            if opts.preview_limit > 0 && index >= opts.preview_limit {
+                hidden += 1;
+                hidden_bytes += size;
+                continue;
+            }
+            match opts.move_to {
+                Some(staging) => println!(
+                    "Would move: {} -> {}",
+                    shown(target),
+                    staging_path(target.path(), root, staging).display()
+                ),
+                None => match opts.archive_to {
+                    Some(archive_dir) => println!(
+                        "Would archive and remove: {} -> {}",
+                        shown(target),
+                        archive_path(target.path(), root, archive_dir).display()
+                    ),
+                    None => println!("Would remove: {}", shown(target)),
+                },
+            }
+        }
+        if hidden > 0 {
+            println!(
+                "... and {} more (total {})",
+                format_count(hidden as u64, opts.locale),
+                format_size(hidden_bytes, opts.units)
+            );
+        }
+    } else if opts.interactive && !opts.force {
+        use std::io::Write;
+        for target in targets {
+            if opts.deadline_passed() {
+                report.record(target, &opts.owner_of(target.path()), Outcome::Unfinished);
+                continue;
+            }
+            let confirmed = opts.confirm_all.load(Ordering::Relaxed) || {
+                print!(
+                    "Delete {} ({})? [y/N/a]: ",
+                    shown(target),
+                    format_size(target.size(opts.size_cache), opts.units)
+                );
+                io::stdout().flush().unwrap();
+                let mut input = String::new();
+                io::stdin().read_line(&mut input).unwrap();
+                let input = input.trim().to_lowercase();
+                if input == "a" || input == "all" {
+                    opts.confirm_all.store(true, Ordering::Relaxed);
+                }
+                matches!(input.as_str(), "y" | "yes" | "a" | "all")
+            };
+            if confirmed {
+                match dispose_target(target, root, opts) {
+                    Ok(outcome) => report.record(target, &opts.owner_of(target.path()), outcome),
+                    Err(e) if opts.fail_fast => return Err(e),
+                    Err(failure) => report.failures.push(failure),
+                }
+            } else {
+                report.skipped.declined += 1;
+                println!("Skipped: {}", shown(target));
+            }
+        }
+    } else if opts.fail_fast {
+        // try_fold/try_reduce stop scheduling new removals as soon as one fails
+        report = targets
+            .par_iter()
+            .try_fold(CleanReport::default, |mut report, target| {
+                report.record(target, &opts.owner_of(target.path()), remove(target)?);
+                Ok(report)
+            })
+            .try_reduce(CleanReport::default, |a, b| Ok(a.merge(b)))?;
+    } else {
+        let results: Vec<_> = targets.par_iter().map(remove).collect();
+        for (target, result) in targets.iter().zip(results) {
+            match result {
+                Ok(outcome) => report.record(target, &opts.owner_of(target.path()), outcome),
+                Err(failure) => report.failures.push(failure),
+            }
+        }
+    }
+    if opts.prune_empty_parents && !opts.dry_run {
+        report.pruned = prune_empty_parents(root, targets, opts);
+    }
+    if let Some(command) = opts.post_command.filter(|_| !opts.dry_run) {
+        run_post_command(command, root, targets, opts);
+    }
+    Ok(report)
+}
+
+/// Run the kind's `post_command` once in every project directory (see
+/// [`CleanOptions::project_of`]) that had a target removed, e.g. to recreate a `.venv`. A
+/// failing command is logged and does not fail the run.
+fn run_post_command(command: &str, root: &str, targets: &[Target], opts: &CleanOptions) {
+    let projects: BTreeSet<PathBuf> = targets
+        .iter()
+        .map(Target::path)
+        .filter(|path| !path.exists())
+        .map(|path| opts.project_of(path))
+        .filter(|project| project.is_dir())
+        .collect();
+    for project in &projects {
+        let shown = display_path(project, root, opts.relative);
+        info!("running post_command in {}: {}", shown, command);
+        let status = if cfg!(windows) {
+            std::process::Command::new("cmd")
+                .args(["/C", command])
+                .current_dir(project)
+                .status()
+        } else {
+            std::process::Command::new("sh")
+                .args(["-c", command])
+                .current_dir(project)
+                .status()
+        };
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => warn!("post_command in {} failed: {}", shown, status),
+            Err(e) => warn!("could not run post_command in {}: {}", shown, e),
+        }
+    }
+}
+
+/// Remove the parents of removed `targets` that are now empty, walking upwards and stopping at
+/// the first non-empty parent or at `root`. Returns the number of directories removed.
+fn prune_empty_parents(root: &str, targets: &[Target], opts: &CleanOptions) -> usize {
+    let root = Path::new(root);
+    let mut pruned = 0;
+    // Deepest first, so a parent shared by several targets is only checked once it can be empty
+    let mut removed: Vec<&Path> = targets
+        .iter()
+        .map(Target::path)
+        .filter(|path| !path.exists())
+        .collect();
+    removed.sort_by_key(|path| std::cmp::Reverse(path.components().count()));
+    for path in removed {
+        let mut parent = path.parent();
+        while let Some(dir) = parent.filter(|dir| dir.starts_with(root) && *dir != root) {
+            // remove_dir only succeeds on empty directories
+            if fs::remove_dir(dir).is_err() {
+                break;
+            }
+            info!(
+                "pruned empty parent: {}",
+                display_path(dir, &root.to_string_lossy(), opts.relative)
+            );
+            pruned += 1;
+            parent = dir.parent();
+        }
+    }
+    pruned
+}
+
+/// Header written at the top of a --review file. Lines starting with `#` are ignored on read-back.
+const REVIEW_HEADER: &str = "\
+# cleaner review: the directories below will be removed.
+# Delete the lines of any directory you want to keep, save, then press Enter in the terminal.
+# Format: <path><TAB><size>
+";
+
+/// Write `targets` to the review file at `file`, wait for the user to edit it, and return only
+/// the targets still listed afterwards. Paths added to the file that were not found by the scan
+/// are ignored, so the review can narrow the selection but never widen it.
+///
+/// Returns `None` if the user aborted (stdin closed before Enter was pressed).
+fn review_targets<'r>(
+    file: &Path,
+    targets: Vec<(&'r str, Target)>,
+    opts: &CleanOptions,
+) -> Result<Option<Vec<(&'r str, Target)>>, CleanerError> {
+    use std::io::Write;
+    let review_error = |source| CleanerError::ReviewFile {
+        path: file.to_path_buf(),
+        source,
+    };
+    let mut contents = String::from(REVIEW_HEADER);
+    for (_, target) in &targets {
+        contents.push_str(&format!(
+            "{}\t{}\n",
+            target.path().display(),
+            format_size(target.size(opts.size_cache), opts.units)
+        ));
+    }
+    fs::write(file, contents).map_err(review_error)?;
+    print!(
+        "Wrote {} directories to {}. Edit it, then press Enter to continue: ",
+        targets.len(),
+        file.display()
+    );
+    io::stdout().flush().unwrap();
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+        return Ok(None);
+    }
+    let edited = fs::read_to_string(file).map_err(review_error)?;
+    let listed: HashSet<PathBuf> = edited
+        .lines()
+        .map(|line| line.split('\t').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect();
+    let known: HashSet<&Path> = targets.iter().map(|(_, target)| target.path()).collect();
+    for path in listed.iter().filter(|path| !known.contains(path.as_path())) {
+        warn!(
+            "Ignoring {} from review file: not found by the scan",
+            path.display()
+        );
+    }
+    let (kept, dropped): (Vec<_>, Vec<_>) = targets
+        .into_iter()
+        .partition(|(_, target)| listed.contains(target.path()));
+    for (root, target) in &dropped {
+        println!(
+            "Keeping: {}",
+            display_path(target.path(), root, opts.relative)
+        );
+    }
+    Ok(Some(kept))
+}
+
+/// One project in a --confirm-per-project-with-sizes request: the directory containing the
+/// matches, identified by its path.
+#[derive(Serialize)]
+struct ConfirmationGroup {
+    id: String,
+    bytes: u64,
+    targets: Vec<ConfirmationTarget>,
+}
+
+#[derive(Serialize)]
+struct ConfirmationTarget {
+    path: String,
+    bytes: u64,
+}
+
+/// The JSON line written to stdout by --confirm-per-project-with-sizes.
+#[derive(Serialize)]
+struct ConfirmationRequest {
+    groups: Vec<ConfirmationGroup>,
+    total_bytes: u64,
+}
+
+/// The JSON line a frontend answers with: the ids of the groups to delete.
+#[derive(Deserialize)]
+struct ConfirmationDecision {
+    delete: Vec<String>,
+}
+
+/// Print `targets`, grouped by project, as a JSON confirmation request and read the frontend's
+/// decision from stdin, returning only the targets of approved groups. Group ids the request did
+/// not contain are ignored, so a decision can narrow the selection but never widen it.
+///
+/// Returns `None` if stdin closed before a decision was sent.
+fn confirm_groups_json<'r>(
+    targets: Vec<(&'r str, Target)>,
+    opts: &CleanOptions,
+) -> Result<Option<Vec<(&'r str, Target)>>, CleanerError> {
+    use std::io::Write;
+    let group_id = |target: &Target| opts.project_of(target.path()).display().to_string();
+    let sizes: Vec<u64> = targets
+        .par_iter()
+        .map(|(_, target)| target.size(opts.size_cache))
+        .collect();
+    let mut groups: BTreeMap<String, ConfirmationGroup> = BTreeMap::new();
+    for ((_, target), bytes) in targets.iter().zip(sizes) {
+        let id = group_id(target);
+        let group = groups
+            .entry(id.clone())
+            .or_insert_with(|| ConfirmationGroup {
+                id,
+                bytes: 0,
+                targets: Vec::new(),
+            });
+        group.bytes += bytes;
+        group.targets.push(ConfirmationTarget {
+            path: target.path().display().to_string(),
+            bytes,
+        });
+    }
+    let request = ConfirmationRequest {
+        total_bytes: groups.values().map(|group| group.bytes).sum(),
+        groups: groups.into_values().collect(),
+    };
+    println!("{}", serde_json::to_string(&request).unwrap());
+    io::stdout().flush().unwrap();
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+        return Ok(None);
+    }
+    let decision: ConfirmationDecision =
+        serde_json::from_str(&input).map_err(|e| CleanerError::InvalidDecision {
+            message: e.to_string(),
+        })?;
+    let approved: HashSet<&str> = decision.delete.iter().map(String::as_str).collect();
+    for id in &approved {
+        if !request.groups.iter().any(|group| group.id == *id) {
+            warn!("Ignoring unknown group {} in decision", id);
+        }
+    }
+    Ok(Some(
+        targets
+            .into_iter()
+            .filter(|(_, target)| approved.contains(group_id(target).as_str()))
+            .collect(),
+    ))
+}
+
+/// Remove every directory under `path` that contains no files, however deeply nested, ignoring
+/// the name patterns. Directories are removed bottom-up with `remove_dir`, so a directory that
+/// gained content in the meantime is never deleted.
+fn clean_empty_directories(path: &str, opts: &CleanOptions) -> Result<CleanReport, CleanerError> {
+    if !Path::new(path).exists() {
+        return Err(CleanerError::PathNotFound(PathBuf::from(path)));
+    }
+    info!(
+        "Cleaning empty directories, excluding: {:?}, max_depth: {}",
+        opts.exclude, opts.max_depth
+    );
+    let exclude_patterns = compile_patterns(opts.exclude, opts.literal)?;
+    let mut walkdir = WalkDir::new(path).contents_first(true);
+    if opts.sorted {
+        walkdir = walkdir.sort_by_file_name();
+    }
+    if opts.max_depth > 0 {
+        walkdir = walkdir.max_depth(opts.max_depth);
+    }
+    // Directories that (transitively) contain a file, or must be kept, plus their ancestors
+    let mut occupied: HashSet<PathBuf> = HashSet::new();
+    let mut mark_occupied = |entry: &Path| {
+        for ancestor in entry.ancestors() {
+            if !occupied.insert(ancestor.to_path_buf()) || ancestor == Path::new(path) {
+                break;
+            }
+        }
+    };
+    // With contents_first, children are yielded before their parent
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    for entry in walkdir {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                // Its contents are unknown, so it is not empty
+                if let Some(unreadable) = e.path() {
+                    mark_occupied(unreadable);
+                }
+                warn!("skipping {}", traversal_error(&e, path, opts));
+                continue;
+            }
+        };
+        let entry_path = entry.path();
+        if !entry.file_type().is_dir() {
+            mark_occupied(entry_path);
+        } else if entry.depth() == 0 {
+            continue;
+        } else if find_match(&exclude_patterns, entry_path, opts.match_options()).is_some() {
+            mark_occupied(entry_path);
+        } else if entry.depth() == opts.max_depth
+            && fs::read_dir(entry_path).map_or(true, |mut d| d.next().is_some())
+        {
+            // Contents below the depth limit were not visited
+            mark_occupied(entry_path);
+        } else {
+            candidates.push(entry_path.to_path_buf());
+        }
+    }
+    let mut report = CleanReport::default();
+    let mut hidden = 0;
+    for dir in candidates.iter().filter(|d| !occupied.contains(*d)) {
+        let shown = display_path(dir, path, opts.relative);
+        if opts.dry_run {
+            if opts.preview_limit > 0 && report.directories >= opts.preview_limit {
+                hidden += 1;
+            } else {
+                println!("Would remove: {}", shown);
+            }
+            report.directories += 1;
+            continue;
+        }
+        if opts.deadline_passed() {
+            report.unfinished += 1;
+            continue;
+        }
+        info!("removing empty directory: {}", shown);
+        match fs::remove_dir(dir) {
+            Ok(()) => report.directories += 1,
+            Err(source) => {
+                let failure = CleanerError::Removal {
+                    path: dir.clone(),
+                    source,
+                };
+                if opts.fail_fast {
+                    return Err(failure);
+                }
+                error!("{}", failure);
+                report.failures.push(failure);
+            }
+        }
+    }
+    if hidden > 0 {
+        println!("... and {} more", format_count(hidden as u64, opts.locale));
+    }
+    Ok(report)
+}
+
+/// Group matched directories with identical content and print how much space deduplicating would save.
+///
+/// This is a read-only analysis: nothing is removed.
+/// Each target is given with the root it was found under.
+fn report_duplicates(targets: &[(&str, PathBuf)], opts: &CleanOptions) {
+    let limiter = fd_limiter(opts.max_open_fds);
+    let fingerprints: Vec<_> = targets
+        .par_iter()
+        .filter_map(|(root, path)| {
+            let _permit = limiter.as_ref().map(|l| l.acquire());
+            dir_fingerprint(path).map(|fp| (fp, display_path(path, root, opts.relative)))
+        })
+        .collect();
+    let mut groups: HashMap<(u64, u64), Vec<String>> = HashMap::new();
+    for ((hash, size), path) in fingerprints {
+        // Empty directories are trivially identical and not worth reporting
+        if size > 0 {
+            groups.entry((hash, size)).or_default().push(path);
+        }
+    }
+    let mut duplicates: Vec<_> = groups
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect();
+    // Largest potential savings first
+    duplicates.sort_by_key(|((_, size), paths)| std::cmp::Reverse(size * (paths.len() as u64 - 1)));
+    let mut savings = 0u64;
+    for (i, ((_, size), paths)) in duplicates.iter_mut().enumerate() {
+        paths.sort();
+        println!(
+            "Duplicate group {} ({} directories, {} each):",
+            format_count(i as u64 + 1, opts.locale),
+            format_count(paths.len() as u64, opts.locale),
+            format_size(*size, opts.units)
+        );
+        for path in paths.iter() {
+            println!("  - {}", path);
+        }
+        savings += *size * (paths.len() as u64 - 1);
+    }
+    println!(
+        "Found {} duplicate groups. Potential savings by deduplicating: {}",
+        format_count(duplicates.len() as u64, opts.locale),
+        format_size(savings, opts.units)
+    );
+}
+
+/// Buckets of --age-histogram: a label and the exclusive upper bound of the age in days, the
+/// last bucket being unbounded.
+const AGE_BUCKETS: [(&str, Option<u64>); 3] = [
+    ("< 1 week", Some(7)),
+    ("1-4 weeks", Some(28)),
+    ("> 4 weeks", None),
+];
+
+/// Reclaimable space of the matches in one --age-histogram bucket.
+#[derive(Serialize)]
+struct AgeBucket {
+    label: &'static str,
+    directories: usize,
+    bytes: u64,
+}
+
+/// Sum the sizes of `targets` per age bucket, the age being the time since each target's last
+/// modification as of `now`. Targets whose modification time cannot be read are left out.
+fn age_histogram(targets: &[Target], now: SystemTime, opts: &CleanOptions) -> Vec<AgeBucket> {
+    let aged: Vec<(u64, u64)> = targets
+        .par_iter()
+        .filter_map(|target| {
+            let modified = fs::metadata(target.path())
+                .and_then(|m| m.modified())
+                .ok()?;
+            // A modification time in the future counts as brand new
+            let age = now.duration_since(modified).unwrap_or_default();
+            Some((age.as_secs() / 86_400, target.size(opts.size_cache)))
+        })
+        .collect();
+    let mut buckets: Vec<AgeBucket> = AGE_BUCKETS
+        .iter()
+        .map(|(label, _)| AgeBucket {
+            label,
+            directories: 0,
+            bytes: 0,
+        })
+        .collect();
+    for (days, size) in aged {
+        let index = AGE_BUCKETS
+            .iter()
+            .position(|(_, below)| below.is_none_or(|below| days < below))
+            .unwrap();
+        buckets[index].directories += 1;
+        buckets[index].bytes += size;
+    }
+    buckets
+}
+
+/// Print --age-histogram buckets as a text bar chart scaled to the largest bucket.
+fn print_age_histogram(buckets: &[AgeBucket], units: Units, locale: SummaryLocale) {
+    const WIDTH: u64 = 30;
+    let largest = buckets.iter().map(|b| b.bytes).max().unwrap_or(0).max(1);
+    for bucket in buckets {
+        let bar = "#".repeat((bucket.bytes * WIDTH).div_ceil(largest) as usize);
+        println!(
+            "{:<10} {:<30} {} ({} directories)",
+            bucket.label,
+            bar,
+            format_size(bucket.bytes, units),
+            format_count(bucket.directories as u64, locale)
+        );
+    }
+}
+
+/// Tally matched targets by their depth below `root` (1 for a direct child), for --depth-report.
+fn depth_counts(root: &str, targets: &[Target], counts: &mut BTreeMap<usize, usize>) {
+    for target in targets {
+        let relative = target.path().strip_prefix(root).unwrap_or(target.path());
+        *counts.entry(relative.components().count()).or_default() += 1;
+    }
+}
+
+/// Main entry point for the Cleaner CLI tool.
+///
+/// Parses command-line arguments, sets up logging, determines which directories to clean,
+/// and recursively removes them from the specified root path.
+///
+/// # Returns
+/// * `Result<()>` - Ok on success, error if the path does not exist or a removal fails.
+fn main() {
+    use clap::error::ErrorKind;
+    match Args::try_parse() {
+        Ok(args) => {
+            if let Err(e) = run(args) {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        Err(e) => match e.kind() {
+            ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => {
+                e.print().expect("Failed to print help/version");
+                std::process::exit(0);
+            }
+            _ => {
+                eprintln!("Cleaner v{}", env!("CARGO_PKG_VERSION"));
+                eprintln!("Build: {}", env!("BUILD_DATE"));
+                e.print().expect("Failed to print error");
+                std::process::exit(1);
+            }
+        },
+    }
+}
+
+/// Run one cleaning session for the parsed `args`.
+///
+/// Everything here is synchronous: the work is blocking filesystem calls, which rayon spreads
+/// over a thread pool. Nothing awaits anything, so there is no async runtime.
+fn run(mut args: Args) -> Result<()> {
+    let started = Instant::now();
+    if let Some(shell) = args.generate_completions {
+        let mut script = Vec::new();
+        clap_complete::generate(
+            clap_complete::Shell::from(shell),
+            &mut Args::command(),
+            "cleaner",
+            &mut script,
+        );
+        return match io::stdout().write_all(&script) {
+            // e.g. piped into `head`, which stops reading early
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            written => Ok(written?),
+        };
+    }
+    // Set up logger with thread info and user-specified log level
+    setup_logger(true, Some(&args.log), args.log_file.as_deref());
+    // Before any rayon work, so the pool's threads inherit the lower priority
+    if args.low_priority {
+        match platform::lower_priority() {
+            Ok(()) => info!("running with idle I/O priority and niceness 19"),
+            Err(e) => warn!("could not lower priority: {}", e),
+        }
+    }
+    // Before any rayon work, as the global pool can only be configured once
+    if args.auto_concurrency {
+        auto_concurrency(&args.paths);
+    }
+    // Load config if provided
+    let config = args.config.as_deref().map(load_config).transpose()?;
+    validate_kind_keys(&args, &config)?;
+    if let Some(window) = &args.only_between {
+        let window = TimeWindow::parse(window)?;
+        let now = match &args.clock {
+            Some(clock) => parse_time_of_day(clock)?,
+            None => Local::now().time(),
+        };
+        if !window.contains(now) && !args.dry_run {
+            warn!(
+                "{} is outside the --only-between window {}: reporting only, nothing is deleted",
+                now.format("%H:%M"),
+                window
+            );
+            args.dry_run = true;
+        }
+    }
+    if args.atomic && args.move_to.is_none() && args.archive_to.is_none() {
+        return Err(eyre!(
+            "--atomic only applies to --move-to and --archive-to, which transfer each target"
+        ));
+    }
+    if args.list_kinds {
+        list_kinds(&config);
+        return Ok(());
+    }
+    let roots = if args.workspace_members {
+        let mut members = Vec::new();
+        for root in &args.paths {
+            let found = workspace_members(Path::new(root))?;
+            info!("cargo workspace {}: {} members", root, found.len());
+            members.extend(found.into_iter().map(|m| m.to_string_lossy().into_owned()));
+        }
+        members
+    } else {
+        args.paths.clone()
+    };
+    let mut roots: Vec<String> = roots.iter().map(|root| resolve_root(root)).collect();
+    if args.roots_stdin {
+        let listed = read_roots(io::stdin().lock(), &roots);
+        info!("read {} roots from stdin", listed.len());
+        roots.extend(listed);
+    }
+    // --units, then the config, then binary
+    let units = args
+        .units
+        .or_else(|| {
+            config
+                .as_ref()
+                .and_then(|cfg| cfg.general.as_ref())
+                .and_then(|general| general.units)
+        })
+        .unwrap_or_default();
+    let prune_contents_before = match &args.prune_contents_older_than {
+        Some(age) => Some(SystemTime::now() - parse_duration(age)?),
+        None => None,
+    };
+    let protect_newer_than = match &args.exclude_newer_than {
+        Some(age) => Some(SystemTime::now() - parse_duration(age)?),
+        None => None,
+    };
+    let deadline = match &args.max_runtime {
+        Some(budget) => Some(started + parse_duration(budget)?),
+        None => None,
+    };
+    // Determine which directories to clean
+    let mut dirs = determine_dirs_to_clean(&args, &config)?;
+    if args.infer_from_ignores {
+        let known = dirs_for_kinds(all_kinds());
+        for root in &roots {
+            let inferred = dirs_from_ignore_files(Path::new(root), &known, args.max_depth);
+            if !inferred.is_empty() {
+                info!(
+                    "inferred from ignore files under {}: {}",
+                    root,
+                    inferred.join(", ")
+                );
+            }
+            for dir in inferred {
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+    }
+    let pattern_depths = determine_pattern_depths(&args, &config);
+    // Parse exclude list
+    let exclude = determine_exclude(&args, &config);
+    if args.print_config {
+        let effective = effective_config(&args, &config, dirs, exclude);
+        if args.ci {
+            println!("{}", serde_json::to_string_pretty(&effective)?);
+        } else {
+            print!("{}", toml::to_string(&effective)?);
+        }
+        return Ok(());
+    }
+    let dirs = dirs.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let exclude = exclude.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let force = args.force || args.ci;
+    let cache_path = size_cache_path();
+    if args.clear_cache {
+        match fs::remove_file(&cache_path) {
+            Ok(()) => info!("cleared size cache {}", cache_path.display()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => warn!("could not clear size cache {}: {}", cache_path.display(), e),
+        }
+        if roots.is_empty() {
+            return Ok(());
+        }
+    }
+    if let Some(staging) = args.move_to.as_deref().filter(|_| !args.dry_run) {
+        let crossing = roots
+            .iter()
+            .find(|root| same_device(Path::new(root), Path::new(staging)) == Some(false));
+        if let (Some(root), false) = (crossing, args.allow_cross_device) {
+            return Err(eyre!(
+                "--move-to {} is on another filesystem than {}: every directory would be copied \
+                 and then deleted instead of renamed; pass --allow-cross-device to do it anyway",
+                staging,
+                root
+            ));
+        }
+    }
+    let size_cache = args.cache.then(|| SizeCache::load(&cache_path));
+    let checkpoint = match &args.checkpoint {
+        Some(file) if !args.dry_run => Some(Checkpoint::open(Path::new(file), args.resume)?),
+        _ => None,
+    };
+    let pattern_matches = PatternMatches::new();
+    let project_roots = ProjectRoots::default();
+    let active_venv = std::env::var_os("VIRTUAL_ENV")
+        .filter(|venv| !venv.is_empty())
+        .map(|venv| fs::canonicalize(&venv).unwrap_or_else(|_| PathBuf::from(venv)));
+    let protected = if args.allow_package_caches {
+        Vec::new()
+    } else {
+        let extra = config
+            .as_ref()
+            .and_then(|cfg| cfg.general.as_ref())
+            .and_then(|general| general.always_exclude.clone())
+            .unwrap_or_default();
+        protected_roots(&extra)
+    };
+    let opts = CleanOptions {
+        dry_run: args.dry_run,
+        exclude: &exclude,
+        max_depth: args.max_depth,
+        project_relative_depth: args.project_relative_depth,
+        detected_only: args.detected_only,
+        interactive: args.interactive,
+        confirm_all: AtomicBool::new(false),
+        force,
+        fail_fast: args.fail_fast,
+        skip_in_use: args.skip_in_use,
+        verify_readable: args.verify_readable,
+        respect_cargo_metadata: args.respect_cargo_metadata,
+        relative: args.relative,
+        max_open_fds: args.max_open_fds,
+        simulate_delete_latency: args.simulate_delete_latency.map(Duration::from_millis),
+        no_op_delete: args.no_op_delete,
+        retries: args.retries,
+        deadline,
+        units,
+        locale: args.summary_locale,
+        protect_newer_than,
+        explain: args.explain,
+        case_insensitive: case_insensitive(&args, &config),
+        keep_file: &args.keep_file,
+        exclude_if_contains: &args.exclude_if_contains,
+        require_marker: &args.require_marker,
+        skip_submodules: args.skip_submodules,
+        one_file_system: args.one_file_system,
+        device_of: device_id,
+        preview_limit: args.preview_limit,
+        post_command: configured_post_command(&args, &config),
+        sorted: args.sorted,
+        keep_recent: args.keep_recent,
+        prune_contents_before,
+        // The cruft kind consists of file patterns only
+        match_files: args.match_files
+            || (args.dirs.is_none() && effective_kind(&args, &config) == ProjectKind::Cruft),
+        stale_deps_only: args.stale_deps_only,
+        group_by_language: args.group_by_language,
+        literal: args.literal,
+        move_to: args.move_to.as_deref().map(Path::new),
+        archive_to: args.archive_to.as_deref().map(Path::new),
+        atomic: args.atomic,
+        on_conflict: args.on_conflict,
+        pattern_depths: &pattern_depths,
+        protected_roots: &protected,
+        active_venv: active_venv.as_deref(),
+        prune_empty_parents: args.prune_empty_parents,
+        size_cache: size_cache.as_ref(),
+        checkpoint: checkpoint.as_ref(),
+        project_roots: args.project_boundaries.then_some(&project_roots),
+        pattern_matches: &pattern_matches,
+    };
+    // Duplicate analysis is read-only, so no confirmation is needed
+    if args.find_duplicates {
+        let mut targets = Vec::new();
+        for root in &roots {
+            let found = collect_targets(root, &dirs, &opts, &mut Skipped::default())?;
+            // Only directories can be compared by content
+            targets.extend(found.into_iter().filter_map(|target| match target {
+                Target::Dir(path) => Some((root.as_str(), path)),
+                Target::File(_) => None,
+            }));
+        }
+        report_duplicates(&targets, &opts);
+        return Ok(());
+    }
+    // Like duplicate analysis, the age histogram only reports
+    if args.age_histogram {
+        let mut targets = Vec::new();
+        for root in &roots {
+            targets.extend(collect_targets(
+                root,
+                &dirs,
+                &opts,
+                &mut Skipped::default(),
+            )?);
+        }
+        let buckets = age_histogram(&targets, SystemTime::now(), &opts);
+        save_size_cache(size_cache.as_ref());
+        if args.ci {
+            println!("{}", serde_json::to_string(&buckets).unwrap());
+        } else {
+            print_age_histogram(&buckets, units, args.summary_locale);
+        }
+        return Ok(());
+    }
+    if args.depth_report {
+        let mut counts = BTreeMap::new();
+        for root in &roots {
+            let targets = collect_targets(root, &dirs, &opts, &mut Skipped::default())?;
+            depth_counts(root, &targets, &mut counts);
+        }
+        if args.ci {
+            println!("{}", serde_json::to_string(&counts).unwrap());
+        } else {
+            for (depth, count) in &counts {
+                println!(
+                    "depth {}: {}",
+                    depth,
+                    format_count(*count as u64, args.summary_locale)
+                );
+            }
+        }
+        return Ok(());
+    }
+    if let Some(file) = &args.list_to {
+        let mut listing = String::new();
+        for root in &roots {
+            for target in collect_targets(root, &dirs, &opts, &mut Skipped::default())? {
+                listing.push_str(&display_path(target.path(), root, opts.relative));
+                listing.push('\n');
+            }
+        }
+        if file == "-" {
+            print!("{}", listing);
+        } else {
+            fs::write(file, listing).map_err(|source| CleanerError::ReportFile {
+                path: PathBuf::from(file),
+                source,
+            })?;
+        }
+        return Ok(());
+    }
+    if args.write_baseline.is_some() || args.baseline.is_some() {
+        let current = baseline_entries(&roots, &dirs, &opts)?;
+        save_size_cache(opts.size_cache);
+        if let Some(file) = &args.write_baseline {
+            let json = serde_json::to_string_pretty(&current).unwrap();
+            fs::write(file, json).map_err(|source| CleanerError::ReportFile {
+                path: PathBuf::from(file),
+                source,
+            })?;
+            println!(
+                "Wrote {} matches to {}.",
+                format_count(current.entries.len() as u64, args.summary_locale),
+                file
+            );
+        }
+        if let Some(file) = &args.baseline {
+            report_baseline_diff(&args, file, &current, units)?;
+        }
+        return Ok(());
+    }
+    // Everything below may delete, so overlapping runs on the same root are kept apart. The
+    // locks are released when they go out of scope at the end of the run.
+    let keep_going = args.keep_going_across_roots;
+    let mut root_failures: Vec<RootFailure> = Vec::new();
+    let _locks = if args.dry_run {
+        Vec::new()
+    } else {
+        per_root(
+            &roots,
+            false,
+            keep_going.then_some(&mut root_failures),
+            |root| lock_root(Path::new(root), args.wait),
+        )?
+    };
+    // Roots that could not be locked are left out of the rest of the run
+    let root_count = roots.len();
+    roots.retain(|root| !root_failures.iter().any(|failure| &failure.root == root));
+    // The review file or a JSON frontend replaces the confirmation prompt
+    if args.review.is_some() || args.confirm_per_project_with_sizes {
+        let mut targets = Vec::new();
+        let mut skipped = Skipped::default();
+        for root in &roots {
+            let found = collect_targets(root, &dirs, &opts, &mut skipped)?;
+            targets.extend(found.into_iter().map(|target| (root.as_str(), target)));
+        }
+        let found = targets.len();
+        check_found(&args, found)?;
+        let decided = match &args.review {
+            Some(review) => review_targets(Path::new(review), targets, &opts)?,
+            None => confirm_groups_json(targets, &opts)?,
+        };
+        let Some(approved) = decided else {
+            println!("Aborted by user.");
+            return Ok(());
+        };
+        // Lines removed from the review file and rejected groups count as declined
+        skipped.declined += found - approved.len();
+        let mut report = CleanReport {
+            skipped,
+            ..CleanReport::default()
+        };
+        let removal_started = Instant::now();
+        let free_before = audit_free_space(&args, &roots);
+        for root in &roots {
+            let listed: Vec<Target> = approved
+                .iter()
+                .filter(|(r, _)| r == root)
+                .map(|(_, target)| target.clone())
+                .collect();
+            report = report.merge(remove_targets(root, &listed, &opts)?);
+        }
+        report.removal_time = removal_started.elapsed();
+        report.free_space = free_before.zip(audit_free_space(&args, &roots));
+        save_size_cache(size_cache.as_ref());
+        warn_unmatched(&args, &config, &pattern_matches);
+        return print_summary(&args, report, units, started.elapsed());
+    }
+    let prompt = !(force || args.dry_run || args.ci);
+    if prompt && args.roots_stdin {
+        return Err(eyre!(
+            "--roots-stdin reads stdin, so it cannot prompt: add --force, --dry-run or --ci"
+        ));
+    }
+    let parallel = args.parallel_roots && !(args.interactive && prompt);
+    if args.only_empty {
+        if prompt
+            && !confirm_deletion(
+                &["<empty directories>".to_string()],
+                "",
+                args.confirm_phrase.as_deref(),
+            )
+        {
+            println!("Aborted by user.");
+            return Ok(());
+        }
+        let removal_started = Instant::now();
+        let free_before = audit_free_space(&args, &roots);
+        let reports = per_root(
+            &roots,
+            parallel,
+            keep_going.then_some(&mut root_failures),
+            |root| clean_empty_directories(root, &opts),
+        )?;
+        let mut report = reports
+            .into_iter()
+            .fold(CleanReport::default(), CleanReport::merge);
+        report.removal_time = removal_started.elapsed();
+        report.roots = root_count;
+        report.root_failures = root_failures;
+        report.free_space = free_before.zip(audit_free_space(&args, &roots));
+        let found = report.directories;
+        print_summary(&args, report, units, started.elapsed())?;
+        return check_found(&args, found);
+    }
+    // Scan every root first, so the confirmation lists what will actually be removed
+    let mut scans = per_root(
+        &roots,
+        parallel,
+        keep_going.then_some(&mut root_failures),
+        |root| {
+            let mut skipped = Skipped::default();
+            let targets = collect_targets(root, &dirs, &opts, &mut skipped)?;
+            Ok((root.as_str(), targets, skipped))
+        },
+    )?;
+    // --free-target keeps only the largest matches needed to reach the goal; without roots
+    // there is nothing to choose from
+    let free_target = match (args.free_target, roots.first()) {
+        (Some(goal), Some(root)) => {
+            let volume = Path::new(root);
+            let available = available_space(volume)
+                .map_err(|e| eyre!("cannot query free space for --free-target: {}", e))?;
+            let (candidates, elsewhere) = on_volume(
+                scans.iter().flat_map(|(_, targets, _)| targets),
+                volume,
+                device_id,
+            );
+            if elsewhere > 0 {
+                println!(
+                    "Leaving {} matches alone: they are not on the filesystem of {}.",
+                    format_count(elsewhere as u64, args.summary_locale),
+                    root
+                );
+            }
+            let mut sized: Vec<(u64, &Path)> = candidates
+                .par_iter()
+                // Measured afresh: a stale cached size would pick the wrong matches
+                .map(|target| (target.size(None), target.path()))
+                .collect();
+            sized.sort_by_key(|(size, _)| std::cmp::Reverse(*size));
+            let sizes: Vec<u64> = sized.iter().map(|(size, _)| *size).collect();
+            let count = free_target_count(&sizes, available, goal);
+            println!(
+                "Free space: {}, --free-target {}: removing the {} largest of {} matches.",
+                format_size(available, units),
+                format_size(goal, units),
+                format_count(count as u64, args.summary_locale),
+                format_count(sized.len() as u64, args.summary_locale)
+            );
+            let chosen: HashSet<PathBuf> = sized
+                .into_iter()
+                .take(count)
+                .map(|(_, path)| path.to_path_buf())
+                .collect();
+            for (_, targets, _) in &mut scans {
+                targets.retain(|target| chosen.contains(target.path()));
+            }
+            Some((volume, available, goal))
+        }
+        _ => None,
+    };
+    let found: Vec<(&str, &Target)> = scans
+        .iter()
+        .flat_map(|(root, targets, _)| targets.iter().map(move |target| (*root, target)))
+        .collect();
+    check_found(&args, found.len())?;
+    if prompt && !found.is_empty() {
+        let sized: Vec<(String, u64)> = found
+            .par_iter()
+            .map(|(root, target)| {
+                (
+                    display_path(target.path(), root, opts.relative),
+                    target.size(opts.size_cache),
+                )
+            })
+            .collect();
+        let items: Vec<String> = sized
+            .iter()
+            .map(|(shown, size)| format!("{} ({})", shown, format_size(*size, units)))
+            .collect();
+        let total = sized.iter().map(|(_, size)| size).sum();
+        let summary = format!(
+            "{} entries, {} in total.",
+            format_count(items.len() as u64, args.summary_locale),
+            format_size(total, units)
+        );
+        if !confirm_deletion(&items, &summary, confirm_phrase(&args, items.len())) {
+            println!("Aborted by user.");
+            return Ok(());
+        }
+    }
+    let removal_started = Instant::now();
+    let free_before = audit_free_space(&args, &roots);
+    let reports = per_root(
+        &scans,
+        parallel,
+        keep_going.then_some(&mut root_failures),
+        |(root, targets, skipped)| {
+            let mut report = remove_targets(root, targets, &opts)?;
+            report.skipped = report.skipped.merge(*skipped);
+            Ok(report)
+        },
+    )?;
+    let mut report = reports
+        .into_iter()
+        .fold(CleanReport::default(), CleanReport::merge);
+    report.removal_time = removal_started.elapsed();
+    report.roots = root_count;
+    report.root_failures = root_failures;
+    report.free_space = free_before.zip(audit_free_space(&args, &roots));
+    save_size_cache(size_cache.as_ref());
+    if let Some((volume, available, goal)) = free_target {
+        let free = if args.dry_run {
+            available.saturating_add(report.total_bytes)
+        } else {
+            available_space(volume).unwrap_or(available)
+        };
+        println!(
+            "Free space {}: {}, --free-target {} {}.",
+            if args.dry_run { "would be" } else { "now" },
+            format_size(free, units),
+            format_size(goal, units),
+            if free >= goal { "met" } else { "not met" }
+        );
+    }
+    warn_unmatched(&args, &config, &pattern_matches);
+    print_summary(&args, report, units, started.elapsed())
+}
+
+/// Read the --roots-stdin list: one root per line, resolved like the command-line roots. Blank
+/// lines, paths that are not directories and roots already in `known` or listed before are
+/// skipped.
+fn read_roots(input: impl io::BufRead, known: &[String]) -> Vec<String> {
+    let mut seen: HashSet<String> = known.iter().cloned().collect();
+    let mut roots = Vec::new();
+    for line in input.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("could not read roots from stdin: {}", e);
+                break;
+            }
+        };
+        let root = line.trim();
+        if root.is_empty() {
+            continue;
+        }
+        if !Path::new(root).is_dir() {
+            warn!("skipping root {} from stdin: not a directory", root);
+            continue;
+        }
+        let resolved = resolve_root(root);
+        if seen.insert(resolved.clone()) {
+            roots.push(resolved);
+        } else {
+            debug!("skipping duplicate root {} from stdin", root);
+        }
+    }
+    roots
+}
+
+/// Size the global rayon pool for the storage of the first root (--auto-concurrency), unless
+/// RAYON_NUM_THREADS is set.
+fn auto_concurrency(paths: &[String]) {
+    if std::env::var_os("RAYON_NUM_THREADS").is_some() {
+        info!("RAYON_NUM_THREADS overrides --auto-concurrency");
+        return;
+    }
+    let Some(root) = paths.first() else {
+        return;
+    };
+    let root = Path::new(root);
+    let threads = platform::detect_storage_parallelism(root);
+    info!(
+        "--auto-concurrency: {:?} storage at {}, using {} threads",
+        platform::storage_kind(root),
+        root.display(),
+        threads
+    );
+    if let Err(e) = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()
+    {
+        warn!("could not configure {} threads: {}", threads, e);
+    }
+}
+
+/// Canonicalize a root path so a symlinked root (e.g. `~/current -> ~/projects/app`) is walked,
+/// matched and reported by its real location. A root that cannot be resolved is returned as
+/// given, so the scan reports it as not found.
+fn resolve_root(root: &str) -> String {
+    let Ok(resolved) = fs::canonicalize(root) else {
+        return root.to_string();
+    };
+    let resolved = resolved.to_string_lossy().into_owned();
+    if fs::symlink_metadata(root).is_ok_and(|meta| meta.file_type().is_symlink()) {
+        info!("root {} is a symlink to {}", root, resolved);
+    } else {
+        debug!("root {} resolved to {}", root, resolved);
+    }
+    resolved
+}
+
+/// Scan every root and list its matches as baseline entries, sorted by path.
+fn baseline_entries(roots: &[String], dirs: &[&str], opts: &CleanOptions) -> Result<Baseline> {
+    let mut entries = Vec::new();
+    for root in roots {
+        let targets = collect_targets(root, dirs, opts, &mut Skipped::default())?;
+        entries.par_extend(targets.par_iter().map(|target| BaselineEntry {
+            path: display_path(target.path(), root, true),
+            bytes: target.size(opts.size_cache),
+        }));
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(Baseline { entries })
+}
+
+/// Load a --baseline file.
+fn load_baseline(path: &str) -> Result<Baseline, CleanerError> {
+    let invalid = |message: String| CleanerError::Baseline {
+        path: PathBuf::from(path),
+        message,
+    };
+    let contents = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+    serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))
+}
+
+/// Split `current` into the entries that are new since `baseline` and those that recur.
+fn baseline_diff<'c>(
+    baseline: &Baseline,
+    current: &'c Baseline,
+) -> (Vec<&'c BaselineEntry>, Vec<&'c BaselineEntry>) {
+    let known: HashSet<&str> = baseline
+        .entries
+        .iter()
+        .map(|entry| entry.path.as_str())
+        .collect();
+    current
+        .entries
+        .iter()
+        .partition(|entry| !known.contains(entry.path.as_str()))
+}
+
+/// Print how the matches compare with the --baseline, and fail if the new ones exceed
+/// --fail-if-new.
+fn report_baseline_diff(args: &Args, file: &str, current: &Baseline, units: Units) -> Result<()> {
+    let baseline = load_baseline(file)?;
+    let (new, recurring) = baseline_diff(&baseline, current);
+    let new_bytes: u64 = new.iter().map(|entry| entry.bytes).sum();
+    let recurring_bytes: u64 = recurring.iter().map(|entry| entry.bytes).sum();
+    let locale = args.summary_locale;
+    println!(
+        "Compared with {}: {} new ({}), {} recurring ({}).",
+        file,
+        format_count(new.len() as u64, locale),
+        format_size(new_bytes, units),
+        format_count(recurring.len() as u64, locale),
+        format_size(recurring_bytes, units)
+    );
+    for entry in &new {
+        println!("  + {} ({})", entry.path, format_size(entry.bytes, units));
+    }
+    match args.fail_if_new {
+        Some(limit) if new_bytes > limit => Err(eyre!(
+            "{} of new matches since the baseline exceeds --fail-if-new {}",
+            format_size(new_bytes, units),
+            format_size(limit, units)
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Free space on the filesystems of the roots, with --audit.
+fn audit_free_space(args: &Args, roots: &[String]) -> Option<u64> {
+    if !args.audit || roots.is_empty() {
+        return None;
+    }
+    free_space_of(roots, device_id, available_space)
+        .map_err(|e| warn!("cannot query free space for --audit: {}", e))
+        .ok()
+}
+
+/// Total free space of the filesystems holding `roots`, each filesystem counted once however
+/// many roots it holds. Roots whose device `device_of` cannot tell are taken to share one.
+fn free_space_of(
+    roots: &[String],
+    device_of: impl Fn(&Path) -> Option<u64>,
+    available: impl Fn(&Path) -> io::Result<u64>,
+) -> io::Result<u64> {
+    let mut measured = HashSet::new();
+    let mut free = 0u64;
+    for root in roots {
+        let root = Path::new(root);
+        if measured.insert(device_of(root)) {
+            free = free.saturating_add(available(root)?);
+        }
+    }
+    Ok(free)
+}
+
+/// For --free-target: the targets on the filesystem of `volume`, where removing them frees
+/// space, and how many were left out for being on another one. Where `device_of` cannot tell
+/// filesystems apart, every target counts.
+fn on_volume<'a>(
+    targets: impl Iterator<Item = &'a Target>,
+    volume: &Path,
+    device_of: impl Fn(&Path) -> Option<u64>,
+) -> (Vec<&'a Target>, usize) {
+    let Some(device) = device_of(volume) else {
+        return (targets.collect(), 0);
+    };
+    let (on, off): (Vec<&Target>, Vec<&Target>) =
+        targets.partition(|target| device_of(target.path()).is_none_or(|d| d == device));
+    (on, off.len())
+}
+
+/// With --fail-on-nothing a scan that matched nothing is an error, e.g. for a CI pipeline that
+/// unexpectedly stopped producing build output.
+fn check_found(args: &Args, found: usize) -> Result<()> {
+    if args.fail_on_nothing && found == 0 {
+        return Err(eyre!("nothing matched and --fail-on-nothing is set"));
+    }
+    Ok(())
+}
+
+/// For --free-target: how many of the matches, sized largest first in `sizes`, must be removed
+/// for `available` bytes of free space to reach `goal`. All of them if even that is not enough.
+fn free_target_count(sizes: &[u64], available: u64, goal: u64) -> usize {
+    let mut free = available;
+    sizes
+        .iter()
+        .take_while(|size| {
+            let needed = free < goal;
+            free = free.saturating_add(**size);
+            needed
+        })
+        .count()
+}
+
+/// Persist the --cache size cache; failing to write it only costs speed on the next run.
+fn save_size_cache(cache: Option<&SizeCache>) {
+    if let Some(cache) = cache {
+        if let Err(e) = cache.save() {
+            warn!("could not save size cache: {}", e);
+        }
+    }
+}
+
+/// Something processed once per root by [`per_root`].
+trait PerRoot {
+    fn root(&self) -> &str;
+}
+
+impl PerRoot for String {
+    fn root(&self) -> &str {
+        self
+    }
+}
+
+impl PerRoot for (&str, Vec<Target>, Skipped) {
+    fn root(&self) -> &str {
+        self.0
+    }
+}
+
+/// Run `f` for every root (or per-root item), concurrently when `parallel` is set, stopping at
+/// the first error. With `failures` (--keep-going-across-roots) every item is processed
+/// instead: one that fails is dropped from the result and recorded there under its root.
+///
+/// Each root is a task on rayon's global pool, which the per-root parallel deletion shares, so
+/// handling roots concurrently does not oversubscribe threads.
+fn per_root<'a, I: PerRoot + Sync, T: Send>(
+    items: &'a [I],
+    parallel: bool,
+    failures: Option<&mut Vec<RootFailure>>,
+    f: impl Fn(&'a I) -> Result<T, CleanerError> + Sync + Send,
+) -> Result<Vec<T>, CleanerError> {
+    let Some(failures) = failures else {
+        return if parallel {
+            items.par_iter().map(f).collect()
+        } else {
+            items.iter().map(f).collect()
+        };
+    };
+    let results: Vec<Result<T, CleanerError>> = if parallel {
+        items.par_iter().map(f).collect()
+    } else {
+        items.iter().map(f).collect()
+    };
+    let mut done = Vec::new();
+    for (item, result) in items.iter().zip(results) {
+        match result {
+            Ok(value) => done.push(value),
+            Err(error) => {
+                warn!("giving up on root {}: {}", item.root(), error);
+                failures.push(RootFailure {
+                    root: item.root().to_string(),
+                    error,
+                });
+            }
+        }
+    }
+    Ok(done)
+}
+
+/// Render the run's metrics in the Prometheus text exposition format.
+fn prometheus_metrics(report: &CleanReport, elapsed: Duration) -> String {
+    let metrics = [
+        (
+            "cleaner_directories_removed",
+            "Directories removed by the last run.",
+            report.directories.to_string(),
+        ),
+        (
+            "cleaner_bytes_freed",
+            "Bytes freed by the last run.",
+            report.total_bytes.to_string(),
+        ),
+        (
+            "cleaner_errors_total",
+            "Directories the last run failed to remove.",
+            report.failures.len().to_string(),
+        ),
+        (
+            "cleaner_duration_seconds",
+            "Wall-clock duration of the last run.",
+            format!("{:.3}", elapsed.as_secs_f64()),
+        ),
+    ];
+    let mut out = String::new();
+    for (name, help, value) in metrics {
+        out.push_str(&format!(
+            "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}\n"
+        ));
+    }
+    out
+}
+
+/// Write --metrics-file for the node-exporter textfile collector. The file is written under a
+/// temporary name and renamed, so the collector never reads a partial file.
+fn write_metrics(file: &Path, report: &CleanReport, elapsed: Duration) -> Result<(), CleanerError> {
+    let metrics_error = |source| CleanerError::MetricsFile {
+        path: file.to_path_buf(),
+        source,
+    };
+    let partial = file.with_extension("prom.tmp");
+    fs::write(&partial, prometheus_metrics(report, elapsed)).map_err(metrics_error)?;
+    fs::rename(&partial, file).map_err(metrics_error)
+}
+
+/// GitHub-flavored Markdown for --report-md: one row per project, largest first, and a totals
+/// row.
+fn markdown_report(
+    report: &CleanReport,
+    dry_run: bool,
+    units: Units,
+    locale: SummaryLocale,
+) -> String {
+    let mut rows: Vec<(&PathBuf, &(usize, u64))> = report.projects.iter().collect();
+    rows.sort_by_key(|(_, (_, bytes))| std::cmp::Reverse(*bytes));
+    let mut out = String::new();
+    out.push_str(if dry_run {
+        "## Cleanup report (dry run)\n\n"
+    } else {
+        "## Cleanup report\n\n"
+    });
+    out.push_str("| Project | Freed | Entries |\n|---|---:|---:|\n");
+    for (project, (entries, bytes)) in rows {
+        out.push_str(&format!(
+            "| `{}` | {} | {} |\n",
+            project.display(),
+            format_size(*bytes, units),
+            format_count(*entries as u64, locale)
+        ));
+    }
+    out.push_str(&format!(
+        "| **Total** | **{}** | **{}** |\n",
+        format_size(report.total_bytes, units),
+        format_count((report.directories + report.files) as u64, locale)
+    ));
+    if !report.failures.is_empty() {
+        out.push_str(&format!(
+            "\n{} entries could not be removed.\n",
+            format_count(report.failures.len() as u64, locale)
+        ));
+    }
+    out
+}
+
+/// JSON body of the --webhook POST. `text` (Slack) and `content` (Discord) carry the same
+/// human-readable line; the other fields are for generic consumers.
+#[derive(Serialize)]
+struct WebhookPayload {
+    text: String,
+    content: String,
+    directories: usize,
+    files: usize,
+    total_bytes: u64,
+    errors: usize,
+    duration_seconds: f64,
+    dry_run: bool,
+}
+
+/// POST the run summary to --webhook. A failing webhook is only a warning: the cleanup itself
+/// has already happened.
+fn send_webhook(
+    url: &str,
+    report: &CleanReport,
+    dry_run: bool,
+    units: Units,
+    locale: SummaryLocale,
+    elapsed: Duration,
+) {
+    let (title, body) = notification(report, dry_run, units, locale);
+    let text = format!("{}: {}", title, body);
+    let payload = WebhookPayload {
+        content: text.clone(),
+        text,
+        directories: report.directories,
+        files: report.files,
+        total_bytes: report.total_bytes,
+        errors: report.failures.len(),
+        duration_seconds: elapsed.as_secs_f64(),
+        dry_run,
+    };
+    let json = serde_json::to_string(&payload).unwrap();
+    if let Err(e) = webhook::post_json(url, &json) {
+        warn!("could not post summary to webhook: {}", e);
+    }
+}
+
+/// Title and text of the --notify desktop notification for a finished run.
+fn notification(
+    report: &CleanReport,
+    dry_run: bool,
+    units: Units,
+    locale: SummaryLocale,
+) -> (String, String) {
+    let size = format_size(report.total_bytes, units);
+    let mut body = if dry_run {
+        format!(
+            "{} would be removed, freeing {}",
+            report.describe(locale),
+            size
+        )
+    } else {
+        format!("Removed {}, freed {}", report.describe(locale), size)
+    };
+    if report.failures.is_empty() {
+        ("cleaner finished".to_string(), body)
+    } else {
+        body.push_str(&format!(
+            "; {} could not be removed",
+            format_count(report.failures.len() as u64, locale)
+        ));
+        ("cleaner finished with errors".to_string(), body)
+    }
+}
+
+/// Print the end-of-run summary and turn collected removal failures into an error.
+fn print_summary(
+    args: &Args,
+    mut report: CleanReport,
+    units: Units,
+    elapsed: Duration,
+) -> Result<()> {
+    let locale = args.summary_locale;
+    if args.sorted {
+        report.failures.sort_by_cached_key(ToString::to_string);
+    }
+    if let Some(file) = &args.metrics_file {
+        write_metrics(Path::new(file), &report, elapsed)?;
+    }
+    if let Some(file) = &args.report_md {
+        fs::write(file, markdown_report(&report, args.dry_run, units, locale)).map_err(
+            |source| CleanerError::ReportFile {
+                path: PathBuf::from(file),
+                source,
+            },
+        )?;
+    }
+    if args.notify {
+        let (summary, body) = notification(&report, args.dry_run, units, args.summary_locale);
+        platform::notify(&summary, &body);
+    }
+    if let Some(url) = &args.webhook {
+        send_webhook(
+            url,
+            &report,
+            args.dry_run,
+            units,
+            args.summary_locale,
+            elapsed,
+        );
+    }
+    let count = report.directories;
+    let total_bytes = report.total_bytes;
+    // Prompts in interactive mode would make the rate meaningless
+    let throughput = report
+        .throughput()
+        .filter(|_| !args.dry_run && !args.interactive);
+    let audit = report.free_space.map(|(free_before, free_after)| Audit {
+        claimed_bytes: total_bytes,
+        free_before,
+        free_after,
+        measured_bytes: (free_after as i128 - free_before as i128) as i64,
+    });
+    if audit.as_ref().is_some_and(Audit::discrepant) {
+        warn!("--audit: the measured free space differs from the computed size by more than 10%, e.g. because of hardlinks, sparse files or other processes writing to the disk");
+    }
+    let quiet = args
+        .quiet_below
+        .is_some_and(|threshold| total_bytes < threshold);
+    if quiet {
+        info!(
+            "freed {}, below --quiet-below: no summary",
+            format_size(total_bytes, units)
+        );
+    } else if args.ci {
+        let summary = Summary {
+            directories: count,
+            files: report.files,
+            total_bytes,
+            total_mb: total_bytes as f64 / 1_048_576.0,
+            dry_run: args.dry_run,
+            no_op_delete: args.no_op_delete,
+            failed: report.failures.len(),
+            unfinished: report.unfinished,
+            gone: report.gone,
+            skipped: report.skipped,
+            languages: report
+                .languages
+                .iter()
+                .map(|(language, &(entries, bytes))| {
+                    (language.clone(), LanguageTotals { entries, bytes })
+                })
+                .collect(),
+            root_failures: report
+                .root_failures
+                .iter()
+                .map(|failure| RootFailureSummary {
+                    root: failure.root.clone(),
+                    error: failure.error.to_string(),
+                })
+                .collect(),
+            audit,
+            archived_bytes: args
+                .archive_to
+                .as_ref()
+                .filter(|_| !args.dry_run)
+                .map(|_| report.archived_bytes),
+            patterns: report.patterns.clone(),
+            removal_seconds: report.removal_time.as_secs_f64(),
+            bytes_per_second: throughput.map(|(bytes, _)| bytes),
+            directories_per_second: throughput.map(|(_, entries)| entries),
+        };
+        println!("{}", serde_json::to_string(&summary).unwrap());
+    } else if args.dry_run {
+        println!(
+            "Dry run: {} would be removed. (Total size: {})",
+            report.describe(args.summary_locale),
+            format_size(total_bytes, units)
+        );
+    } else if args.no_op_delete {
+        println!(
+            "No-op delete: {} processed, nothing was deleted. (Total size: {})",
+            report.describe(args.summary_locale),
+            format_size(total_bytes, units)
+        );
+    } else if let Some(archive_dir) = &args.archive_to {
+        println!(
+            "Archived {} to {} ({} of archives) and removed them. (Total size: {})",
+            report.describe(args.summary_locale),
+            archive_dir,
+            format_size(report.archived_bytes, units),
+            format_size(total_bytes, units)
+        );
+    } else if let Some(staging) = &args.move_to {
+        println!(
+            "Moved {} to {}. (Total size: {})",
+            report.describe(args.summary_locale),
+            staging,
+            format_size(total_bytes, units)
+        );
+    } else {
+        println!(
+            "Removed {}. (Total size: {})",
+            report.describe(args.summary_locale),
+            format_size(total_bytes, units)
+        );
+    }
+    if let Some((bytes, entries)) = throughput.filter(|_| !args.ci && !quiet) {
+        println!(
+            "Throughput: {}/s, {:.1} entries/s ({:.2}s removing).",
+            format_size(bytes as u64, units),
+            entries,
+            report.removal_time.as_secs_f64()
+        );
+    }
+    if let Some(audit) = audit.filter(|_| !args.ci && !quiet) {
+        println!(
+            "Audit: claimed {} freed, measured {}{} ({} -> {} free).",
+            format_size(audit.claimed_bytes, units),
+            if audit.measured_bytes < 0 { "-" } else { "" },
+            format_size(audit.measured_bytes.unsigned_abs(), units),
+            format_size(audit.free_before, units),
+            format_size(audit.free_after, units)
+        );
+    }
+    if !report.patterns.is_empty() && !args.ci && !quiet {
+        println!("By pattern: {}.", pattern_breakdown(&report, units, locale));
+    }
+    if !report.languages.is_empty() && !args.ci && !quiet {
+        println!(
+            "By language: {}.",
+            language_breakdown(&report, units, locale)
+        );
+    }
+    if report.pruned > 0 && !args.ci && !quiet {
+        println!(
+            "Pruned {} empty parent directories.",
+            format_count(report.pruned as u64, locale)
+        );
+    }
+    if report.skipped.total() > 0 && !args.ci && !quiet {
+        println!("Skipped: {}.", report.skipped.describe(locale));
+    }
+    if report.gone > 0 && !args.ci && !quiet {
+        println!(
+            "Already gone: {} matches disappeared between scan and removal.",
+            format_count(report.gone as u64, locale)
+        );
+    }
+    if report.unfinished > 0 {
+        println!(
+            "Time budget exceeded: stopped before {} remaining directories.",
+            format_count(report.unfinished as u64, locale)
+        );
+    }
+    if !report.root_failures.is_empty() {
+        eprintln!(
+            "Failed {} of {} roots:",
+            format_count(report.root_failures.len() as u64, locale),
+            format_count(report.roots as u64, locale)
+        );
+        for failure in &report.root_failures {
+            eprintln!("  - {}: {}", failure.root, failure.error);
+        }
+        if report.root_failures.len() == report.roots {
+            return Err(eyre!("every root failed"));
+        }
+    }
+    if !report.failures.is_empty() {
+        let failed = format_count(report.failures.len() as u64, locale);
+        eprintln!("Failed to remove {} entries:", failed);
+        for failure in &report.failures {
+            eprintln!("  - {}", failure);
+        }
+        return Err(eyre!("{} entries could not be removed", failed));
+    }
+    let found = report.directories + report.files;
+    if args.exit_nonzero_if_found && found > 0 {
+        return Err(eyre!(
+            "{} matches found and --exit-nonzero-if-found is set",
+            format_count(found as u64, locale)
+        ));
+    }
+    info!("DONE.");
+    Ok(())
+}
+
+/// The per-language totals, largest first: "node: 9.10 GiB (30 entries), rust: 4.20 GiB (12 entries)".
+fn language_breakdown(report: &CleanReport, units: Units, locale: SummaryLocale) -> String {
+    let mut languages: Vec<_> = report.languages.iter().collect();
+    languages.sort_by_key(|(_, (_, bytes))| std::cmp::Reverse(*bytes));
+    languages
+        .into_iter()
+        .map(|(language, (entries, bytes))| {
+            format!(
+                "{}: {} ({} entries)",
+                language,
+                format_size(*bytes, units),
+                format_count(*entries as u64, locale)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The per-pattern totals of a dry run, largest first: "node_modules: 15 dirs, 4.20 GiB; target:
+/// 3 dirs, 1.10 GiB".
+fn pattern_breakdown(report: &CleanReport, units: Units, locale: SummaryLocale) -> String {
+    let mut patterns: Vec<_> = report.patterns.iter().collect();
+    patterns.sort_by_key(|(_, totals)| std::cmp::Reverse(totals.bytes));
+    patterns
+        .into_iter()
+        .map(|(pattern, totals)| {
+            let directories = format_count(totals.directories as u64, locale);
+            let files = format_count(totals.files as u64, locale);
+            let count = match (totals.directories, totals.files) {
+                (_, 0) => format!("{} dirs", directories),
+                (0, _) => format!("{} files", files),
+                _ => format!("{} dirs and {} files", directories, files),
+            };
+            format!(
+                "{}: {}, {}",
+                pattern,
+                count,
+                format_size(totals.bytes, units)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static NO_DEPTH_LIMITS: std::sync::LazyLock<HashMap<String, usize>> =
+        std::sync::LazyLock::new(HashMap::new);
+    static PATTERN_MATCHES: PatternMatches = PatternMatches::new();
+
+    fn options<'a>(exclude: &'a [&'a str]) -> CleanOptions<'a> {
+        CleanOptions {
+            dry_run: true,
+            exclude,
+            max_depth: 0,
+            project_relative_depth: None,
+            detected_only: false,
+            interactive: false,
+            confirm_all: AtomicBool::new(false),
+            force: false,
+            fail_fast: false,
+            skip_in_use: false,
+            verify_readable: false,
+            respect_cargo_metadata: false,
+            relative: false,
+            max_open_fds: 0,
+            simulate_delete_latency: None,
+            no_op_delete: false,
+            retries: 0,
+            deadline: None,
+            units: Units::Binary,
+            locale: SummaryLocale::En,
+            protect_newer_than: None,
+            explain: false,
+            case_insensitive: false,
+            keep_file: ".cleaner-keep",
+            exclude_if_contains: &[],
+            require_marker: &[],
+            skip_submodules: false,
+            one_file_system: false,
+            device_of: device_id,
+            preview_limit: 0,
+            post_command: None,
+            sorted: false,
+            keep_recent: None,
+            prune_contents_before: None,
+            match_files: false,
+            stale_deps_only: false,
+            group_by_language: false,
+            literal: false,
+            move_to: None,
+            archive_to: None,
+            atomic: false,
+            on_conflict: OnConflict::Rename,
+            pattern_depths: &NO_DEPTH_LIMITS,
+            protected_roots: &[],
+            active_venv: None,
+            prune_empty_parents: false,
+            size_cache: None,
+            checkpoint: None,
+            project_roots: None,
+            pattern_matches: &PATTERN_MATCHES,
+        }
+    }
+
+    #[test]
+    fn test_confirm_phrase_engages_above_threshold() {
+        let args = Args::parse_from(["cleaner", ".", "--confirm-phrase-above", "2"]);
+        assert_eq!(confirm_phrase(&args, 2), None);
+        assert_eq!(confirm_phrase(&args, 3), Some("DELETE"));
+        let args = Args::parse_from(["cleaner", ".", "--confirm-phrase", "project"]);
+        assert_eq!(confirm_phrase(&args, 1), Some("project"));
+    }
+
+    #[test]
+    fn test_one_file_system_stops_at_mount_points() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().to_str().unwrap();
+        for dir in ["app/target", "other-fs/app/target"] {
+            fs::create_dir_all(temp.path().join(dir)).unwrap();
+        }
+        // Pretend another filesystem is mounted at `other-fs`
+        fn device(path: &Path) -> Option<u64> {
+            Some(if path.iter().any(|part| part == "other-fs") {
+                2
+            } else {
+                1
+            })
+        }
+        let targets = |one_file_system| {
+            let opts = CleanOptions {
+                one_file_system,
+                device_of: device,
+                ..options(&[])
+            };
+            let mut targets: Vec<PathBuf> =
+                collect_targets(root, &["target"], &opts, &mut Skipped::default())
+                    .unwrap()
+                    .iter()
+                    .map(|t| t.path().strip_prefix(root).unwrap().to_path_buf())
+                    .collect();
+            targets.sort();
+            targets
+        };
+        assert_eq!(
+            targets(false),
+            [
+                PathBuf::from("app/target"),
+                PathBuf::from("other-fs/app/target")
+            ]
+        );
+        assert_eq!(targets(true), [PathBuf::from("app/target")]);
+    }
+
+    #[test]
+    fn test_keep_recent_counts_unlistable_dirs() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().to_str().unwrap();
+        let listed = temp.path().join("a/target");
+        for (i, child) in ["old", "new"].iter().enumerate() {
+            fs::create_dir_all(listed.join(child)).unwrap();
+            File::open(listed.join(child))
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(60 * (i as u64 + 1)))
+                .unwrap();
+        }
+        // Vanished since the scan, so it cannot be listed
+        let missing = temp.path().join("b/target");
+        let mut skipped = Skipped::default();
+        let targets = keep_recent(
+            vec![Target::Dir(listed.clone()), Target::Dir(missing)],
+            1,
+            root,
+            &options(&[]),
+            &mut skipped,
+        );
+        assert_eq!(targets, vec![Target::Dir(listed.join("old"))]);
+        assert_eq!(skipped.kept_recent, 1);
+        assert_eq!(skipped.unreadable, 1);
+    }
+
+    #[test]
+    fn test_prune_contents_counts_unreadable_entries() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().to_str().unwrap();
+        let listed = temp.path().join("a/target");
+        fs::create_dir_all(&listed).unwrap();
+        let old = listed.join("old.o");
+        File::create(&old)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        // Vanished since the scan, so it cannot be walked
+        let missing = temp.path().join("b/target");
+        let mut skipped = Skipped::default();
+        let targets = prune_contents(
+            vec![Target::Dir(listed), Target::Dir(missing)],
+            SystemTime::UNIX_EPOCH + Duration::from_secs(60),
+            root,
+            &options(&[]),
+            &mut skipped,
+        );
+        assert_eq!(targets, vec![Target::File(old)]);
+        assert_eq!(skipped.unreadable, 1);
+    }
+
+    #[test]
+    fn test_skipped_describe_uses_locale() {
+        let skipped = Skipped {
+            excluded: 1234,
+            symlinks: 2,
+            ..Skipped::default()
+        };
+        assert_eq!(
+            skipped.describe(SummaryLocale::De),
+            "1.234 excluded, 2 symlinks"
+        );
+        assert_eq!(skipped.to_string(), "1234 excluded, 2 symlinks");
+    }
+
+    #[test]
+    fn test_effective_config_sources() {
+        let config: Option<Config> = Some(
+            toml::from_str("[general]\nunits = \"si\"\n[kinds.rust]\ndirs = [\"target\"]\n")
+                .unwrap(),
+        );
+        let args = Args::parse_from(["cleaner", ".", "--kind", "rust", "--max-depth", "3"]);
+        let dirs = determine_dirs_to_clean(&args, &config).unwrap();
+        let effective = effective_config(&args, &config, dirs, vec![]);
+        assert_eq!(effective.kind.source, Source::Cli);
+        assert_eq!(effective.dirs.value, vec!["target"]);
+        assert_eq!(effective.dirs.source, Source::Config);
+        assert_eq!(effective.exclude.source, Source::Default);
+        assert_eq!(effective.max_depth.source, Source::Cli);
+        assert_eq!(effective.units.value, Units::Si);
+        assert_eq!(effective.units.source, Source::Config);
+    }
+
+    #[test]
+    fn test_pattern_matches_counts_per_pattern() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("a/target")).unwrap();
+        fs::create_dir_all(root.join("b/target")).unwrap();
+        fs::create_dir_all(root.join("c/build")).unwrap();
+        let matches = PatternMatches::new();
+        let opts = CleanOptions {
+            pattern_matches: &matches,
+            ..options(&["build"])
+        };
+        let root = root.to_str().unwrap();
+        let targets = collect_targets(
+            root,
+            &["target", "build", "targte"],
+            &opts,
+            &mut Skipped::default(),
+        )
+        .unwrap();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(matches.count("target"), 2);
+        // Excluded matches still show the pattern is not a typo
+        assert_eq!(matches.count("build"), 1);
+        assert_eq!(
+            matches.unmatched(&["target", "build", "targte"]),
+            vec!["targte"]
+        );
+        // Collected targets and their contents are attributed to the pattern
+        let target = Path::new(root).join("a/target");
+        assert_eq!(matches.pattern_of(&target).as_deref(), Some("target"));
+        assert_eq!(
+            matches.pattern_of(&target.join("debug")).as_deref(),
+            Some("target")
+        );
+        assert_eq!(matches.pattern_of(&Path::new(root).join("c/build")), None);
+    }
+
+    #[test]
+    fn test_markdown_report() {
+        let mut report = CleanReport::default();
+        let removed = [
+            ("/w/app", "target", 2048),
+            ("/w/web", "node_modules", 4096),
+            ("/w/web", "dist", 1024),
+        ];
+        for (project, dir, size) in removed {
+            let owner = Owner {
+                project: PathBuf::from(project),
+                language: None,
+            };
+            report.record(
+                &Target::Dir(owner.project.join(dir)),
+                &owner,
+                Outcome::Removed(size),
+            );
+        }
+        let owner = Owner {
+            project: PathBuf::from("/w/old"),
+            language: None,
+        };
+        report.record(
+            &Target::Dir(PathBuf::from("/w/old/target")),
+            &owner,
+            Outcome::Gone,
+        );
+        let markdown = markdown_report(&report, false, Units::Binary, SummaryLocale::Plain);
+        let lines: Vec<&str> = markdown.lines().collect();
+        assert_eq!(lines[2], "| Project | Freed | Entries |");
+        assert_eq!(lines[4], "| `/w/web` | 5.00 KiB | 2 |");
+        assert_eq!(lines[5], "| `/w/app` | 2.00 KiB | 1 |");
+        assert_eq!(lines[6], "| **Total** | **7.00 KiB** | **3** |");
+        assert_eq!(lines.len(), 7);
+    }
+
+    #[test]
+    fn test_missing_root_is_path_not_found() {
+        let result = collect_targets(
+            "/definitely/not/here",
+            &["target"],
+            &options(&[]),
+            &mut Skipped::default(),
+        );
+        assert!(matches!(result, Err(CleanerError::PathNotFound(_))));
+    }
+
+    #[test]
+    fn test_bad_glob_is_invalid_pattern() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().to_str().unwrap();
+        let result = collect_targets(
+            root,
+            &["build[", "target"],
+            &options(&[]),
+            &mut Skipped::default(),
+        );
+        assert!(matches!(
+            result,
+            Err(CleanerError::InvalidPattern { pattern, .. }) if pattern == "build["
+        ));
+        let result = collect_targets(root, &["target"], &options(&["["]), &mut Skipped::default());
+        assert!(matches!(result, Err(CleanerError::InvalidPattern { .. })));
+    }
+
+    #[test]
+    fn test_bad_config_is_config_parse() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join("cleaner.toml");
+        fs::write(&config_path, "[kinds.rust\ndirs = 1").unwrap();
+        let result = load_config(config_path.to_str().unwrap());
+        assert!(matches!(result, Err(CleanerError::ConfigParse { .. })));
+        let result = load_config(temp.path().join("missing.toml").to_str().unwrap());
+        assert!(matches!(result, Err(CleanerError::ConfigRead { .. })));
+    }
+
+    #[test]
+    fn test_matches_any_nested_pattern() {
+        let patterns = compile_patterns(&[".nx/cache", "bazel-*"], false).unwrap();
+        assert!(find_match(&patterns, Path::new("/repo/.nx/cache"), MatchOptions::new()).is_some());
+        assert!(find_match(&patterns, Path::new("/repo/cache"), MatchOptions::new()).is_none());
+        assert!(find_match(&patterns, Path::new("/repo/.nx"), MatchOptions::new()).is_none());
+        let matched = find_match(&patterns, Path::new("/repo/bazel-out"), MatchOptions::new());
+        assert_eq!(matched.map(|p| p.as_str()), Some("bazel-*"));
+    }
+
+    #[test]
+    fn test_literal_patterns_match_exact_names() {
+        let glob = compile_patterns(&["build[1]"], false).unwrap();
+        let literal = compile_patterns(&["build[1]"], true).unwrap();
+        let options = MatchOptions::new();
+        assert!(find_match(&glob, Path::new("/repo/build1"), options).is_some());
+        assert!(find_match(&glob, Path::new("/repo/build[1]"), options).is_none());
+        assert!(find_match(&literal, Path::new("/repo/build[1]"), options).is_some());
+        assert!(find_match(&literal, Path::new("/repo/build1"), options).is_none());
+    }
+
+    #[test]
+    fn test_config_paths_resolve_against_config_dir() {
+        let temp = tempfile::tempdir().unwrap();
+        let conf = temp.path().join("conf");
+        fs::create_dir(&conf).unwrap();
+        fs::write(conf.join("ignore.txt"), "# generated\nout\n\nvendor\n").unwrap();
+        fs::write(
+            conf.join("cleaner.toml"),
+            "[general]\nalways_exclude = [\"caches\", \"~/.conan\", \"/opt/cache\"]\n\
+             [exclude]\npatterns = [\".git\"]\nfrom = \"ignore.txt\"\n",
+        )
+        .unwrap();
+        let config = load_config(conf.join("cleaner.toml").to_str().unwrap()).unwrap();
+        let patterns = config.exclude.unwrap().patterns.unwrap();
+        assert_eq!(patterns, vec![".git", "out", "vendor"]);
+        let always = config.general.unwrap().always_exclude.unwrap();
+        assert_eq!(always[0], conf.join("caches").to_string_lossy());
+        assert_eq!(always[1], "~/.conan");
+        assert_eq!(always[2], "/opt/cache");
+    }
+
+    #[test]
+    fn test_read_roots_skips_blank_invalid_and_duplicate_lines() {
+        let temp = tempfile::tempdir().unwrap();
+        let a = temp.path().join("a");
+        let b = temp.path().join("b");
+        fs::create_dir(&a).unwrap();
+        fs::create_dir(&b).unwrap();
+        let known = [resolve_root(b.to_str().unwrap())];
+        let input = format!(
+            "{}\n\n  {}/  \n{}\n{}\n",
+            a.display(),
+            a.display(),
+            b.display(),
+            temp.path().join("missing").display()
+        );
+        let roots = read_roots(io::Cursor::new(input), &known);
+        assert_eq!(roots, [resolve_root(a.to_str().unwrap())]);
+    }
+
+    #[test]
+    fn test_case_insensitive_precedence() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join("cleaner.toml");
+        fs::write(&config_path, "[general]\ncase_insensitive = true\n").unwrap();
+        let config = Some(load_config(config_path.to_str().unwrap()).unwrap());
+        let args = |flags: &[&str]| Args::parse_from([&["cleaner", "."], flags].concat());
+        assert_eq!(
+            case_insensitive(&args(&[]), &None),
+            platform::CASE_INSENSITIVE_BY_DEFAULT
+        );
+        #[cfg(target_os = "linux")]
+        assert!(!case_insensitive(&args(&[]), &None));
+        assert!(case_insensitive(&args(&[]), &config));
+        assert!(!case_insensitive(&args(&["--case-sensitive"]), &config));
+        assert!(case_insensitive(&args(&["--ignore-case"]), &None));
+    }
+
+    #[test]
+    fn test_baseline_diff_splits_new_and_recurring() {
+        let entry = |path: &str, bytes| BaselineEntry {
+            path: path.to_string(),
+            bytes,
+        };
+        let baseline = Baseline {
+            entries: vec![entry("app/target", 10), entry("old/target", 5)],
+        };
+        let current = Baseline {
+            entries: vec![entry("app/target", 20), entry("web/node_modules", 30)],
+        };
+        let (new, recurring) = baseline_diff(&baseline, &current);
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].path, "web/node_modules");
+        assert_eq!(recurring.len(), 1);
+        assert_eq!(recurring[0].bytes, 20);
+    }
+
+    #[test]
+    fn test_audit_flags_large_discrepancies() {
+        let audit = |claimed_bytes, measured_bytes| Audit {
+            claimed_bytes,
+            free_before: 0,
+            free_after: 0,
+            measured_bytes,
+        };
+        let gib: i64 = 1 << 30;
+        assert!(!audit(gib as u64, gib - gib / 20).discrepant());
+        assert!(audit(gib as u64, gib / 2).discrepant());
+        assert!(audit(gib as u64, -4096).discrepant());
+        // Block rounding on small runs is not flagged
+        assert!(!audit(1000, 8192).discrepant());
+    }
+
+    #[test]
+    fn test_unknown_kind_keys() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join("cleaner.toml");
+        fs::write(
+            &config_path,
+            "[kinds.rust]\ndirs = [\"target\"]\n[kinds.rsut]\ndirs = [\"x\"]\n[kinds.all]\ndirs = [\"y\"]\n[kinds.Node]\ndirs = [\"z\"]\n",
+        )
+        .unwrap();
+        let config = load_config(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(unknown_kind_keys(&config), ["Node", "rsut"]);
+    }
+
+    #[test]
+    fn test_json_config_matches_toml() {
+        let temp = tempfile::tempdir().unwrap();
+        let toml_path = temp.path().join("cleaner.toml");
+        let json_path = temp.path().join("cleaner.json");
+        fs::write(
+            &toml_path,
+            "[general]\ndefault_kind = \"node\"\n\
+             [kinds.node]\ndirs = [{ name = \"node_modules\", max_depth = 2 }, \"dist\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            &json_path,
+            r#"{"general": {"default_kind": "node"},
+                "kinds": {"node": {"dirs": [{"name": "node_modules", "max_depth": 2}, "dist"]}}}"#,
+        )
+        .unwrap();
+        let args = Args::parse_from(["cleaner", "."]);
+        let from_toml = Some(load_config(toml_path.to_str().unwrap()).unwrap());
+        let from_json = Some(load_config(json_path.to_str().unwrap()).unwrap());
+        assert_eq!(
+            determine_dirs_to_clean(&args, &from_json).unwrap(),
+            determine_dirs_to_clean(&args, &from_toml).unwrap()
+        );
+        assert_eq!(
+            determine_pattern_depths(&args, &from_json),
+            determine_pattern_depths(&args, &from_toml)
+        );
+        fs::write(&json_path, "[kinds.node]").unwrap();
+        assert!(matches!(
+            load_config(json_path.to_str().unwrap()),
+            Err(CleanerError::ConfigParse { .. })
+        ));
+    }
+
+    #[test]
+    fn test_dirs_aliases_expand() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join("cleaner.toml");
+        fs::write(
+            &config_path,
+            "[aliases]\nwebjunk = [\"node_modules\", \"dist\"]\n\"@py\" = [\"__pycache__\"]\n",
+        )
+        .unwrap();
+        let config = Some(load_config(config_path.to_str().unwrap()).unwrap());
+        let args = Args::parse_from(["cleaner", ".", "--dirs", "@webjunk,target,@py"]);
+        assert_eq!(
+            determine_dirs_to_clean(&args, &config).unwrap(),
+            vec!["node_modules", "dist", "target", "__pycache__"]
+        );
+        let args = Args::parse_from(["cleaner", ".", "--dirs", "@nope"]);
+        assert!(matches!(
+            determine_dirs_to_clean(&args, &config),
+            Err(CleanerError::UnknownAlias { name }) if name == "nope"
+        ));
+    }
+
+    #[test]
+    fn test_all_kinds_unions_every_kind() {
+        let args = Args::parse_from(["cleaner", ".", "--all-kinds"]);
+        let dirs = determine_dirs_to_clean(&args, &None).unwrap();
+        for dir in ["target", "node_modules", "__pycache__", "_opam", "obj"] {
+            assert!(dirs.iter().any(|d| d == dir), "{} missing", dir);
+        }
+        let unique: HashSet<&String> = dirs.iter().collect();
+        assert_eq!(unique.len(), dirs.len());
+    }
+
+    #[test]
+    fn test_except_kind_subtracts_kind_dirs() {
+        let args = Args::parse_from(["cleaner", ".", "--all-kinds", "--except-kind", "node"]);
+        let dirs = determine_dirs_to_clean(&args, &None).unwrap();
+        assert!(dirs.iter().any(|d| d == "target"));
+        for dir in ["node_modules", "dist", "build"] {
+            assert!(!dirs.iter().any(|d| d == dir), "{} not subtracted", dir);
+        }
+    }
+
+    #[test]
+    fn test_affix_patterns() {
+        let patterns = affix_patterns(&["-cache".to_string()], &["tmp[1]".to_string()]);
+        assert_eq!(patterns, vec!["*-cache", "tmp[[]1[]]*"]);
+        let compiled = compile_patterns(&["*-cache", "tmp[[]1[]]*"], false).unwrap();
+        assert!(compiled[0].matches("foo-cache"));
+        assert!(!compiled[0].matches("cachebox"));
+        assert!(compiled[1].matches("tmp[1]-old"));
+        assert!(!compiled[1].matches("tmp1"));
+    }
+
+    #[test]
+    fn test_free_target_leaves_other_filesystems_alone() {
+        let (first, second) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        for root in [&first, &second] {
+            fs::create_dir(root.path().join("target")).unwrap();
+        }
+        let targets: Vec<Target> = [&first, &second]
+            .iter()
+            .flat_map(|root| {
+                let root = root.path().to_str().unwrap();
+                collect_targets(root, &["target"], &options(&[]), &mut Skipped::default()).unwrap()
+            })
+            .collect();
+        assert_eq!(targets.len(), 2);
+        // Pretend the second root is another filesystem
+        let device = |path: &Path| {
+            Some(if path.starts_with(second.path()) {
+                2
+            } else {
+                1
+            })
+        };
+        let (candidates, elsewhere) = on_volume(targets.iter(), first.path(), device);
+        assert_eq!(elsewhere, 1);
+        assert_eq!(candidates.len(), 1);
+        assert!(candidates[0].path().starts_with(first.path()));
+        // Without device IDs every match is a candidate
+        let (candidates, elsewhere) = on_volume(targets.iter(), first.path(), |_| None);
+        assert_eq!((candidates.len(), elsewhere), (2, 0));
+    }
+
+    #[test]
+    fn test_free_space_counts_each_filesystem_once() {
+        let roots = ["/a", "/a/b", "/c"].map(String::from);
+        let device = |path: &Path| Some(if path.starts_with("/c") { 2 } else { 1 });
+        let free = |path: &Path| Ok(if path.starts_with("/c") { 100 } else { 10 });
+        assert_eq!(free_space_of(&roots, device, free).unwrap(), 110);
+        assert_eq!(free_space_of(&roots, |_| None, free).unwrap(), 10);
+        let unreadable = |_: &Path| Err(io::Error::other("no statvfs"));
+        assert!(free_space_of(&roots, device, unreadable).is_err());
+    }
+
+    #[test]
+    fn test_free_target_count() {
+        let sizes = [50, 40, 30, 5];
+        // 10 free, 100 wanted: the two largest bring it to exactly 100
+        assert_eq!(free_target_count(&sizes, 10, 100), 2);
+        assert_eq!(free_target_count(&sizes, 10, 101), 3);
+        assert_eq!(free_target_count(&sizes, 100, 100), 0);
+        assert_eq!(free_target_count(&sizes, 0, 1_000), 4);
+        assert_eq!(free_target_count(&[], 0, 1_000), 0);
+    }
+
+    #[test]
+    fn test_depth_counts() {
+        let targets = [
+            Target::Dir(PathBuf::from("/w/target")),
+            Target::Dir(PathBuf::from("/w/a/target")),
+            Target::Dir(PathBuf::from("/w/b/target")),
+            Target::File(PathBuf::from("/w/a/b/c.o")),
+        ];
+        let mut counts = BTreeMap::new();
+        depth_counts("/w", &targets, &mut counts);
+        depth_counts(
+            "/v",
+            &[Target::Dir(PathBuf::from("/v/x/target"))],
+            &mut counts,
+        );
+        assert_eq!(counts, BTreeMap::from([(1, 1), (2, 3), (3, 1)]));
+    }
+
+    #[test]
+    fn test_age_histogram_bucket_boundaries() {
+        let temp = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        let day = Duration::from_secs(86_400);
+        let mut targets = Vec::new();
+        for (name, days) in [
+            ("a", 0),
+            ("b", 6),
+            ("c", 7),
+            ("d", 27),
+            ("e", 28),
+            ("f", 400),
+        ] {
+            let path = temp.path().join(name);
+            fs::write(&path, "12345").unwrap();
+            File::open(&path)
+                .unwrap()
+                .set_modified(now - day * days - Duration::from_secs(60))
+                .unwrap();
+            targets.push(Target::File(path));
+        }
+        let buckets = age_histogram(&targets, now, &options(&[]));
+        let counts: Vec<usize> = buckets.iter().map(|b| b.directories).collect();
+        assert_eq!(counts, vec![2, 2, 2]);
+        assert!(buckets.iter().all(|b| b.bytes == 10));
+    }
+
+    #[test]
+    fn test_prometheus_metrics_format() {
+        let report = CleanReport {
+            directories: 4,
+            total_bytes: 1_500,
+            failures: vec![CleanerError::PathNotFound(PathBuf::from("/gone"))],
+            ..CleanReport::default()
+        };
+        let text = prometheus_metrics(&report, Duration::from_millis(2500));
+        assert!(text
+            .contains("# TYPE cleaner_directories_removed gauge\ncleaner_directories_removed 4\n"));
+        assert!(text.contains("\ncleaner_bytes_freed 1500\n"));
+        assert!(text.contains("\ncleaner_errors_total 1\n"));
+        assert!(text.contains("\ncleaner_duration_seconds 2.500\n"));
+        assert_eq!(text.lines().filter(|l| l.starts_with("# HELP")).count(), 4);
+    }
+
+    #[test]
+    fn test_notification_payload() {
+        let mut report = CleanReport {
+            directories: 3,
+            total_bytes: 2048,
+            ..CleanReport::default()
+        };
+        assert_eq!(
+            notification(&report, false, Units::Binary, SummaryLocale::En),
+            (
+                "cleaner finished".to_string(),
+                "Removed 3 directories, freed 2.00 KiB".to_string()
+            )
+        );
+        report
+            .failures
+            .push(CleanerError::PathNotFound(PathBuf::from("/gone")));
+        assert_eq!(
+            notification(&report, false, Units::Binary, SummaryLocale::En),
+            (
+                "cleaner finished with errors".to_string(),
+                "Removed 3 directories, freed 2.00 KiB; 1 could not be removed".to_string()
+            )
+        );
+        assert_eq!(
+            notification(&CleanReport::default(), true, Units::Si, SummaryLocale::En).1,
+            "0 directories would be removed, freeing 0 B"
+        );
+    }
+
+    #[test]
+    fn test_display_path_relative() {
+        let path = Path::new("/work/app/target");
+        assert_eq!(display_path(path, "/work", true), "app/target");
+        assert_eq!(display_path(path, "/work", false), "/work/app/target");
+        assert_eq!(display_path(Path::new("/work"), "/work", true), ".");
+        assert_eq!(display_path(path, "/other", true), "/work/app/target");
+    }
+
+    #[test]
+    fn test_preview_limit_still_counts_everything() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().to_str().unwrap();
+        let targets: Vec<Target> = (0..5)
+            .map(|i| {
+                let path = temp.path().join(format!("p{}/target", i));
+                fs::create_dir_all(&path).unwrap();
+                fs::write(path.join("f"), "12345").unwrap();
+                Target::Dir(path)
+            })
+            .collect();
+        let mut opts = options(&[]);
+        opts.preview_limit = 2;
+        let report = remove_targets(root, &targets, &opts).unwrap();
+        assert_eq!(report.directories, 5);
+        assert_eq!(report.total_bytes, 25);
+        assert!(targets.iter().all(|t| t.path().exists()));
+    }
+
+    #[test]
+    fn test_vanished_target_is_gone_not_failure() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("a/target/debug")).unwrap();
+        fs::create_dir_all(temp.path().join("b/target")).unwrap();
+        let root = temp.path().to_str().unwrap();
+        let mut opts = options(&[]);
+        opts.dry_run = false;
+        let targets = collect_targets(root, &["target"], &opts, &mut Skipped::default()).unwrap();
+        assert_eq!(targets.len(), 2);
+        // Another process cleans one of them between the scan and the removal
+        fs::remove_dir_all(temp.path().join("a/target")).unwrap();
+        let report = remove_targets(root, &targets, &opts).unwrap();
+        assert_eq!(report.gone, 1);
+        assert_eq!(report.directories, 1);
+        assert!(report.failures.is_empty());
+        assert!(!temp.path().join("b/target").exists());
+    }
+
+    #[test]
+    fn test_removal_error_is_removal() {
+        let path = PathBuf::from("/definitely/not/here/target");
+        let result = remove_target(&Target::Dir(path.clone()), "target", 0, false);
+        assert!(matches!(result, Err(CleanerError::Removal { .. })));
+        let result = remove_target(&Target::Dir(path.clone()), "target", 0, true);
+        assert!(matches!(result, Err(CleanerError::Removal { .. })));
+        let result = remove_target(&Target::File(path), "target", 0, false);
+        assert!(matches!(result, Err(CleanerError::Removal { .. })));
     }
-    info!("DONE.");
-    Ok(())
 }
@@ -0,0 +1,245 @@
+//! Platform-specific helpers for the Cleaner project.
+//!
+//! This module isolates functionality that depends on the operating system:
+//! - Detection of files and directories currently held open by running processes.
+//! - Desktop notifications (behind the `notify` cargo feature).
+//! - Lowering CPU and I/O priority for --low-priority.
+//! - The default case sensitivity of name matching.
+//! - Telling rotational, solid-state and network storage apart for --auto-concurrency.
+//!
+//! On unsupported platforms the helpers degrade to no-ops.
+
+use std::path::{Path, PathBuf};
+
+/// Returns the paths currently held open (files or working directories) by running processes.
+///
+/// On Linux this reads `/proc/<pid>/fd` and `/proc/<pid>/cwd`; processes that cannot be inspected
+/// are silently ignored. On macOS it parses the output of `lsof`. On other platforms, or if
+/// detection fails, an empty list is returned.
+#[cfg(target_os = "linux")]
+pub fn open_paths() -> Vec<PathBuf> {
+    use std::fs;
+    let mut paths = Vec::new();
+    let Ok(procs) = fs::read_dir("/proc") else {
+        return paths;
+    };
+    for proc_entry in procs.flatten() {
+        let pid_dir = proc_entry.path();
+        let is_pid = proc_entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.chars().all(|c| c.is_ascii_digit()));
+        if !is_pid {
+            continue;
+        }
+        if let Ok(cwd) = fs::read_link(pid_dir.join("cwd")) {
+            paths.push(cwd);
+        }
+        if let Ok(fds) = fs::read_dir(pid_dir.join("fd")) {
+            paths.extend(fds.flatten().filter_map(|fd| fs::read_link(fd.path()).ok()));
+        }
+    }
+    paths
+}
+
+#[cfg(target_os = "macos")]
+pub fn open_paths() -> Vec<PathBuf> {
+    // -F n prints one "n<path>" line per open file
+    match std::process::Command::new("lsof")
+        .arg("-F")
+        .arg("n")
+        .output()
+    {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.strip_prefix('n'))
+            .filter(|path| path.starts_with('/'))
+            .map(PathBuf::from)
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn open_paths() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+/// Shows a desktop notification with `summary` as title and `body` as text.
+///
+/// Sent through `notify-rust`. A failure, e.g. in a headless session without a notification
+/// daemon, is only logged at debug level: the run itself succeeded.
+#[cfg(feature = "notify")]
+pub fn notify(summary: &str, body: &str) {
+    let shown = notify_rust::Notification::new()
+        .appname("cleaner")
+        .summary(summary)
+        .body(body)
+        .show();
+    if let Err(e) = shown {
+        log::debug!("notification failed: {}", e);
+    }
+}
+
+/// Without the `notify` feature --notify only logs that notifications are unavailable.
+#[cfg(not(feature = "notify"))]
+pub fn notify(_summary: &str, _body: &str) {
+    log::warn!("--notify ignored: cleaner was built without the `notify` feature");
+}
+
+/// Lowers the priority of the calling thread to the lowest CPU niceness (19) and, on Linux, the
+/// idle I/O class, so disk work only uses bandwidth nothing else wants. Threads spawned
+/// afterwards (such as rayon's pool) inherit both.
+///
+/// On other Unix systems only the niceness is changed; elsewhere this is a no-op.
+#[cfg(unix)]
+pub fn lower_priority() -> std::io::Result<()> {
+    // The `which` argument's type differs between libc targets
+    #[allow(clippy::useless_conversion)]
+    let rc = unsafe { libc::setpriority(libc::PRIO_PROCESS.try_into().unwrap(), 0, 19) };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    #[cfg(target_os = "linux")]
+    {
+        // From linux/ioprio.h, which libc does not expose
+        const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+        const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+        const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+        let rc = unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                0,
+                IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+            )
+        };
+        if rc != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn lower_priority() -> std::io::Result<()> {
+    Ok(())
+}
+
+/// The kind of storage a path lives on, as far as it can be told.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Storage {
+    /// A spinning disk, where parallel removals mostly add seeks.
+    Rotational,
+    SolidState,
+    /// NFS, SMB and similar mounts, where every operation is a round trip.
+    Network,
+    Unknown,
+}
+
+/// Returns the kind of storage `path` lives on.
+///
+/// On Linux network filesystems are recognized by their `statfs` type, and block devices by
+/// `/sys/dev/block/<major>:<minor>/queue/rotational` (of the whole disk, for a partition).
+/// Elsewhere, or when that information is missing, this is [`Storage::Unknown`].
+#[cfg(target_os = "linux")]
+pub fn storage_kind(path: &Path) -> Storage {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+    // From linux/magic.h and the cifs/smb client sources
+    const NETWORK_MAGICS: [i64; 6] = [
+        0x6969,      // NFS
+        0x517b,      // SMB
+        0xff53_4d42, // CIFS
+        0xfe53_4d42, // SMB2
+        0x0102_1997, // v9fs
+        0x6b41_4653, // AFS
+    ];
+    if let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) {
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } == 0
+            && NETWORK_MAGICS.contains(&(stat.f_type as i64))
+        {
+            return Storage::Network;
+        }
+    }
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return Storage::Unknown;
+    };
+    let dev = metadata.dev();
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+    let Ok(device) = std::fs::canonicalize(format!("/sys/dev/block/{}:{}", major, minor)) else {
+        return Storage::Unknown;
+    };
+    // A partition has no queue of its own; its disk is the parent directory
+    let disk = if device.join("partition").exists() {
+        device.parent().unwrap_or(&device).to_path_buf()
+    } else {
+        device
+    };
+    match std::fs::read_to_string(disk.join("queue/rotational")) {
+        Ok(flag) if flag.trim() == "1" => Storage::Rotational,
+        Ok(flag) if flag.trim() == "0" => Storage::SolidState,
+        _ => Storage::Unknown,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn storage_kind(_path: &Path) -> Storage {
+    Storage::Unknown
+}
+
+/// Returns how many directory operations to run in parallel on the storage behind `path`: few
+/// on spinning disks and network mounts, plenty on solid-state drives, and one per CPU when the
+/// storage cannot be told.
+pub fn detect_storage_parallelism(path: &Path) -> usize {
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    match storage_kind(path) {
+        Storage::Rotational | Storage::Network => 2,
+        Storage::SolidState => (cpus * 2).max(8),
+        Storage::Unknown => cpus,
+    }
+}
+
+/// Whether names are matched ignoring case unless configured otherwise. The usual filesystems of
+/// Windows (NTFS) and macOS (APFS) are case-insensitive but case-preserving, so `Target` and
+/// `target` name the same directory there; on Linux they are different directories.
+pub const CASE_INSENSITIVE_BY_DEFAULT: bool = cfg!(any(target_os = "windows", target_os = "macos"));
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_storage_parallelism_is_sane() {
+        let temp = tempfile::tempdir().unwrap();
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let parallelism = detect_storage_parallelism(temp.path());
+        assert!((1..=(cpus * 2).max(8)).contains(&parallelism));
+        match storage_kind(temp.path()) {
+            Storage::Rotational | Storage::Network => assert_eq!(parallelism, 2),
+            Storage::SolidState => assert!(parallelism >= 8),
+            Storage::Unknown => assert_eq!(parallelism, cpus),
+        }
+        assert_eq!(
+            storage_kind(Path::new("/definitely/not/here")),
+            Storage::Unknown
+        );
+    }
+
+    #[test]
+    fn test_lower_priority_sets_idle_io_class() {
+        // Run on a fresh thread so the test harness keeps its priority
+        std::thread::spawn(|| {
+            lower_priority().unwrap();
+            let ioprio = unsafe { libc::syscall(libc::SYS_ioprio_get, 1, 0) };
+            assert_eq!(ioprio >> 13, 3);
+            let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+            assert_eq!(nice, 19);
+        })
+        .join()
+        .unwrap();
+    }
+}
@@ -10,14 +10,26 @@
 //! The logger setup function allows for colored, timestamped, and optionally thread-aware log output.
 //! The directory selection logic is used by the main cleaning routine to determine which directories to remove.
 
-use crate::args::ProjectKind;
+use crate::args::{ProjectKind, SummaryLocale, Units};
+use crate::error::CleanerError;
 use chrono::prelude::*;
+use clap::ValueEnum;
 use env_logger::fmt::Formatter;
 use env_logger::{Builder, WriteStyle};
-use log::{Level, LevelFilter, Record};
+use log::{debug, Level, LevelFilter, Record};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fs::OpenOptions;
-use std::io::Write;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
 use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+use walkdir::WalkDir;
 
 /// Sets up the logger with custom formatting.
 ///
@@ -134,6 +146,22 @@ pub fn default_dirs_for_kind(kind: &ProjectKind) -> Vec<&'static str> {
         ],
         ProjectKind::Php => vec!["vendor", "out", "build", "cache"],
         ProjectKind::Ruby => vec![".bundle", "vendor", "log", "tmp", "coverage"],
+        ProjectKind::OCaml => vec!["_build", "_opam", ".merlin"],
+        ProjectKind::Erlang => vec!["_build", "ebin", ".rebar3", "deps"],
+        // Files rather than directories, so this kind is cleaned with file matching
+        ProjectKind::Cruft => vec![
+            ".DS_Store",
+            "._*",
+            "Thumbs.db",
+            "desktop.ini",
+            "npm-debug.log*",
+            "yarn-debug.log*",
+            "yarn-error.log*",
+            "*.pyc",
+            "*.pyo",
+        ],
+        // bazel-* are symlinks into Bazel's output base; they are reported but never followed
+        ProjectKind::Monorepo => vec![".nx/cache", ".turbo", "bazel-*", ".cache"],
         ProjectKind::All => {
             let mut dirs = vec![
                 "target",
@@ -164,10 +192,1692 @@ pub fn default_dirs_for_kind(kind: &ProjectKind) -> Vec<&'static str> {
     }
 }
 
+/// Returns the files (glob patterns) whose presence marks a directory as the root of a project
+/// of `kind`. `all` and `ide` have no markers of their own.
+pub fn project_markers(kind: &ProjectKind) -> &'static [&'static str] {
+    match kind {
+        ProjectKind::Rust => &["Cargo.toml"],
+        ProjectKind::Python => &[
+            "pyproject.toml",
+            "setup.py",
+            "setup.cfg",
+            "requirements.txt",
+        ],
+        ProjectKind::Java => &["pom.xml", "build.gradle", "build.gradle.kts"],
+        ProjectKind::Node => &["package.json"],
+        ProjectKind::Go => &["go.mod"],
+        ProjectKind::CSharp => &["*.csproj", "*.fsproj", "*.sln"],
+        ProjectKind::Cpp => &["CMakeLists.txt", "meson.build"],
+        ProjectKind::Php => &["composer.json"],
+        ProjectKind::Ruby => &["Gemfile"],
+        ProjectKind::Monorepo => &[
+            "nx.json",
+            "turbo.json",
+            "lerna.json",
+            "pnpm-workspace.yaml",
+            "WORKSPACE",
+            "MODULE.bazel",
+        ],
+        ProjectKind::OCaml => &["dune-project", "*.opam"],
+        ProjectKind::Erlang => &["rebar.config"],
+        ProjectKind::All | ProjectKind::Ide | ProjectKind::Cruft => &[],
+    }
+}
+
+/// Returns the kind of project rooted at `dir`, judged by the marker files it directly
+/// contains, or `None` if it is not a project root. When markers of several kinds are present
+/// the first kind in `--list-kinds` order wins.
+pub fn detect_project_kind(dir: &Path) -> Option<ProjectKind> {
+    let names: Vec<String> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    all_kinds().iter().copied().find(|kind| {
+        project_markers(kind).iter().any(|marker| {
+            let pattern = glob::Pattern::new(marker).expect("valid marker pattern");
+            names.iter().any(|name| pattern.matches(name))
+        })
+    })
+}
+
+/// Returns the language (project kind) a matched `target` belongs to, for per-language totals.
+///
+/// The kinds whose default directories match the target's name are the candidates; a name shared
+/// by several kinds (`target`, `build`) is settled by the markers of `project`, the directory the
+/// target is attributed to. Without any candidate the project's markers decide alone.
+pub fn language_of(target: &Path, project: &Path) -> Option<ProjectKind> {
+    let components: Vec<String> = target
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let candidates: Vec<ProjectKind> = all_kinds()
+        .iter()
+        .copied()
+        .filter(|kind| *kind != ProjectKind::All)
+        .filter(|kind| {
+            default_dirs_for_kind(kind).iter().any(|dir| {
+                let depth = dir.split('/').count();
+                components.len() >= depth
+                    && glob::Pattern::new(dir)
+                        .is_ok_and(|p| p.matches(&components[components.len() - depth..].join("/")))
+            })
+        })
+        .collect();
+    if let [only] = candidates[..] {
+        return Some(only);
+    }
+    let detected = detect_project_kind(project);
+    if candidates.is_empty() {
+        return detected;
+    }
+    detected
+        .filter(|kind| candidates.contains(kind))
+        .or(candidates.first().copied())
+}
+
+/// Returns the lockfiles that pin the contents of a dependency directory named `name`, such as
+/// `package-lock.json` for `node_modules`. Empty for directories that are not installed
+/// dependencies.
+pub fn dependency_lockfiles(name: &str) -> &'static [&'static str] {
+    match name {
+        "node_modules" => &[
+            "package-lock.json",
+            "npm-shrinkwrap.json",
+            "yarn.lock",
+            "pnpm-lock.yaml",
+            "bun.lockb",
+        ],
+        ".venv" | "venv" | "env" => &["poetry.lock", "Pipfile.lock", "uv.lock", "pdm.lock"],
+        _ => &[],
+    }
+}
+
+/// Returns the lockfile next to the dependency directory `dir` that was modified after it, i.e.
+/// the dependencies are out of date and will be reinstalled anyway. `None` if `dir` is not a
+/// dependency directory, has no lockfile, or is at least as new as all of them.
+pub fn stale_lockfile(dir: &Path) -> Option<PathBuf> {
+    let name = dir.file_name()?.to_str()?;
+    let installed = std::fs::metadata(dir).and_then(|m| m.modified()).ok()?;
+    let parent = dir.parent()?;
+    dependency_lockfiles(name)
+        .iter()
+        .map(|lockfile| parent.join(lockfile))
+        .find(|lockfile| {
+            std::fs::metadata(lockfile)
+                .and_then(|m| m.modified())
+                .is_ok_and(|locked| locked > installed)
+        })
+}
+
+/// First bytes of a valid `CACHEDIR.TAG` file, per the Cache Directory Tagging Specification.
+pub const CACHEDIR_TAG_SIGNATURE: &[u8] = b"Signature: 8a477f597d28d172789f06886806bc55";
+
+/// Returns whether `dir` directly contains the marker `name`. A `CACHEDIR.TAG` only counts if it
+/// starts with the standard signature, so a stray file of that name does not qualify.
+pub fn has_marker(dir: &Path, name: &str) -> bool {
+    let marker = dir.join(name);
+    if name != "CACHEDIR.TAG" {
+        return marker.exists();
+    }
+    let mut head = [0; CACHEDIR_TAG_SIGNATURE.len()];
+    std::fs::File::open(marker)
+        .and_then(|mut file| io::Read::read_exact(&mut file, &mut head))
+        .is_ok_and(|()| head == CACHEDIR_TAG_SIGNATURE)
+}
+
+/// Returns every built-in project kind, in the order they are listed by `--list-kinds`.
+///
+/// Together with [`dirs_for_kinds`] this is meant for the future library API. The crate has no
+/// library target yet, so both are internal to the binary until that split.
+pub fn all_kinds() -> &'static [ProjectKind] {
+    ProjectKind::value_variants()
+}
+
+/// Returns the union of the default directories of `kinds`, without duplicates.
+///
+/// Directories keep the order in which they first appear, so e.g. `build` shared by several
+/// kinds is listed once, at the position of the first kind that cleans it.
+pub fn dirs_for_kinds(kinds: &[ProjectKind]) -> Vec<String> {
+    let mut dirs: Vec<String> = Vec::new();
+    for kind in kinds {
+        for dir in default_dirs_for_kind(kind) {
+            if !dirs.iter().any(|d| d == dir) {
+                dirs.push(dir.to_string());
+            }
+        }
+    }
+    dirs
+}
+
+/// Computes a cheap content fingerprint of a directory.
+///
+/// The fingerprint hashes the sorted relative paths and sizes of all files in the directory;
+/// file contents are not read. Two directories with the same fingerprint are very likely identical.
+///
+/// # Returns
+/// `Some((hash, total_size_in_bytes))`, or `None` if the directory could not be read.
+pub fn dir_fingerprint(path: &Path) -> Option<(u64, u64)> {
+    let mut files: Vec<(String, u64)> = Vec::new();
+    for entry in WalkDir::new(path) {
+        let entry = entry.ok()?;
+        if entry.file_type().is_file() {
+            let rel = entry.path().strip_prefix(path).ok()?;
+            let size = entry.metadata().ok()?.len();
+            files.push((rel.to_string_lossy().into_owned(), size));
+        }
+    }
+    files.sort();
+    let mut hasher = DefaultHasher::new();
+    files.hash(&mut hasher);
+    let total = files.iter().map(|(_, size)| size).sum();
+    Some((hasher.finish(), total))
+}
+
+/// Total size in bytes of all regular files below `path`, skipping entries that cannot be read.
+/// Symlinks are not followed.
+pub fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// Walks `path` and reads every file below it to the end, returning the first entry that could
+/// not be read with its error, or `None` if the whole tree is readable. Symlinks are not
+/// followed.
+pub fn first_unreadable(path: &Path) -> Option<(PathBuf, io::Error)> {
+    for entry in WalkDir::new(path) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let failed = e.path().unwrap_or(path).to_path_buf();
+                return Some((failed, e.into()));
+            }
+        };
+        if entry.file_type().is_file() {
+            let read = std::fs::File::open(entry.path())
+                .and_then(|mut file| io::copy(&mut file, &mut io::sink()));
+            if let Err(e) = read {
+                return Some((entry.path().to_path_buf(), e));
+            }
+        }
+    }
+    None
+}
+
+/// Where the --cache size cache is stored: `$XDG_DATA_HOME/cleaner/sizes.json`, falling back
+/// to `~/.local/share/cleaner/sizes.json`.
+pub fn size_cache_path() -> PathBuf {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| expand_home("~/.local/share"));
+    data_dir.join("cleaner").join("sizes.json")
+}
+
+/// A cached directory size, valid while the directory's modification time is unchanged.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct CachedSize {
+    modified_secs: u64,
+    modified_nanos: u32,
+    size: u64,
+}
+
+/// Record of completed removals for --checkpoint, one path per line. Each removal is appended
+/// as soon as it completes, so an interrupted run can be continued with --resume.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    file: Mutex<std::fs::File>,
+    done: HashSet<PathBuf>,
+}
+
+impl Checkpoint {
+    /// Open the checkpoint at `path`. With `resume` the paths already recorded there are
+    /// loaded and new ones appended; otherwise the file is started afresh.
+    pub fn open(path: &Path, resume: bool) -> Result<Checkpoint, CleanerError> {
+        let checkpoint_error = |source| CleanerError::Checkpoint {
+            path: path.to_path_buf(),
+            source,
+        };
+        let done = if resume {
+            match std::fs::read_to_string(path) {
+                Ok(contents) => contents.lines().map(PathBuf::from).collect(),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => HashSet::new(),
+                Err(e) => return Err(checkpoint_error(e)),
+            }
+        } else {
+            HashSet::new()
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(resume)
+            .write(true)
+            .truncate(!resume)
+            .open(path)
+            .map_err(checkpoint_error)?;
+        Ok(Checkpoint {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            done,
+        })
+    }
+
+    /// Whether `target` was removed by an earlier run recorded in this checkpoint.
+    pub fn is_done(&self, target: &Path) -> bool {
+        self.done.contains(target)
+    }
+
+    /// Append `target` as removed.
+    pub fn record(&self, target: &Path) -> Result<(), CleanerError> {
+        let line = format!("{}\n", target.display());
+        self.file
+            .lock()
+            .unwrap()
+            .write_all(line.as_bytes())
+            .map_err(|source| CleanerError::Checkpoint {
+                path: self.path.clone(),
+                source,
+            })
+    }
+}
+
+/// On-disk cache of recursive directory sizes, keyed by canonical path and modification time.
+///
+/// Only the directory's own mtime is compared, which changes when entries are added or removed
+/// directly inside it, so a size can be stale if only a deeply nested file changed.
+#[derive(Debug, Default)]
+pub struct SizeCache {
+    path: PathBuf,
+    entries: Mutex<HashMap<PathBuf, CachedSize>>,
+    /// Sizes answered from the cache.
+    pub hits: AtomicUsize,
+    /// Sizes that had to be computed by walking the directory.
+    pub misses: AtomicUsize,
+}
+
+impl SizeCache {
+    /// Load the cache stored at `path`. A missing or unreadable file gives an empty cache.
+    pub fn load(path: &Path) -> SizeCache {
+        let entries = std::fs::read(path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        SizeCache {
+            path: path.to_path_buf(),
+            entries: Mutex::new(entries),
+            ..SizeCache::default()
+        }
+    }
+
+    /// The recursive size of `dir`, from the cache if its mtime is unchanged.
+    pub fn dir_size(&self, dir: &Path) -> u64 {
+        let key = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let Some(modified) = std::fs::metadata(dir)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        else {
+            return dir_size(dir);
+        };
+        let cached = self.entries.lock().unwrap().get(&key).copied();
+        if let Some(entry) = cached.filter(|entry| {
+            entry.modified_secs == modified.as_secs()
+                && entry.modified_nanos == modified.subsec_nanos()
+        }) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return entry.size;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let size = dir_size(dir);
+        self.entries.lock().unwrap().insert(
+            key,
+            CachedSize {
+                modified_secs: modified.as_secs(),
+                modified_nanos: modified.subsec_nanos(),
+                size,
+            },
+        );
+        size
+    }
+
+    /// Write the cache back to disk, dropping entries for directories that no longer exist.
+    pub fn save(&self) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|dir, _| dir.exists());
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_vec(&*entries).map_err(io::Error::other)?;
+        std::fs::write(&self.path, data)
+    }
+}
+
+/// Asks cargo for the real target directory of the Rust project at `project`.
+///
+/// Runs `cargo metadata --format-version 1 --no-deps --offline`, which honours `CARGO_TARGET_DIR`
+/// and `[build] target-dir` from cargo config files.
+///
+/// # Returns
+/// The absolute target directory, or `None` if cargo is unavailable or the project is invalid.
+pub fn cargo_target_dir(project: &Path) -> Option<PathBuf> {
+    let output = Command::new("cargo")
+        .args([
+            "metadata",
+            "--format-version",
+            "1",
+            "--no-deps",
+            "--offline",
+        ])
+        .current_dir(project)
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            let metadata: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+            metadata
+                .get("target_directory")?
+                .as_str()
+                .map(PathBuf::from)
+        }
+        Ok(output) => {
+            debug!(
+                "cargo metadata failed in {}: {}",
+                project.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            None
+        }
+        Err(e) => {
+            debug!("cargo not available: {}", e);
+            None
+        }
+    }
+}
+
+/// A counting semaphore bounding how many operations run at the same time.
+pub struct Semaphore {
+    permits: Mutex<usize>,
+    released: Condvar,
+}
+
+/// A permit held from a [`Semaphore`]; released when dropped.
+pub struct SemaphorePermit<'a>(&'a Semaphore);
+
+impl Semaphore {
+    /// Creates a semaphore with `permits` available permits (at least one).
+    pub fn new(permits: usize) -> Self {
+        Semaphore {
+            permits: Mutex::new(permits.max(1)),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Blocks until a permit is available and takes it.
+    pub fn acquire(&self) -> SemaphorePermit<'_> {
+        let mut permits = self.permits.lock().unwrap();
+        while *permits == 0 {
+            permits = self.released.wait(permits).unwrap();
+        }
+        *permits -= 1;
+        SemaphorePermit(self)
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        *self.0.permits.lock().unwrap() += 1;
+        self.0.released.notify_one();
+    }
+}
+
+/// File descriptors assumed to be held at once by a single recursive removal or scan
+/// (roughly one per nesting level).
+const FDS_PER_TASK: usize = 32;
+
+/// File descriptors kept in reserve for stdio, log files and the directory walker.
+const RESERVED_FDS: usize = 16;
+
+/// Returns a semaphore bounding parallel directory operations so that together they stay
+/// within `max_open_fds` open file descriptors, or `None` when `max_open_fds` is 0 (unlimited).
+pub fn fd_limiter(max_open_fds: usize) -> Option<Semaphore> {
+    (max_open_fds > 0)
+        .then(|| Semaphore::new(max_open_fds.saturating_sub(RESERVED_FDS) / FDS_PER_TASK))
+}
+
+/// Formats a byte count with an automatically chosen unit: KiB, MiB, GiB or TiB for binary
+/// units and KB, MB, GB or TB for SI units, the same labels [`parse_size`] reads, so a printed
+/// size can be passed back to a SIZE option unchanged.
+///
+/// # Arguments
+/// * `bytes` - The size to format.
+/// * `units` - Whether a kilobyte is 1024 (`Binary`) or 1000 (`Si`) bytes.
+pub fn format_size(bytes: u64, units: Units) -> String {
+    let (base, suffixes) = match units {
+        Units::Binary => (1024.0, ["KiB", "MiB", "GiB", "TiB"]),
+        Units::Si => (1000.0, ["KB", "MB", "GB", "TB"]),
+    };
+    if (bytes as f64) < base {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / base;
+    let mut suffix = 0;
+    while value >= base && suffix < suffixes.len() - 1 {
+        value /= base;
+        suffix += 1;
+    }
+    format!("{:.2} {}", value, suffixes[suffix])
+}
+
+/// Formats a count with the thousands separator of `locale`, e.g. `1,234,567` for `En`.
+pub fn format_count(count: u64, locale: SummaryLocale) -> String {
+    let digits = count.to_string();
+    let separator = match locale {
+        SummaryLocale::En => ',',
+        SummaryLocale::De => '.',
+        SummaryLocale::Fr => '\u{202f}',
+        SummaryLocale::Plain => return digits,
+    };
+    let mut formatted =
+        String::with_capacity(digits.len() + digits.len() / 3 * separator.len_utf8());
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(separator);
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Splits a comma-separated pattern list, keeping commas inside `{...}` groups, so
+/// `{target,build},dist` yields `{target,build}` and `dist`.
+pub fn split_patterns(list: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&list[start..]);
+    parts
+}
+
+/// Expands shell-style brace alternatives into every combination: `a{b,c}d` becomes `abd` and
+/// `acd`, and groups may nest (`{x,y{1,2}}`). A group without a comma or without its closing
+/// brace is kept as written.
+pub fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(open) = pattern.find('{') else {
+        return vec![pattern.to_string()];
+    };
+    let mut depth = 0;
+    let close = pattern[open..].char_indices().find_map(|(i, c)| {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        (depth == 0).then_some(open + i)
+    });
+    let Some(close) = close else {
+        return vec![pattern.to_string()];
+    };
+    let (prefix, inner, suffix) = (
+        &pattern[..open],
+        &pattern[open + 1..close],
+        &pattern[close + 1..],
+    );
+    let alternatives = split_patterns(inner);
+    if alternatives.len() == 1 {
+        return expand_braces(suffix)
+            .into_iter()
+            .map(|rest| format!("{}{}", &pattern[..=close], rest))
+            .collect();
+    }
+    alternatives
+        .into_iter()
+        .flat_map(|alternative| expand_braces(&format!("{}{}{}", prefix, alternative, suffix)))
+        .collect()
+}
+
+/// Parses a human-friendly duration such as `90s`, `5m` or `1h 30m`.
+pub fn parse_duration(value: &str) -> Result<Duration, CleanerError> {
+    humantime::parse_duration(value).map_err(|e| CleanerError::InvalidDuration {
+        value: value.to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// A daily time window such as `02:00-05:00`, from its start up to (not including) its end. The
+/// end may be earlier than the start for a window spanning midnight, like `22:00-06:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    /// Parses `HH:MM-HH:MM`.
+    pub fn parse(value: &str) -> Result<TimeWindow, CleanerError> {
+        let invalid = |message: &str| CleanerError::InvalidTimeWindow {
+            value: value.to_string(),
+            message: message.to_string(),
+        };
+        let (start, end) = value
+            .split_once('-')
+            .ok_or_else(|| invalid("expected HH:MM-HH:MM"))?;
+        let (start, end) = (parse_time_of_day(start)?, parse_time_of_day(end)?);
+        if start == end {
+            return Err(invalid("start and end must differ"));
+        }
+        Ok(TimeWindow { start, end })
+    }
+
+    /// Returns whether `time` falls inside the window.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl std::fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// Parses a time of day such as `02:30`.
+pub fn parse_time_of_day(value: &str) -> Result<NaiveTime, CleanerError> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").map_err(|e| CleanerError::InvalidTimeWindow {
+        value: value.to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Parses a size such as `500`, `10KB`, `1.5 GiB` or `200M`.
+///
+/// `KB`, `MB`, `GB` and `TB` are powers of 1000; `KiB`, `MiB`, ... and the bare `K`, `M`, `G`,
+/// `T` are powers of 1024. Suffixes are case-insensitive and a plain number means bytes.
+pub fn parse_size(value: &str) -> Result<u64, CleanerError> {
+    let invalid = |message: &str| CleanerError::InvalidSize {
+        value: value.to_string(),
+        message: message.to_string(),
+    };
+    let trimmed = value.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, suffix) = trimmed.split_at(split);
+    let number: f64 = number.parse().map_err(|_| invalid("expected a number"))?;
+    let multiplier: u64 = match suffix.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000u64.pow(2),
+        "gb" => 1000u64.pow(3),
+        "tb" => 1000u64.pow(4),
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        _ => {
+            return Err(invalid(
+                "unknown unit, expected B, KB, MB, GB, TB, KiB, MiB, GiB or TiB",
+            ))
+        }
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Returns true for I/O errors that may go away on retry (typical of network filesystems),
+/// as opposed to permanent ones such as permission denied.
+pub fn is_transient(err: &io::Error) -> bool {
+    // EIO has no dedicated ErrorKind
+    #[cfg(unix)]
+    if err.raw_os_error() == Some(5) {
+        return true;
+    }
+    matches!(
+        err.kind(),
+        io::ErrorKind::ResourceBusy
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::StaleNetworkFileHandle
+    )
+}
+
+/// Runs `op`, retrying up to `retries` times with exponential backoff (starting at `base_delay`)
+/// while it fails with a transient error. Permanent errors are returned immediately.
+pub fn retry_with_backoff<T>(
+    retries: u32,
+    base_delay: Duration,
+    mut op: impl FnMut() -> io::Result<T>,
+) -> io::Result<T> {
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e) if attempt < retries && is_transient(&e) => {
+                let delay = base_delay * 2u32.pow(attempt);
+                debug!("transient error ({}), retrying in {:?}", e, delay);
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Shared package-manager caches that are never cleaned unless --allow-package-caches is given,
+/// relative to the home directory.
+pub const PACKAGE_CACHE_ROOTS: &[&str] = &[
+    "~/.cargo",
+    "~/.rustup",
+    "~/.npm",
+    "~/.pnpm-store",
+    "~/.yarn",
+    "~/.m2",
+    "~/.gradle",
+    "~/.ivy2",
+    "~/.nuget/packages",
+    "~/.gem",
+    "~/.composer",
+    "~/go/pkg/mod",
+    "~/.cache/pip",
+    "~/Library/Caches",
+];
+
+/// Expands a leading `~` to the home directory (`HOME`, or `USERPROFILE` on Windows).
+pub fn expand_home(path: &str) -> PathBuf {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    match (path.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest.trim_start_matches(['/', '\\'])),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// The canonicalized built-in package cache roots plus `extra` entries (from
+/// `[general] always_exclude`). Roots that do not exist are left out.
+pub fn protected_roots(extra: &[String]) -> Vec<PathBuf> {
+    PACKAGE_CACHE_ROOTS
+        .iter()
+        .copied()
+        .chain(extra.iter().map(String::as_str))
+        .filter_map(|root| std::fs::canonicalize(expand_home(root)).ok())
+        .collect()
+}
+
+/// Fails with `AlreadyExists` if `dest` exists, and creates its missing parents otherwise.
+fn prepare_destination(dest: &Path) -> io::Result<()> {
+    if dest.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", dest.display()),
+        ));
+    }
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(())
+}
+
+/// Moves the directory `src` to `dest`, creating missing parents of `dest`.
+///
+/// Uses `rename` and falls back to copying then removing `src` when the two are on different
+/// filesystems. Fails with `AlreadyExists` instead of replacing an existing `dest`.
+pub fn move_dir(src: &Path, dest: &Path) -> io::Result<()> {
+    prepare_destination(dest)?;
+    match std::fs::rename(src, dest) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_dir(src, dest)?;
+            std::fs::remove_dir_all(src)
+        }
+        result => result,
+    }
+}
+
+/// Like [`move_dir`], but all or nothing when `src` and `dest` are on different filesystems:
+/// the copy is staged next to `dest` and renamed into place only once complete, and `src` is
+/// renamed aside before it is deleted. If the copy fails, `src` is left intact and nothing is
+/// left at `dest`.
+pub fn move_dir_atomic(src: &Path, dest: &Path) -> io::Result<()> {
+    prepare_destination(dest)?;
+    match std::fs::rename(src, dest) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            transfer_atomically(src, dest, copy_dir)
+        }
+        result => result,
+    }
+}
+
+/// The cross-filesystem part of [`move_dir_atomic`], with the copy step passed in.
+fn transfer_atomically(
+    src: &Path,
+    dest: &Path,
+    copy: impl FnOnce(&Path, &Path) -> io::Result<()>,
+) -> io::Result<()> {
+    let staging = sibling_path(dest, "partial");
+    // Left over by an interrupted run
+    let _ = remove_path(&staging);
+    let committed = copy(src, &staging).and_then(|()| std::fs::rename(&staging, dest));
+    if let Err(e) = committed {
+        let _ = remove_path(&staging);
+        return Err(e);
+    }
+    let aside = sibling_path(src, "removing");
+    if let Err(e) = std::fs::rename(src, &aside) {
+        // Roll back: `src` is still complete where it was
+        let _ = remove_path(dest);
+        return Err(e);
+    }
+    remove_path(&aside)
+}
+
+/// Removes `path` all or nothing as seen from where it was: it is renamed aside first, which
+/// either fully succeeds or leaves it untouched, and only then deleted.
+pub fn remove_aside(path: &Path) -> io::Result<()> {
+    let aside = sibling_path(path, "removing");
+    std::fs::rename(path, &aside)?;
+    remove_path(&aside)
+}
+
+/// A hidden sibling of `path` used while it is staged or set aside, e.g.
+/// `.target.cleaner-partial` next to `target`.
+pub fn sibling_path(path: &Path, purpose: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.cleaner-{}", name, purpose))
+}
+
+/// Removes a directory recursively, or a single file or symlink.
+fn remove_path(path: &Path) -> io::Result<()> {
+    if std::fs::symlink_metadata(path)?.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+/// Returns the space available to unprivileged users on the filesystem holding `path`, in bytes.
+///
+/// Uses `statvfs` on Unix; other platforms return an `Unsupported` error.
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // The field types differ between platforms
+        #[allow(clippy::unnecessary_cast)]
+        Ok(stats.f_bavail as u64 * stats.f_frsize as u64)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "free space cannot be queried on this platform",
+        ))
+    }
+}
+
+/// The current user's lock directory: `$XDG_RUNTIME_DIR/cleaner-locks`, or
+/// `$TMPDIR/cleaner-locks-<uid>` without a runtime directory. Being private to the user, no
+/// other user can create or hold a lock in it.
+fn lock_dir() -> PathBuf {
+    #[cfg(unix)]
+    {
+        if let Some(runtime) = std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+            return PathBuf::from(runtime).join("cleaner-locks");
+        }
+        let uid = unsafe { libc::getuid() };
+        std::env::temp_dir().join(format!("cleaner-locks-{}", uid))
+    }
+    #[cfg(not(unix))]
+    {
+        std::env::temp_dir().join("cleaner-locks")
+    }
+}
+
+/// Creates the lock directory `dir` if needed (mode 0700 on Unix) and checks that it is a real
+/// directory owned by the current user that nobody else can write to, so a directory planted
+/// in a shared location by another user is refused rather than used.
+fn private_lock_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+        match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                std::fs::create_dir_all(dir.parent().unwrap_or(dir))?;
+                std::fs::DirBuilder::new().mode(0o700).create(dir)?;
+            }
+            Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+            _ => {}
+        }
+        let meta = std::fs::symlink_metadata(dir)?;
+        let uid = unsafe { libc::getuid() };
+        if !meta.is_dir() || meta.uid() != uid || meta.mode() & 0o077 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "{} is not a private directory of the current user",
+                    dir.display()
+                ),
+            ));
+        }
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    {
+        std::fs::create_dir_all(dir)
+    }
+}
+
+/// The lock file guarding cleaning runs on `root`: one per canonical root path, in the current
+/// user's lock directory.
+pub fn lock_path(root: &Path) -> PathBuf {
+    let canonical = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let mut hasher = DefaultHasher::new();
+    canonical.hash(&mut hasher);
+    lock_dir().join(format!("{:016x}.lock", hasher.finish()))
+}
+
+/// Takes the exclusive lock for cleaning `root`, so overlapping runs on the same tree cannot
+/// race on deletions. With `wait` this blocks until the other run is done; otherwise a held
+/// lock fails with [`CleanerError::AlreadyRunning`].
+///
+/// The lock is held until the returned file is dropped, and the OS releases it if the process
+/// dies. Locks live in a directory private to the current user (see [`lock_dir`]), so they only
+/// keep apart runs of the same user.
+pub fn lock_root(root: &Path, wait: bool) -> Result<std::fs::File, CleanerError> {
+    let lock = lock_path(root);
+    let lock_error = |source| CleanerError::Lock {
+        path: lock.clone(),
+        source,
+    };
+    if let Some(parent) = lock.parent() {
+        private_lock_dir(parent).map_err(lock_error)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock)
+        .map_err(lock_error)?;
+    if wait {
+        file.lock().map_err(lock_error)?;
+    } else {
+        file.try_lock().map_err(|e| match e {
+            std::fs::TryLockError::WouldBlock => CleanerError::AlreadyRunning {
+                root: root.to_path_buf(),
+                lock: lock.clone(),
+            },
+            std::fs::TryLockError::Error(source) => lock_error(source),
+        })?;
+    }
+    Ok(file)
+}
+
+/// Returns the ID of the device (filesystem) holding `path`.
+///
+/// Returns `None` where device IDs are not available (non-Unix platforms) or `path` cannot be
+/// inspected.
+pub fn device_id(path: &Path) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::metadata(path).ok().map(|meta| meta.dev())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Returns whether `a` and `b` are on the same filesystem, by comparing device IDs. A path that
+/// does not exist yet (such as a staging directory to be created) is judged by its closest
+/// existing ancestor.
+///
+/// Returns `None` where device IDs are not available (non-Unix platforms) or a path cannot be
+/// inspected at all.
+pub fn same_device(a: &Path, b: &Path) -> Option<bool> {
+    let device = |path: &Path| path.ancestors().find_map(device_id);
+    Some(device(a)? == device(b)?)
+}
+
+/// Returns `path` with a timestamp suffix (e.g. `target.20250101-120000`) that does not exist
+/// yet, adding a counter if needed.
+pub fn timestamped_path(path: &Path) -> PathBuf {
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut candidate = path.with_file_name(format!("{}.{}", name, stamp));
+    let mut counter = 1;
+    while candidate.exists() {
+        candidate = path.with_file_name(format!("{}.{}-{}", name, stamp, counter));
+        counter += 1;
+    }
+    candidate
+}
+
+/// Recursively copies `src` to `dest`, recreating symlinks rather than following them.
+fn copy_dir(src: &Path, dest: &Path) -> io::Result<()> {
+    for entry in WalkDir::new(src) {
+        let entry = entry.map_err(io::Error::other)?;
+        let rel = entry.path().strip_prefix(src).map_err(io::Error::other)?;
+        let target = dest.join(rel);
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else if file_type.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Lists the git submodule directories declared in `dir/.gitmodules`, resolved against `dir`.
+///
+/// Only the `path = ...` entries are read; a missing or unreadable file yields an empty list.
+pub fn git_submodules(dir: &Path) -> Vec<PathBuf> {
+    let Ok(contents) = std::fs::read_to_string(dir.join(".gitmodules")) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "path").then(|| dir.join(value.trim()))
+        })
+        .collect()
+}
+
+/// Ignore files whose directory entries --infer-from-ignores turns into clean targets.
+pub const IGNORE_FILES: [&str; 2] = [".gitignore", ".dockerignore"];
+
+/// Returns the entries of an ignore file (`.gitignore` syntax) that look like build output:
+/// directory entries (ending in `/`) and names of `known` build directories. Comments,
+/// negations and `**` patterns are left out, and leading `/` or `**/` anchors are dropped.
+pub fn ignored_build_dirs(contents: &str, known: &[String]) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("**/").unwrap_or(line);
+            let line = line.strip_prefix('/').unwrap_or(line);
+            let (name, is_dir) = match line.strip_suffix('/') {
+                Some(name) => (name, true),
+                None => (line, false),
+            };
+            let usable = !name.is_empty()
+                && !name.contains("**")
+                && !matches!(name, "." | ".." | ".git")
+                && glob::Pattern::new(name).is_ok();
+            (usable && (is_dir || known.iter().any(|dir| dir == name))).then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Collects the build-output entries (see [`ignored_build_dirs`]) of every `.gitignore` and
+/// `.dockerignore` under `root`, without duplicates. Directories named in `known` and `.git` are
+/// not descended into, and `max_depth` (0 for unlimited) bounds the search.
+pub fn dirs_from_ignore_files(root: &Path, known: &[String], max_depth: usize) -> Vec<String> {
+    let mut walker = WalkDir::new(root);
+    if max_depth > 0 {
+        walker = walker.max_depth(max_depth);
+    }
+    let mut dirs: Vec<String> = Vec::new();
+    let entries = walker.into_iter().filter_entry(|entry| {
+        let name = entry.file_name().to_string_lossy();
+        entry.depth() == 0
+            || !entry.file_type().is_dir()
+            || !(name == ".git" || known.iter().any(|dir| *dir == name))
+    });
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy();
+        if !entry.file_type().is_file() || !IGNORE_FILES.contains(&name.as_ref()) {
+            continue;
+        }
+        let Ok(contents) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        for dir in ignored_build_dirs(&contents, known) {
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+    dirs
+}
+
+/// Lists the member directories of the Cargo workspace rooted at `root`.
+///
+/// Reads `[workspace] members` from `root/Cargo.toml`, expanding glob entries such as
+/// `crates/*` and dropping anything listed in `[workspace] exclude`.
+pub fn workspace_members(root: &Path) -> Result<Vec<PathBuf>, CleanerError> {
+    let manifest_path = root.join("Cargo.toml");
+    let manifest_error = |message: String| CleanerError::Manifest {
+        path: manifest_path.clone(),
+        message,
+    };
+    let contents =
+        std::fs::read_to_string(&manifest_path).map_err(|e| manifest_error(e.to_string()))?;
+    let manifest: toml::Value =
+        toml::from_str(&contents).map_err(|e| manifest_error(e.to_string()))?;
+    let workspace = manifest
+        .get("workspace")
+        .ok_or_else(|| manifest_error("no [workspace] section".to_string()))?;
+    let entries = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let excluded: Vec<PathBuf> = entries("exclude").iter().map(|e| root.join(e)).collect();
+    let mut members = Vec::new();
+    for member in entries("members") {
+        let pattern = root.join(&member);
+        let matches = glob::glob(&pattern.to_string_lossy())
+            .map_err(|e| manifest_error(format!("invalid member '{}': {}", member, e)))?;
+        for path in matches.flatten() {
+            if path.is_dir() && !excluded.contains(&path) && !members.contains(&path) {
+                members.push(path);
+            }
+        }
+    }
+    Ok(members)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::args::ProjectKind;
+    use crate::args::{ProjectKind, Units};
+    use crate::error::CleanerError;
+
+    #[test]
+    fn test_ignored_build_dirs() {
+        let known = vec!["target".to_string(), "dist".to_string()];
+        let gitignore = "# build output\nout/\n/generated/\n**/coverage/\n/target\ndist\n*.log\n.env\n!keep/\n**/\n.git/\nsrc/**/gen/\n";
+        assert_eq!(
+            ignored_build_dirs(gitignore, &known),
+            ["out", "generated", "coverage", "target", "dist"]
+        );
+    }
+
+    #[test]
+    fn test_dirs_from_ignore_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        std::fs::create_dir_all(root.join("web/node_modules/pkg")).unwrap();
+        std::fs::write(root.join(".gitignore"), "out/\n").unwrap();
+        std::fs::write(root.join("web/.dockerignore"), "out/\n.cache/\n").unwrap();
+        // Not read: inside a directory that is cleaned anyway
+        std::fs::write(root.join("web/node_modules/pkg/.gitignore"), "lib/\n").unwrap();
+        let known = vec!["node_modules".to_string()];
+        assert_eq!(dirs_from_ignore_files(root, &known, 0), ["out", ".cache"]);
+        assert_eq!(dirs_from_ignore_files(root, &known, 1), ["out"]);
+    }
+
+    #[test]
+    fn test_time_window() {
+        let at = |value| parse_time_of_day(value).unwrap();
+        let night = TimeWindow::parse("02:00-05:00").unwrap();
+        assert!(night.contains(at("02:00")));
+        assert!(night.contains(at("04:59")));
+        assert!(!night.contains(at("05:00")));
+        assert!(!night.contains(at("14:30")));
+        let wrapping = TimeWindow::parse("22:00-06:00").unwrap();
+        assert!(wrapping.contains(at("23:15")));
+        assert!(wrapping.contains(at("00:30")));
+        assert!(!wrapping.contains(at("12:00")));
+        assert_eq!(wrapping.to_string(), "22:00-06:00");
+        assert!(matches!(
+            TimeWindow::parse("2am-5am"),
+            Err(CleanerError::InvalidTimeWindow { .. })
+        ));
+        assert!(TimeWindow::parse("02:00").is_err());
+        assert!(TimeWindow::parse("03:00-03:00").is_err());
+    }
+
+    #[test]
+    fn test_stale_lockfile() {
+        let temp = tempfile::tempdir().unwrap();
+        let deps = temp.path().join("node_modules");
+        let build = temp.path().join("build");
+        std::fs::create_dir(&deps).unwrap();
+        std::fs::create_dir(&build).unwrap();
+        let lockfile = temp.path().join("package-lock.json");
+        std::fs::write(&lockfile, "{}").unwrap();
+        let set_modified = |path: &Path, secs: u64| {
+            std::fs::File::open(path)
+                .unwrap()
+                .set_modified(UNIX_EPOCH + Duration::from_secs(secs))
+                .unwrap();
+        };
+        set_modified(&deps, 1_000);
+        set_modified(&lockfile, 2_000);
+        assert_eq!(stale_lockfile(&deps), Some(lockfile.clone()));
+        set_modified(&lockfile, 500);
+        assert_eq!(stale_lockfile(&deps), None);
+        assert_eq!(stale_lockfile(&build), None);
+    }
+
+    #[test]
+    fn test_language_of() {
+        let temp = tempfile::tempdir().unwrap();
+        let java = temp.path().join("java");
+        let rust = temp.path().join("rust");
+        std::fs::create_dir_all(&java).unwrap();
+        std::fs::create_dir_all(&rust).unwrap();
+        std::fs::write(java.join("pom.xml"), "").unwrap();
+        std::fs::write(rust.join("Cargo.toml"), "").unwrap();
+        std::fs::write(rust.join("package.json"), "").unwrap();
+        // Shared names are settled by the project's markers
+        assert_eq!(
+            language_of(&java.join("target"), &java),
+            Some(ProjectKind::Java)
+        );
+        assert_eq!(
+            language_of(&rust.join("target"), &rust),
+            Some(ProjectKind::Rust)
+        );
+        // A name of one kind wins over the markers
+        assert_eq!(
+            language_of(&rust.join("node_modules"), &rust),
+            Some(ProjectKind::Node)
+        );
+        // Unknown names fall back to the markers
+        assert_eq!(
+            language_of(&java.join("scratch"), &java),
+            Some(ProjectKind::Java)
+        );
+        assert_eq!(language_of(&temp.path().join("scratch"), temp.path()), None);
+    }
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(expand_braces("a{b,c}d"), ["abd", "acd"]);
+        assert_eq!(
+            expand_braces("{target,build,out}"),
+            ["target", "build", "out"]
+        );
+        assert_eq!(expand_braces("{x,y{1,2}}z"), ["xz", "y1z", "y2z"]);
+        assert_eq!(expand_braces("{a,b}{1,2}"), ["a1", "a2", "b1", "b2"]);
+        assert_eq!(expand_braces("{only}{a,b}"), ["{only}a", "{only}b"]);
+        assert_eq!(expand_braces("open{a,b"), ["open{a,b"]);
+        assert_eq!(expand_braces("plain*"), ["plain*"]);
+    }
+
+    #[test]
+    fn test_split_patterns_keeps_brace_groups() {
+        assert_eq!(
+            split_patterns("{target,build},dist,a{b,{c,d}}"),
+            ["{target,build}", "dist", "a{b,{c,d}}"]
+        );
+        assert_eq!(split_patterns("target"), ["target"]);
+    }
+
+    #[test]
+    fn test_semaphore_bounds_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let semaphore = Semaphore::new(2);
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    let _permit = semaphore.acquire();
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(std::time::Duration::from_millis(10));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn test_fd_limiter() {
+        assert!(fd_limiter(0).is_none());
+        // Very low limits still allow one operation at a time
+        let limiter = fd_limiter(8).unwrap();
+        let _permit = limiter.acquire();
+    }
+
+    #[test]
+    fn test_format_size_binary() {
+        assert_eq!(format_size(0, Units::Binary), "0 B");
+        assert_eq!(format_size(1023, Units::Binary), "1023 B");
+        assert_eq!(format_size(1024, Units::Binary), "1.00 KiB");
+        assert_eq!(format_size(1_000_000, Units::Binary), "976.56 KiB");
+        assert_eq!(format_size(1_048_576, Units::Binary), "1.00 MiB");
+        assert_eq!(format_size(1_073_741_824, Units::Binary), "1.00 GiB");
+        assert_eq!(
+            format_size(5 * 1_099_511_627_776, Units::Binary),
+            "5.00 TiB"
+        );
+        // Beyond TB the value keeps growing instead of switching unit
+        assert_eq!(
+            format_size(2048 * 1_099_511_627_776, Units::Binary),
+            "2048.00 TiB"
+        );
+    }
+
+    #[test]
+    fn test_format_size_si() {
+        assert_eq!(format_size(999, Units::Si), "999 B");
+        assert_eq!(format_size(1000, Units::Si), "1.00 KB");
+        assert_eq!(format_size(1023, Units::Si), "1.02 KB");
+        assert_eq!(format_size(1024, Units::Si), "1.02 KB");
+        assert_eq!(format_size(1_000_000, Units::Si), "1.00 MB");
+        assert_eq!(format_size(1_500_000_000, Units::Si), "1.50 GB");
+        assert_eq!(format_size(1_000_000_000_000, Units::Si), "1.00 TB");
+    }
+
+    #[test]
+    fn test_workspace_members_expands_globs() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        for member in ["crates/a", "crates/b", "crates/skip", "tools/cli"] {
+            std::fs::create_dir_all(root.join(member)).unwrap();
+        }
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\", \"tools/cli\"]\nexclude = [\"crates/skip\"]\n",
+        )
+        .unwrap();
+        let mut members = workspace_members(root).unwrap();
+        members.sort();
+        assert_eq!(
+            members,
+            vec![
+                root.join("crates/a"),
+                root.join("crates/b"),
+                root.join("tools/cli")
+            ]
+        );
+        std::fs::write(root.join("Cargo.toml"), "[package]\nname = \"x\"\n").unwrap();
+        assert!(matches!(
+            workspace_members(root),
+            Err(CleanerError::Manifest { .. })
+        ));
+    }
+
+    #[test]
+    fn test_dir_size_is_recursive() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(temp.path().join("a/b")).unwrap();
+        std::fs::write(temp.path().join("a/one"), [0u8; 10]).unwrap();
+        std::fs::write(temp.path().join("a/b/two"), [0u8; 32]).unwrap();
+        assert_eq!(dir_size(temp.path()), 42);
+    }
+
+    #[test]
+    fn test_move_dir_refuses_to_overwrite() {
+        let temp = tempfile::tempdir().unwrap();
+        let src = temp.path().join("src/target");
+        std::fs::create_dir_all(src.join("debug")).unwrap();
+        std::fs::write(src.join("debug/app"), b"bin").unwrap();
+        let dest = temp.path().join("staging/src/target");
+        move_dir(&src, &dest).unwrap();
+        assert!(!src.exists());
+        assert!(dest.join("debug/app").is_file());
+        std::fs::create_dir_all(&src).unwrap();
+        let err = move_dir(&src, &dest).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(src.exists());
+    }
+
+    #[test]
+    fn test_failed_atomic_transfer_leaves_source_intact() {
+        let temp = tempfile::tempdir().unwrap();
+        let src = temp.path().join("src/target");
+        std::fs::create_dir_all(src.join("debug")).unwrap();
+        std::fs::write(src.join("debug/app"), b"bin").unwrap();
+        std::fs::write(src.join("log"), b"log").unwrap();
+        let dest = temp.path().join("staging/src/target");
+        std::fs::create_dir_all(dest.parent().unwrap()).unwrap();
+        // Copies one file, then fails as a full disk would
+        let result = transfer_atomically(&src, &dest, |from, to| {
+            std::fs::create_dir_all(to)?;
+            std::fs::copy(from.join("log"), to.join("log"))?;
+            Err(io::Error::other("no space left on device"))
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(src.join("debug/app")).unwrap(), b"bin");
+        assert_eq!(std::fs::read(src.join("log")).unwrap(), b"log");
+        assert!(!dest.exists());
+        assert!(!sibling_path(&dest, "partial").exists());
+
+        transfer_atomically(&src, &dest, copy_dir).unwrap();
+        assert!(!src.exists());
+        assert!(!sibling_path(&src, "removing").exists());
+        assert_eq!(std::fs::read(dest.join("debug/app")).unwrap(), b"bin");
+    }
+
+    #[test]
+    fn test_timestamped_path_is_unused() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("target");
+        let first = timestamped_path(&path);
+        assert!(first
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("target."));
+        std::fs::create_dir_all(&first).unwrap();
+        let second = timestamped_path(&path);
+        assert_ne!(first, second);
+        assert_eq!(second.parent(), path.parent());
+    }
+
+    #[test]
+    fn test_copy_dir_preserves_structure() {
+        let temp = tempfile::tempdir().unwrap();
+        let src = temp.path().join("a");
+        std::fs::create_dir_all(src.join("b/c")).unwrap();
+        std::fs::write(src.join("b/c/file"), b"data").unwrap();
+        copy_dir(&src, &temp.path().join("copy")).unwrap();
+        assert_eq!(
+            std::fs::read(temp.path().join("copy/b/c/file")).unwrap(),
+            b"data"
+        );
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(1234567, SummaryLocale::En), "1,234,567");
+        assert_eq!(format_count(1234567, SummaryLocale::De), "1.234.567");
+        assert_eq!(
+            format_count(1234567, SummaryLocale::Fr),
+            "1\u{202f}234\u{202f}567"
+        );
+        assert_eq!(format_count(1234567, SummaryLocale::Plain), "1234567");
+        assert_eq!(format_count(123456, SummaryLocale::En), "123,456");
+        assert_eq!(format_count(1234, SummaryLocale::En), "1,234");
+        assert_eq!(format_count(999, SummaryLocale::En), "999");
+        assert_eq!(format_count(0, SummaryLocale::En), "0");
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = PathBuf::from(std::env::var_os("HOME").unwrap());
+        assert_eq!(expand_home("~/.cargo"), home.join(".cargo"));
+        assert_eq!(expand_home("/opt/cache"), PathBuf::from("/opt/cache"));
+    }
+
+    #[test]
+    fn test_checkpoint_resume() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("run.checkpoint");
+        let checkpoint = Checkpoint::open(&file, true).unwrap();
+        assert!(!checkpoint.is_done(Path::new("/w/a/target")));
+        checkpoint.record(Path::new("/w/a/target")).unwrap();
+        checkpoint.record(Path::new("/w/b/target")).unwrap();
+        drop(checkpoint);
+        let resumed = Checkpoint::open(&file, true).unwrap();
+        assert!(resumed.is_done(Path::new("/w/a/target")));
+        assert!(resumed.is_done(Path::new("/w/b/target")));
+        resumed.record(Path::new("/w/c/target")).unwrap();
+        drop(resumed);
+        assert_eq!(std::fs::read_to_string(&file).unwrap().lines().count(), 3);
+        // Without --resume a new run starts over
+        let fresh = Checkpoint::open(&file, false).unwrap();
+        assert!(!fresh.is_done(Path::new("/w/a/target")));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "");
+    }
+
+    #[test]
+    fn test_size_cache_reuses_until_mtime_changes() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("target");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a"), [0u8; 10]).unwrap();
+        let cache_file = temp.path().join("cache/sizes.json");
+        let cache = SizeCache::load(&cache_file);
+        assert_eq!(cache.dir_size(&dir), 10);
+        assert_eq!(cache.dir_size(&dir), 10);
+        assert_eq!(cache.misses.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.hits.load(Ordering::Relaxed), 1);
+        cache.save().unwrap();
+
+        // A fresh process reuses the saved size
+        let cache = SizeCache::load(&cache_file);
+        assert_eq!(cache.dir_size(&dir), 10);
+        assert_eq!(cache.hits.load(Ordering::Relaxed), 1);
+
+        // Adding an entry changes the directory mtime and forces a recount
+        std::fs::write(dir.join("b"), [0u8; 5]).unwrap();
+        std::fs::File::open(&dir)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() + Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(cache.dir_size(&dir), 15);
+        assert_eq!(cache.misses.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("1h 30m").unwrap(), Duration::from_secs(5400));
+        assert!(matches!(
+            parse_duration("soon"),
+            Err(CleanerError::InvalidDuration { .. })
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_same_device() {
+        let temp = tempfile::tempdir().unwrap();
+        let inside = temp.path().join("not/created/yet");
+        assert_eq!(same_device(temp.path(), &inside), Some(true));
+        // procfs is always its own filesystem
+        assert_eq!(
+            same_device(temp.path(), Path::new("/proc/self")),
+            Some(false)
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_device_id() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::create_dir(temp.path().join("sub")).unwrap();
+        assert!(device_id(temp.path()).is_some());
+        assert_eq!(device_id(temp.path()), device_id(&temp.path().join("sub")));
+        assert_ne!(device_id(temp.path()), device_id(Path::new("/proc")));
+        assert_eq!(device_id(&temp.path().join("missing")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_available_space() {
+        let temp = tempfile::tempdir().unwrap();
+        assert!(available_space(temp.path()).unwrap() > 0);
+        assert!(available_space(&temp.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_lock_root_is_exclusive() {
+        let temp = tempfile::tempdir().unwrap();
+        let held = lock_root(temp.path(), false).unwrap();
+        assert!(matches!(
+            lock_root(temp.path(), false),
+            Err(CleanerError::AlreadyRunning { .. })
+        ));
+        // Another spelling of the same root maps to the same lock
+        assert_eq!(lock_path(&temp.path().join(".")), lock_path(temp.path()));
+        drop(held);
+        lock_root(temp.path(), false).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_lock_dir_is_private() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("locks");
+        private_lock_dir(&dir).unwrap();
+        let meta = std::fs::metadata(&dir).unwrap();
+        assert_eq!(meta.mode() & 0o777, 0o700);
+        assert_eq!(meta.uid(), unsafe { libc::getuid() });
+        // A directory others can write to, e.g. planted in /tmp, is refused
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o1777)).unwrap();
+        let err = private_lock_dir(&dir).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(lock_path(temp.path()).starts_with(lock_dir()));
+    }
+
+    #[test]
+    fn test_detect_project_kind() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        assert_eq!(detect_project_kind(root), None);
+        std::fs::create_dir(root.join("api")).unwrap();
+        std::fs::write(root.join("api/Api.csproj"), "").unwrap();
+        assert_eq!(
+            detect_project_kind(&root.join("api")),
+            Some(ProjectKind::CSharp)
+        );
+        std::fs::write(root.join("Cargo.toml"), "").unwrap();
+        assert_eq!(detect_project_kind(root), Some(ProjectKind::Rust));
+        assert_eq!(detect_project_kind(&root.join("missing")), None);
+    }
+
+    #[test]
+    fn test_first_unreadable() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("target");
+        std::fs::create_dir_all(dir.join("debug")).unwrap();
+        std::fs::write(dir.join("debug/app"), "binary").unwrap();
+        assert!(first_unreadable(&dir).is_none());
+        let (missing, _) = first_unreadable(&temp.path().join("gone")).unwrap();
+        assert_eq!(missing, temp.path().join("gone"));
+    }
+
+    #[test]
+    fn test_has_marker() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        assert!(!has_marker(dir, "CACHEDIR.TAG"));
+        std::fs::write(dir.join("CACHEDIR.TAG"), "not a tag").unwrap();
+        assert!(!has_marker(dir, "CACHEDIR.TAG"));
+        let mut tag = CACHEDIR_TAG_SIGNATURE.to_vec();
+        tag.extend_from_slice(b"\n# This file is a cache directory tag.\n");
+        std::fs::write(dir.join("CACHEDIR.TAG"), tag).unwrap();
+        assert!(has_marker(dir, "CACHEDIR.TAG"));
+        std::fs::write(dir.join(".cache-marker"), "").unwrap();
+        assert!(has_marker(dir, ".cache-marker"));
+    }
+
+    #[test]
+    fn test_git_submodules() {
+        let temp = tempfile::tempdir().unwrap();
+        assert!(git_submodules(temp.path()).is_empty());
+        std::fs::write(
+            temp.path().join(".gitmodules"),
+            "[submodule \"vendor/lib\"]\n\tpath = vendor/lib\n\turl = https://example.com/lib.git\n\
+             [submodule \"docs\"]\n\tpath=docs\n",
+        )
+        .unwrap();
+        assert_eq!(
+            git_submodules(temp.path()),
+            vec![temp.path().join("vendor/lib"), temp.path().join("docs")]
+        );
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("500").unwrap(), 500);
+        assert_eq!(parse_size("10KB").unwrap(), 10_000);
+        assert_eq!(parse_size("10 kib").unwrap(), 10_240);
+        assert_eq!(parse_size("1.5G").unwrap(), 1_610_612_736);
+        assert_eq!(parse_size("2MB").unwrap(), 2_000_000);
+        assert!(matches!(
+            parse_size("lots"),
+            Err(CleanerError::InvalidSize { .. })
+        ));
+        assert!(matches!(
+            parse_size("5 parsecs"),
+            Err(CleanerError::InvalidSize { .. })
+        ));
+        // A printed size reads back as the same amount in either unit system
+        assert_eq!(
+            parse_size(&format_size(500 << 20, Units::Binary)).unwrap(),
+            500 << 20
+        );
+        assert_eq!(
+            parse_size(&format_size(500_000_000, Units::Si)).unwrap(),
+            500_000_000
+        );
+    }
+
+    #[test]
+    fn test_retry_succeeds_after_transient_error() {
+        let mut attempts = 0;
+        let result = retry_with_backoff(3, Duration::from_millis(1), || {
+            attempts += 1;
+            if attempts == 1 {
+                Err(io::Error::from(io::ErrorKind::ResourceBusy))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_retry_gives_up_on_permanent_error() {
+        let mut attempts = 0;
+        let result: io::Result<()> = retry_with_backoff(3, Duration::from_millis(1), || {
+            attempts += 1;
+            Err(io::Error::from(io::ErrorKind::PermissionDenied))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_retry_stops_after_retries() {
+        let mut attempts = 0;
+        let result: io::Result<()> = retry_with_backoff(2, Duration::from_millis(1), || {
+            attempts += 1;
+            Err(io::Error::from(io::ErrorKind::ResourceBusy))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+    }
 
     #[test]
     fn test_default_dirs_for_rust() {
@@ -238,4 +1948,64 @@ mod tests {
         assert!(dirs.contains(&".bundle"));
         assert!(dirs.contains(&"tmp"));
     }
+
+    #[test]
+    fn test_default_dirs_for_ocaml() {
+        let dirs = default_dirs_for_kind(&ProjectKind::OCaml);
+        assert!(dirs.contains(&"_build"));
+        assert!(dirs.contains(&"_opam"));
+        assert!(dirs.contains(&".merlin"));
+    }
+
+    #[test]
+    fn test_default_dirs_for_erlang() {
+        let dirs = default_dirs_for_kind(&ProjectKind::Erlang);
+        assert!(dirs.contains(&"_build"));
+        assert!(dirs.contains(&"ebin"));
+        assert!(dirs.contains(&".rebar3"));
+        assert!(dirs.contains(&"deps"));
+    }
+
+    #[test]
+    fn test_dirs_for_kinds_union_and_dedup() {
+        let dirs = dirs_for_kinds(&[ProjectKind::Rust, ProjectKind::Node]);
+        assert_eq!(dirs[..3], ["target", "out", "build"]);
+        assert!(dirs.contains(&"node_modules".to_string()));
+        // `build` is cleaned by both kinds but listed once
+        assert_eq!(dirs.iter().filter(|d| *d == "build").count(), 1);
+        let mut unique = dirs.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), dirs.len());
+        assert!(dirs_for_kinds(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_default_dirs_for_cruft() {
+        let dirs = default_dirs_for_kind(&ProjectKind::Cruft);
+        assert!(dirs.contains(&".DS_Store"));
+        assert!(dirs.contains(&"npm-debug.log*"));
+        assert!(dirs.contains(&"*.pyc"));
+    }
+
+    #[test]
+    fn test_all_kinds_covers_every_variant() {
+        let kinds = all_kinds();
+        assert!(matches!(kinds[0], ProjectKind::All));
+        assert!(kinds.iter().any(|k| matches!(k, ProjectKind::Erlang)));
+        let every = dirs_for_kinds(kinds);
+        for kind in kinds {
+            for dir in default_dirs_for_kind(kind) {
+                assert!(every.contains(&dir.to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_default_dirs_for_monorepo() {
+        let dirs = default_dirs_for_kind(&ProjectKind::Monorepo);
+        assert!(dirs.contains(&".nx/cache"));
+        assert!(dirs.contains(&".turbo"));
+        assert!(dirs.contains(&"bazel-*"));
+    }
 }
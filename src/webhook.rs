@@ -0,0 +1,140 @@
+//! Posting the run summary to a webhook (behind the `webhook` cargo feature).
+//!
+//! Plain `http://` URLs are posted directly over a `TcpStream`; `https://` URLs are handed to
+//! `curl`, so no TLS stack is linked into the binary.
+
+use std::io;
+
+/// Sends `body` as an `application/json` POST to `url`. Any 2xx response is success.
+#[cfg(feature = "webhook")]
+pub fn post_json(url: &str, body: &str) -> io::Result<()> {
+    if let Some(rest) = url.strip_prefix("http://") {
+        post_plain_http(rest, body)
+    } else if url.starts_with("https://") {
+        post_with_curl(url, body)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "unsupported webhook URL {}: expected http:// or https://",
+                url
+            ),
+        ))
+    }
+}
+
+/// Without the `webhook` feature --webhook only fails with an explanation.
+#[cfg(not(feature = "webhook"))]
+pub fn post_json(_url: &str, _body: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "cleaner was built without the `webhook` feature",
+    ))
+}
+
+/// POST over HTTP/1.1 to `rest`, the URL without its `http://` scheme.
+#[cfg(feature = "webhook")]
+fn post_plain_http(rest: &str, body: &str) -> io::Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, "/"),
+    };
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+    let mut stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(10)))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        authority,
+        body.len(),
+        body
+    )?;
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line)?;
+    // e.g. "HTTP/1.1 204 No Content"
+    match status_line.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(io::Error::other(format!(
+            "webhook answered {}",
+            status_line.trim()
+        ))),
+    }
+}
+
+#[cfg(feature = "webhook")]
+fn post_with_curl(url: &str, body: &str) -> io::Result<()> {
+    use std::process::Command;
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time", "10"])
+        .args(["--header", "Content-Type: application/json"])
+        .args(["--data-binary", body, url])
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+#[cfg(all(test, feature = "webhook"))]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Accepts one connection, answers with `status` and returns the raw request.
+    fn serve_once(status: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            // The body is small, so headers and body arrive before the client waits for us
+            while !String::from_utf8_lossy(&request).contains("}") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_post_plain_http() {
+        let (url, server) = serve_once("204 No Content");
+        post_json(&url, r#"{"ok":true}"#).unwrap();
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Type: application/json\r\n"));
+        assert!(request.ends_with("\r\n\r\n{\"ok\":true}"));
+    }
+
+    #[test]
+    fn test_post_reports_error_status() {
+        let (url, server) = serve_once("500 Internal Server Error");
+        let err = post_json(&url, "{}").unwrap_err();
+        assert!(err.to_string().contains("500"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_post_rejects_other_schemes() {
+        let err = post_json("ftp://example.com/hook", "{}").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -1,7 +1,9 @@
 use assert_cmd::Command;
+use predicates::prelude::*;
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::Path;
+use std::time::{Duration, SystemTime};
 use tempfile::tempdir;
 
 // Helper to create a directory and a file inside it
@@ -13,6 +15,43 @@ fn create_dir_with_file(base: &Path, dir: &str, file: &str) {
     writeln!(f, "test").unwrap();
 }
 
+// Helper to backdate the modification time of a file or directory
+fn set_age(path: &Path, age: Duration) {
+    let file = File::open(path).unwrap();
+    file.set_modified(SystemTime::now() - age).unwrap();
+}
+
+// Helper to make a directory impossible to remove. Returns false when this cannot be arranged
+// (e.g. running as root without `chattr`), in which case the calling test should be skipped.
+#[cfg(unix)]
+fn make_unremovable(dir: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    File::create(dir.join("locked.txt")).unwrap();
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o555)).unwrap();
+    if File::create(dir.join("probe")).is_err() {
+        return true;
+    }
+    // Permission bits do not apply to root, fall back to the immutable attribute
+    fs::remove_file(dir.join("probe")).unwrap();
+    std::process::Command::new("chattr")
+        .arg("+i")
+        .arg(dir.join("locked.txt"))
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+// Helper to undo `make_unremovable` so the temp dir can be cleaned up
+#[cfg(unix)]
+fn make_removable(dir: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    let _ = std::process::Command::new("chattr")
+        .arg("-i")
+        .arg(dir.join("locked.txt"))
+        .status();
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
 /// Test that --dry-run does not actually delete directories and prints what would be removed.
 #[test]
 fn dry_run_does_not_delete() {
@@ -87,6 +126,154 @@ fn ci_outputs_json() {
     assert!(s.contains("total_bytes"));
 }
 
+/// Test that --report-md writes a Markdown table of projects with a totals row.
+#[test]
+fn report_md_writes_markdown_table() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("projects");
+    create_dir_with_file(&root.join("app"), "target", "should_delete.txt");
+    create_dir_with_file(&root.join("web"), "node_modules", "should_delete.txt");
+    let report = temp.path().join("cleanup.md");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root)
+        .arg("--force")
+        .arg("--report-md")
+        .arg(&report);
+    cmd.assert().success();
+    let markdown = fs::read_to_string(&report).unwrap();
+    assert!(markdown.contains("| Project | Freed |"));
+    assert!(markdown.contains(&format!("| `{}` |", root.join("app").display())));
+    assert!(markdown.contains(&format!("| `{}` |", root.join("web").display())));
+    assert!(markdown.contains("| **Total** | **10 B** | **2** |"));
+}
+
+/// Test that the cruft kind removes .DS_Store files throughout the tree.
+#[test]
+fn cruft_kind_removes_ds_store_files() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("photos"), "2024", ".DS_Store");
+    create_dir_with_file(root, "web", "npm-debug.log");
+    create_dir_with_file(root, "web", "package.json");
+    File::create(root.join(".DS_Store")).unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--kind").arg("cruft");
+    cmd.assert().success();
+    assert!(!root.join("photos/2024/.DS_Store").exists());
+    assert!(!root.join(".DS_Store").exists());
+    assert!(!root.join("web/npm-debug.log").exists());
+    assert!(root.join("web/package.json").exists());
+    assert!(root.join("photos/2024").exists());
+}
+
+/// Test that the virtualenv named by VIRTUAL_ENV is not removed unless --force is given.
+#[test]
+fn active_virtualenv_is_skipped() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("api"), ".venv", "pyvenv.cfg");
+    create_dir_with_file(&root.join("old"), ".venv", "pyvenv.cfg");
+    let active = root.join("api/.venv");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dry-run")
+        .arg("--kind=python")
+        .env("VIRTUAL_ENV", &active);
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("old/.venv"))
+        .stdout(predicates::str::contains("api/.venv").not())
+        .stdout(predicates::str::contains("1 active virtualenv"))
+        .stderr(predicates::str::contains("active virtualenv"));
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--kind=python")
+        .env("VIRTUAL_ENV", &active);
+    cmd.assert().success();
+    assert!(!active.exists());
+}
+
+/// Test that --resume skips the targets listed in the checkpoint and records new removals.
+#[test]
+fn resume_skips_checkpointed_targets() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("projects");
+    create_dir_with_file(&root.join("done"), "target", "rebuilt.txt");
+    create_dir_with_file(&root.join("todo"), "target", "should_delete.txt");
+    let root = fs::canonicalize(&root).unwrap();
+    let checkpoint = temp.path().join("cleanup.checkpoint");
+    fs::write(
+        &checkpoint,
+        format!("{}\n", root.join("done/target").display()),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root)
+        .arg("--force")
+        .arg("--checkpoint")
+        .arg(&checkpoint)
+        .arg("--resume");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("1 done in an earlier run"));
+    assert!(root.join("done/target").exists());
+    assert!(!root.join("todo/target").exists());
+    let recorded = fs::read_to_string(&checkpoint).unwrap();
+    assert_eq!(
+        recorded.lines().collect::<Vec<_>>(),
+        [
+            root.join("done/target").display().to_string(),
+            root.join("todo/target").display().to_string()
+        ]
+    );
+}
+
+/// Test that --require-marker only removes matches tagged with a valid CACHEDIR.TAG.
+#[test]
+fn require_marker_spares_untagged_dirs() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("tool"), "cache", "blob");
+    fs::write(
+        root.join("tool/cache/CACHEDIR.TAG"),
+        "Signature: 8a477f597d28d172789f06886806bc55\n",
+    )
+    .unwrap();
+    create_dir_with_file(&root.join("notes"), "cache", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--dirs=cache")
+        .arg("--require-marker")
+        .arg("CACHEDIR.TAG");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Skipped: 1 no required marker."));
+    assert!(!root.join("tool/cache").exists());
+    assert!(root.join("notes/cache").exists());
+}
+
+/// Test that --list-to writes exactly the matched paths, one per line, and deletes nothing.
+#[test]
+fn list_to_writes_matched_paths() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("projects");
+    create_dir_with_file(&root.join("app"), "target", "should_keep.txt");
+    create_dir_with_file(&root.join("web"), "node_modules", "should_keep.txt");
+    create_dir_with_file(&root.join("web"), "src", "index.js");
+    let list = temp.path().join("matches.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root).arg("--relative").arg("--list-to").arg(&list);
+    cmd.assert().success();
+    assert_eq!(
+        fs::read_to_string(&list).unwrap(),
+        "app/target\nweb/node_modules\n"
+    );
+    assert!(root.join("app/target").exists());
+    assert!(root.join("web/node_modules").exists());
+}
+
 /// Test that a config file can specify custom directories to clean.
 #[test]
 fn config_file_dirs() {
@@ -107,3 +294,2483 @@ dirs = ["custom"]
     cmd.assert().success();
     assert!(!root.join("custom").exists());
 }
+
+/// Test that `--dirs @alias` cleans every pattern of the config alias.
+#[test]
+fn dirs_alias_expands_from_config() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "node_modules", "should_delete.txt");
+    create_dir_with_file(root, "dist", "should_delete.txt");
+    create_dir_with_file(root, "target", "should_keep.txt");
+    let config_path = root.join("cleaner.toml");
+    std::fs::write(
+        &config_path,
+        "[aliases]\n\"@webjunk\" = [\"node_modules\", \"dist\"]\n",
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--dirs=@webjunk")
+        .arg("--config")
+        .arg(&config_path);
+    cmd.assert().success();
+    assert!(!root.join("node_modules").exists());
+    assert!(!root.join("dist").exists());
+    assert!(root.join("target").exists());
+}
+
+/// Test that by default a removal failure does not stop other directories from being cleaned,
+/// and that all failures are reported at the end.
+#[cfg(unix)]
+#[test]
+fn best_effort_reports_failures() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("a"), "target", "should_delete.txt");
+    create_dir_with_file(&root.join("b"), "build", "should_keep.txt");
+    if !make_unremovable(&root.join("b/build")) {
+        return;
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force");
+    cmd.assert()
+        .failure()
+        .stdout(predicates::str::contains("Removed 1 directories"))
+        .stderr(predicates::str::contains("Failed to remove 1 entries"));
+    make_removable(&root.join("b/build"));
+    assert!(!root.join("a/target").exists());
+    assert!(root.join("b/build").exists());
+}
+
+/// Test that --fail-fast aborts with the removal error instead of printing a summary.
+#[cfg(unix)]
+#[test]
+fn fail_fast_aborts_on_first_error() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "target", "should_keep.txt");
+    if !make_unremovable(&root.join("target")) {
+        return;
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--fail-fast");
+    cmd.assert()
+        .failure()
+        .stdout(predicates::str::contains("Removed").not())
+        .stderr(predicates::str::contains("failed to remove"));
+    make_removable(&root.join("target"));
+    assert!(root.join("target").exists());
+}
+
+/// Test that --find-duplicates groups identical directories and deletes nothing.
+#[test]
+fn find_duplicates_groups_identical_dirs() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("p1"), "node_modules", "dep.js");
+    create_dir_with_file(&root.join("p2"), "node_modules", "dep.js");
+    create_dir_with_file(&root.join("p3"), "node_modules", "other.js");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--find-duplicates");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let s = String::from_utf8_lossy(&output);
+    assert!(s.contains("Duplicate group 1 (2 directories, 5 B each)"));
+    assert!(s.contains(&root.join("p1/node_modules").display().to_string()));
+    assert!(s.contains(&root.join("p2/node_modules").display().to_string()));
+    assert!(!s.contains(&root.join("p3/node_modules").display().to_string()));
+    assert!(s.contains("Found 1 duplicate groups"));
+    assert!(root.join("p1/node_modules").exists());
+    assert!(root.join("p2/node_modules").exists());
+}
+
+/// Test that --skip-in-use spares a directory with a file held open by a running process.
+#[cfg(target_os = "linux")]
+#[test]
+fn skip_in_use_spares_open_dirs() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("busy"), "target", "open.txt");
+    create_dir_with_file(&root.join("idle"), "target", "closed.txt");
+    // This test process keeps the file open while cleaner runs
+    let _held = File::open(root.join("busy/target/open.txt")).unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--skip-in-use");
+    cmd.assert().success();
+    assert!(root.join("busy/target").exists());
+    assert!(!root.join("idle/target").exists());
+}
+
+/// Test that an unreadable directory is warned about and skipped while the rest of the tree is
+/// still cleaned.
+#[cfg(unix)]
+#[test]
+fn unreadable_dir_does_not_stop_scan() {
+    use std::os::unix::fs::PermissionsExt;
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("secret"), "target", "hidden.txt");
+    create_dir_with_file(&root.join("app"), "target", "should_delete.txt");
+    let secret = root.join("secret");
+    fs::set_permissions(&secret, fs::Permissions::from_mode(0o000)).unwrap();
+    if fs::read_dir(&secret).is_ok() {
+        // Permission bits do not apply to root
+        fs::set_permissions(&secret, fs::Permissions::from_mode(0o755)).unwrap();
+        return;
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force");
+    let assert = cmd.assert();
+    fs::set_permissions(&secret, fs::Permissions::from_mode(0o755)).unwrap();
+    assert
+        .success()
+        .stderr(predicates::str::contains("Permission denied"));
+    assert!(!root.join("app/target").exists());
+    assert!(root.join("secret/target").exists());
+}
+
+/// Test that removals run concurrently: with a simulated latency per removal, N worker threads
+/// take about latency x count / N.
+#[test]
+fn simulated_delete_latency_shows_concurrency() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    let count = 8;
+    for i in 0..count {
+        create_dir_with_file(&root.join(format!("p{}", i)), "target", "should_delete.txt");
+    }
+    let latency = Duration::from_millis(300);
+    let threads = 4;
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--simulate-delete-latency")
+        .arg(latency.as_millis().to_string())
+        .env("RAYON_NUM_THREADS", threads.to_string());
+    let started = std::time::Instant::now();
+    cmd.assert().success();
+    let elapsed = started.elapsed();
+    let expected = latency * count / threads;
+    assert!(elapsed >= expected, "{:?} < {:?}", elapsed, expected);
+    // Well below the serial latency x count, with room for process startup
+    assert!(elapsed < expected * 2, "{:?} not concurrent", elapsed);
+    for i in 0..count {
+        assert!(!root.join(format!("p{}/target", i)).exists());
+    }
+}
+
+/// Test that --atomic archives and removes targets without leaving staging files behind, and is
+/// refused without --move-to or --archive-to.
+#[test]
+fn atomic_archive_leaves_no_staging_files() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("project");
+    create_dir_with_file(&root, "app/target", "should_delete.txt");
+    let archives = temp.path().join("archives");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root)
+        .arg("--archive-to")
+        .arg(&archives)
+        .arg("--atomic")
+        .arg("--force");
+    cmd.assert().success();
+    assert!(!root.join("app/target").exists());
+    assert!(archives.join("app/target.tar.gz").is_file());
+    let leftovers = |dir: &Path| {
+        fs::read_dir(dir)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with('.')
+            })
+            .count()
+    };
+    assert_eq!(leftovers(&root.join("app")), 0);
+    assert_eq!(leftovers(&archives.join("app")), 0);
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root).arg("--atomic").arg("--force");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--atomic only applies"));
+}
+
+/// Test that --print-config shows the effective settings with their source, a --dirs override
+/// beating the config's dirs, and cleans nothing.
+#[test]
+fn print_config_shows_cli_override() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "target", "should_keep.txt");
+    let config = root.join("cleaner.toml");
+    fs::write(
+        &config,
+        "[kinds.all]\ndirs = [\"target\"]\n[exclude]\npatterns = [\"vendor\"]\n",
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--config")
+        .arg(&config)
+        .arg("--dirs")
+        .arg("out,dist")
+        .arg("--print-config")
+        .arg("--ci");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let effective: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(
+        effective["dirs"]["value"],
+        serde_json::json!(["out", "dist"])
+    );
+    assert_eq!(effective["dirs"]["source"], "cli");
+    assert_eq!(effective["exclude"]["value"], serde_json::json!(["vendor"]));
+    assert_eq!(effective["exclude"]["source"], "config");
+    assert!(root.join("target").exists());
+
+    // Without the override the config's dirs apply, printed as TOML
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg("--config").arg(&config).arg("--print-config");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "[dirs]\nvalue = [\"target\"]\nsource = \"config\"",
+    ));
+}
+
+/// Test that a dry run breaks the matches down by pattern.
+#[test]
+fn dry_run_shows_per_pattern_totals() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "a/target", "should_delete.txt");
+    create_dir_with_file(root, "b/target", "should_delete.txt");
+    create_dir_with_file(root, "web/node_modules", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dirs")
+        .arg("target,node_modules")
+        .arg("--dry-run");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("By pattern: "))
+        .stdout(predicate::str::contains("target: 2 dirs, 10 B"))
+        .stdout(predicate::str::contains("node_modules: 1 dirs, 5 B"));
+    assert!(root.join("a/target").exists());
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dirs")
+        .arg("target,node_modules")
+        .arg("--dry-run")
+        .arg("--ci");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let last = String::from_utf8(output).unwrap();
+    let summary: serde_json::Value = serde_json::from_str(last.lines().last().unwrap()).unwrap();
+    assert_eq!(summary["patterns"]["target"]["directories"], 2);
+    assert_eq!(summary["patterns"]["node_modules"]["bytes"], 5);
+}
+
+/// Test that --archive-to writes a restorable archive before removing a target, and that a
+/// target whose archive cannot be written is left in place.
+#[test]
+fn archive_to_archives_before_removing() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("project");
+    create_dir_with_file(&root, "app/target", "should_delete.txt");
+    let archives = temp.path().join("archives");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root)
+        .arg("--archive-to")
+        .arg(&archives)
+        .arg("--force");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Archived 1 directories to"));
+    assert!(!root.join("app/target").exists());
+    let archive = archives.join("app/target.tar.gz");
+    assert!(archive.is_file());
+
+    // Extracting in the parent restores the directory
+    let file = fs::File::open(&archive).unwrap();
+    tar::Archive::new(flate2::read::GzDecoder::new(file))
+        .unpack(root.join("app"))
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(root.join("app/target/should_delete.txt")).unwrap(),
+        "test\n"
+    );
+
+    // An archive directory that is a file cannot hold archives: nothing is deleted
+    let blocked = temp.path().join("blocked");
+    fs::write(&blocked, "not a directory").unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root).arg("--archive-to").arg(&blocked).arg("--ci");
+    let output = cmd.assert().failure().get_output().stdout.clone();
+    let summary: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(summary["failed"], 1);
+    assert_eq!(summary["directories"], 0);
+    assert!(root.join("app/target/should_delete.txt").exists());
+}
+
+/// Test that with --keep-going-across-roots a failing root does not stop the others.
+#[test]
+fn keep_going_across_roots_isolates_failures() {
+    let temp = tempdir().unwrap();
+    let good = temp.path().join("good");
+    create_dir_with_file(&good, "target", "should_delete.txt");
+    let missing = temp.path().join("missing");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&missing)
+        .arg(&good)
+        .arg("--keep-going-across-roots")
+        .arg("--force");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 1 directories"))
+        .stderr(predicate::str::contains("Failed 1 of 2 roots"))
+        .stderr(predicate::str::contains(format!(
+            "{}: path not found",
+            missing.display()
+        )));
+    assert!(!good.join("target").exists());
+
+    create_dir_with_file(&good, "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&missing)
+        .arg(&good)
+        .arg("--keep-going-across-roots")
+        .arg("--ci");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let summary: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(summary["directories"], 1);
+    assert_eq!(
+        summary["root_failures"][0]["root"],
+        missing.to_str().unwrap()
+    );
+
+    // Every root failing still fails the run
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&missing)
+        .arg("--keep-going-across-roots")
+        .arg("--force");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("every root failed"));
+}
+
+/// Test that --infer-from-ignores cleans the build directories listed in a .gitignore.
+#[test]
+fn infer_from_ignores_cleans_listed_dirs() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    fs::write(root.join(".gitignore"), "out/\ngenerated/\n*.log\n").unwrap();
+    create_dir_with_file(root, "out", "app.bin");
+    create_dir_with_file(root, "generated", "schema.rs");
+    create_dir_with_file(root, "src", "main.rs");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dirs")
+        .arg("target")
+        .arg("--infer-from-ignores")
+        .arg("--force");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("inferred from ignore files"))
+        .stdout(predicate::str::contains("Removed 2 directories"));
+    assert!(!root.join("out").exists());
+    assert!(!root.join("generated").exists());
+    assert!(root.join("src").exists());
+}
+
+/// Test that --audit reports both the claimed and the measured space freed.
+#[test]
+fn audit_reports_claimed_and_measured_space() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "target", "should_delete.txt");
+    fs::write(root.join("target/big.bin"), vec![1u8; 64 * 1024]).unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--audit");
+    cmd.assert().success().stdout(
+        predicate::str::is_match(
+            r"Audit: claimed 64\.00 KiB freed, measured -?[\d.]+ \w+ \(.* -> .* free\)\.",
+        )
+        .unwrap(),
+    );
+
+    create_dir_with_file(root, "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--ci").arg("--audit");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let summary: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(summary["audit"]["claimed_bytes"], 5);
+    assert!(summary["audit"]["measured_bytes"].is_i64());
+    assert!(summary["audit"]["free_after"].is_u64());
+}
+
+/// Test that --prune-contents-older-than removes only stale files inside a match.
+#[test]
+fn prune_contents_keeps_directory_and_recent_files() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "target", "fresh.rlib");
+    create_dir_with_file(&root.join("target"), "debug/deps", "old.rlib");
+    fs::write(root.join("target/old.o"), "old").unwrap();
+    let month = Duration::from_secs(30 * 24 * 60 * 60);
+    set_age(&root.join("target/old.o"), month);
+    set_age(&root.join("target/debug/deps/old.rlib"), month);
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dirs")
+        .arg("target")
+        .arg("--prune-contents-older-than")
+        .arg("14d")
+        .arg("--force");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Removed 0 directories and 2 files",
+        ))
+        .stdout(predicate::str::contains("1 kept recent"));
+    assert!(root.join("target/fresh.rlib").exists());
+    assert!(root.join("target/debug/deps").is_dir());
+    assert!(!root.join("target/old.o").exists());
+    assert!(!root.join("target/debug/deps/old.rlib").exists());
+}
+
+/// Test that --auto-concurrency picks a thread count, and that RAYON_NUM_THREADS overrides it.
+#[test]
+fn auto_concurrency_yields_to_rayon_num_threads() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--auto-concurrency")
+        .arg("--dry-run")
+        .env_remove("RAYON_NUM_THREADS");
+    cmd.assert().success().stderr(
+        predicate::str::is_match(r"--auto-concurrency: \w+ storage at .*, using [1-9]\d* threads")
+            .unwrap(),
+    );
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--auto-concurrency")
+        .arg("--force")
+        .env("RAYON_NUM_THREADS", "1");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "RAYON_NUM_THREADS overrides --auto-concurrency",
+        ))
+        .stderr(predicate::str::contains("using").not());
+    assert!(!root.join("target").exists());
+}
+
+/// Test that --fail-if-new fails once new matches since the baseline exceed the threshold.
+#[test]
+fn baseline_drift_fails_on_new_matches() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("app"), "target", "should_delete.txt");
+    let baseline = tempdir().unwrap();
+    let baseline = baseline.path().join("baseline.json");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--write-baseline").arg(&baseline);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Wrote 1 matches"));
+
+    // Unchanged tree: only recurring matches
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--baseline")
+        .arg(&baseline)
+        .arg("--fail-if-new")
+        .arg("100");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("0 new (0 B), 1 recurring"));
+
+    create_dir_with_file(&root.join("web"), "node_modules", "index.js");
+    fs::write(root.join("web/node_modules/bundle.js"), vec![0u8; 500]).unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--baseline")
+        .arg(&baseline)
+        .arg("--fail-if-new")
+        .arg("100");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("+ web/node_modules (505 B)"))
+        .stderr(predicate::str::contains("exceeds --fail-if-new"));
+    assert!(root.join("web/node_modules").exists());
+    assert!(root.join("app/target").exists());
+}
+
+/// Test that matching is case-sensitive by default on Linux and ignores case with --ignore-case.
+#[cfg(target_os = "linux")]
+#[test]
+fn case_sensitivity_defaults_to_platform() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "Target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--dirs").arg("target").arg("--force");
+    cmd.assert().success();
+    assert!(root.join("Target").exists());
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dirs")
+        .arg("target")
+        .arg("--ignore-case")
+        .arg("--force");
+    cmd.assert().success();
+    assert!(!root.join("Target").exists());
+}
+
+/// Test that --only-between only deletes inside the window and reports outside it.
+#[test]
+fn only_between_defers_outside_window() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--only-between")
+        .arg("02:00-05:00")
+        .arg("--clock")
+        .arg("14:30");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "outside the --only-between window",
+        ))
+        .stdout(predicate::str::contains(
+            "Dry run: 1 directories would be removed",
+        ));
+    assert!(root.join("target").exists());
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--only-between")
+        .arg("02:00-05:00")
+        .arg("--clock")
+        .arg("03:10");
+    cmd.assert().success();
+    assert!(!root.join("target").exists());
+}
+
+/// Test that --roots-stdin cleans every root piped in.
+#[test]
+fn roots_stdin_cleans_each_listed_root() {
+    let first = tempdir().unwrap();
+    let second = tempdir().unwrap();
+    create_dir_with_file(first.path(), "target", "should_delete.txt");
+    create_dir_with_file(second.path(), "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg("--roots-stdin").arg("--force").write_stdin(format!(
+        "{}\n{}\n{}\n",
+        first.path().display(),
+        second.path().display(),
+        first.path().display()
+    ));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 2 directories"));
+    assert!(!first.path().join("target").exists());
+    assert!(!second.path().join("target").exists());
+}
+
+/// Test that --stale-deps-only selects dependencies older than their lockfile and spares the rest.
+#[test]
+fn stale_deps_only_compares_lockfile_age() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    for project in ["stale", "fresh"] {
+        create_dir_with_file(&root.join(project), "node_modules", "index.js");
+        fs::write(root.join(project).join("package-lock.json"), "{}").unwrap();
+    }
+    create_dir_with_file(&root.join("stale"), "target", "lib.rlib");
+    let day = Duration::from_secs(24 * 60 * 60);
+    // The stale lockfile was touched after its install, the fresh one before
+    set_age(&root.join("stale").join("node_modules"), day * 2);
+    set_age(&root.join("stale").join("package-lock.json"), day);
+    set_age(&root.join("fresh").join("node_modules"), day);
+    set_age(&root.join("fresh").join("package-lock.json"), day * 2);
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--stale-deps-only").arg("--force");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2 not stale"));
+    assert!(!root.join("stale").join("node_modules").exists());
+    assert!(root.join("fresh").join("node_modules").exists());
+    assert!(root.join("stale").join("target").exists());
+}
+
+/// Test that --no-op-delete reports what a dry run reports but leaves everything in place.
+#[test]
+fn no_op_delete_reports_like_dry_run() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    for project in ["a", "b", "c"] {
+        create_dir_with_file(&root.join(project), "target", "should_delete.txt");
+    }
+    let summary = |mode: &str| {
+        let mut cmd = Command::cargo_bin("cleaner").unwrap();
+        cmd.arg(root).arg("--ci").arg(mode);
+        let output = cmd.assert().success().get_output().stdout.clone();
+        let stdout = String::from_utf8(output).unwrap();
+        serde_json::from_str::<serde_json::Value>(stdout.lines().last().unwrap()).unwrap()
+    };
+    let dry_run = summary("--dry-run");
+    let no_op = summary("--no-op-delete");
+    assert_eq!(no_op["no_op_delete"], true);
+    assert_eq!(no_op["directories"], dry_run["directories"]);
+    assert_eq!(no_op["total_bytes"], dry_run["total_bytes"]);
+    assert_eq!(no_op["directories"], 3);
+    for project in ["a", "b", "c"] {
+        assert!(root.join(project).join("target").exists());
+    }
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--no-op-delete");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("nothing was deleted"));
+    assert!(root.join("a").join("target").exists());
+}
+
+/// Test that --group-by-language totals Rust and Node artifacts separately.
+#[test]
+fn group_by_language_totals_each_language() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    for crate_dir in ["core", "cli"] {
+        let project = root.join(crate_dir);
+        create_dir_with_file(&project, "target", "lib.rlib");
+        fs::write(project.join("Cargo.toml"), "[package]\n").unwrap();
+    }
+    let web = root.join("web");
+    create_dir_with_file(&web, "node_modules", "index.js");
+    fs::write(web.join("node_modules").join("big.js"), vec![0u8; 1000]).unwrap();
+    fs::write(web.join("package.json"), "{}").unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dry-run")
+        .arg("--ci")
+        .arg("--group-by-language");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    let summary: serde_json::Value = serde_json::from_str(stdout.lines().last().unwrap()).unwrap();
+    let languages = summary["languages"].as_object().unwrap();
+    assert_eq!(languages.len(), 2, "{}", summary);
+    assert_eq!(languages["rust"]["entries"], 2);
+    assert_eq!(languages["rust"]["bytes"], 10);
+    assert_eq!(languages["node"]["entries"], 1);
+    assert_eq!(languages["node"]["bytes"], 1005);
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--dry-run").arg("--group-by-language");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "By language: node: 1005 B (1 entries), rust: 10 B (2 entries).",
+    ));
+}
+
+/// Test that a misspelled `[kinds]` key is warned about, and rejected with --strict.
+#[test]
+fn misspelled_config_kind_is_reported() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    let config = root.join("cleaner.toml");
+    fs::write(&config, "[kinds.rsut]\ndirs = [\"target\"]\n").unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--config").arg(&config).arg("--dry-run");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("unknown kind 'rsut'"));
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--config")
+        .arg(&config)
+        .arg("--dry-run")
+        .arg("--strict");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown kind 'rsut'"));
+}
+
+/// Test that answering `a` at an interactive prompt removes the remaining matches unasked.
+#[test]
+fn interactive_all_answer_stops_prompting() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    for project in ["a", "b", "c"] {
+        create_dir_with_file(&root.join(project), "target", "should_delete.txt");
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--interactive")
+        .arg("--dirs")
+        .arg("target")
+        .write_stdin("y\nn\na\n");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert_eq!(stdout.matches("[y/N/a]").count(), 2, "{}", stdout);
+    assert!(stdout.contains(" B)? [y/N/a]"), "{}", stdout);
+    let remaining: Vec<_> = ["a", "b", "c"]
+        .iter()
+        .filter(|project| root.join(project).join("target").exists())
+        .collect();
+    assert_eq!(remaining.len(), 1);
+}
+
+/// Test that one braced --dirs pattern cleans every alternative.
+#[test]
+fn braced_dirs_pattern_cleans_each_alternative() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    for name in ["target-cache", "build-cache", "out-cache", "docs-cache"] {
+        create_dir_with_file(root, name, "file.txt");
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dirs")
+        .arg("{target,build}-cache,out-cache")
+        .arg("--exclude")
+        .arg("{docs,.git}")
+        .arg("--force");
+    cmd.assert().success();
+    assert!(!root.join("target-cache").exists());
+    assert!(!root.join("build-cache").exists());
+    assert!(!root.join("out-cache").exists());
+    assert!(root.join("docs-cache").exists());
+}
+
+/// Test that --confirm-phrase only deletes when the exact phrase is typed.
+#[test]
+fn confirm_phrase_requires_exact_match() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "target", "should_delete.txt");
+    for wrong in ["y\n", "delete\n"] {
+        let mut cmd = Command::cargo_bin("cleaner").unwrap();
+        cmd.arg(root)
+            .arg("--confirm-phrase")
+            .arg("DELETE")
+            .write_stdin(wrong);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("Type 'DELETE' to proceed"))
+            .stdout(predicate::str::contains("Aborted by user."));
+        assert!(root.join("target").exists());
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--confirm-phrase")
+        .arg("DELETE")
+        .write_stdin("DELETE\n");
+    cmd.assert().success();
+    assert!(!root.join("target").exists());
+}
+
+/// Test that the JSON summary reports throughput as bytes and entries over the removal time.
+#[test]
+fn summary_reports_removal_throughput() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    for i in 0..4 {
+        create_dir_with_file(&root.join(format!("p{}", i)), "target", "should_delete.txt");
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--ci")
+        .arg("--simulate-delete-latency")
+        .arg("200")
+        .env("RAYON_NUM_THREADS", "1");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let summary: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let seconds = summary["removal_seconds"].as_f64().unwrap();
+    assert!(seconds >= 0.8, "{}", seconds);
+    let bytes = summary["total_bytes"].as_f64().unwrap();
+    let bytes_per_second = summary["bytes_per_second"].as_f64().unwrap();
+    assert!((bytes_per_second - bytes / seconds).abs() < 0.01 * bytes_per_second);
+    let per_second = summary["directories_per_second"].as_f64().unwrap();
+    assert!((per_second - 4.0 / seconds).abs() < 0.01 * per_second);
+    assert!(per_second <= 5.0);
+}
+
+/// Test that --verify-readable skips a match containing an unreadable file.
+#[cfg(unix)]
+#[test]
+fn verify_readable_skips_damaged_dirs() {
+    use std::os::unix::fs::PermissionsExt;
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("damaged"), "target", "unreadable.bin");
+    create_dir_with_file(&root.join("healthy"), "target", "should_delete.txt");
+    let unreadable = root.join("damaged/target/unreadable.bin");
+    fs::set_permissions(&unreadable, fs::Permissions::from_mode(0o000)).unwrap();
+    if File::open(&unreadable).is_ok() {
+        // Permission bits do not apply to root
+        return;
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--verify-readable");
+    cmd.assert()
+        .success()
+        .stderr(predicates::str::contains("could not be read"))
+        .stdout(predicates::str::contains("1 unreadable"));
+    assert!(unreadable.exists());
+    assert!(!root.join("healthy/target").exists());
+}
+
+/// Test that --respect-cargo-metadata targets a custom `[build] target-dir` instead of `target`.
+#[test]
+fn cargo_metadata_finds_custom_target_dir() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    let project = root.join("app");
+    create_dir_with_file(&project, "src", "main.rs");
+    fs::write(
+        project.join("Cargo.toml"),
+        "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    fs::create_dir_all(project.join(".cargo")).unwrap();
+    fs::write(
+        project.join(".cargo/config.toml"),
+        "[build]\ntarget-dir = \"custom-out\"\n",
+    )
+    .unwrap();
+    create_dir_with_file(&project, "custom-out", "artifact.rlib");
+    create_dir_with_file(&project, "target", "not_a_build_dir.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.env_remove("CARGO_TARGET_DIR")
+        .arg(root)
+        .arg("--kind=rust")
+        .arg("--dry-run")
+        .arg("--respect-cargo-metadata");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let s = String::from_utf8_lossy(&output);
+    assert!(s.contains(&format!(
+        "Would remove: {}",
+        project.join("custom-out").display()
+    )));
+    assert!(!s.contains(&format!(
+        "Would remove: {}",
+        project.join("target").display()
+    )));
+}
+
+/// Test that `[general] default_kind` in the config is used when --kind is not given.
+#[test]
+fn config_default_kind() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "node_modules", "should_delete.txt");
+    create_dir_with_file(root, "target", "should_keep.txt");
+    let config_path = root.join("cleaner.toml");
+    let config = r#"
+[general]
+default_kind = "node"
+"#;
+    std::fs::write(&config_path, config).unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--config")
+        .arg(&config_path);
+    cmd.assert().success();
+    assert!(!root.join("node_modules").exists());
+    assert!(root.join("target").exists());
+}
+
+/// Test that --relative prints paths relative to the scan root, and full paths otherwise.
+#[test]
+fn relative_paths_in_output() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("app"), "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--dry-run").arg("--relative");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let s = String::from_utf8_lossy(&output);
+    assert!(s.contains("Would remove: app/target\n"));
+    assert!(!s.contains(&root.display().to_string()));
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--dry-run");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let s = String::from_utf8_lossy(&output);
+    assert!(s.contains(&format!(
+        "Would remove: {}",
+        root.join("app/target").display()
+    )));
+}
+
+/// Test that --max-open-fds lets a parallel clean complete under a low open file limit.
+#[cfg(unix)]
+#[test]
+fn max_open_fds_under_low_ulimit() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    for i in 0..40 {
+        let nested = root.join(format!("p{}/target/a/b/c/d/e/f/g/h", i));
+        create_dir_with_file(&nested, "deep", "artifact.o");
+    }
+    let bin = assert_cmd::cargo::cargo_bin("cleaner");
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg("ulimit -n 48 && exec \"$0\" \"$1\" --force --max-open-fds 48")
+        .arg(bin)
+        .arg(root);
+    cmd.assert()
+        .success()
+        .stderr(predicates::str::contains("Too many open files").not());
+    for i in 0..40 {
+        assert!(!root.join(format!("p{}/target", i)).exists());
+    }
+}
+
+/// Test that --list-kinds prints the kinds and their directories without needing a path.
+#[test]
+fn list_kinds_prints_kinds_and_dirs() {
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg("--list-kinds");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let s = String::from_utf8_lossy(&output);
+    let rust_line = s.lines().find(|l| l.starts_with("rust ")).unwrap();
+    assert!(rust_line.contains("target"));
+    assert!(s.lines().any(|l| l.starts_with("python ")));
+}
+
+/// Test that --only-empty removes nested empty directories and keeps those containing files.
+#[test]
+fn only_empty_removes_empty_dirs() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    fs::create_dir_all(root.join("a/b/c")).unwrap();
+    fs::create_dir_all(root.join("a/d")).unwrap();
+    fs::create_dir_all(root.join("keep/empty")).unwrap();
+    create_dir_with_file(root, "keep", "file.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--only-empty");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("Removed 5 directories"));
+    assert!(!root.join("a").exists());
+    assert!(!root.join("keep/empty").exists());
+    assert!(root.join("keep/file.txt").exists());
+    assert!(root.exists());
+}
+
+/// Test that --max-runtime stops before deleting once the deadline has passed and reports it.
+#[test]
+fn max_runtime_stops_early() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("a"), "target", "should_keep.txt");
+    create_dir_with_file(&root.join("b"), "target", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--max-runtime=0s");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("Removed 0 directories"))
+        .stdout(predicates::str::contains(
+            "Time budget exceeded: stopped before 2 remaining directories.",
+        ));
+    assert!(root.join("a/target").exists());
+    assert!(root.join("b/target").exists());
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--max-runtime=soon");
+    cmd.assert()
+        .failure()
+        .stderr(predicates::str::contains("invalid duration 'soon'"));
+}
+
+/// Test that the monorepo kind cleans nested `.nx/cache` but not unrelated `cache` dirs.
+#[test]
+fn monorepo_kind_matches_nested_cache() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join(".nx"), "cache", "should_delete.txt");
+    create_dir_with_file(&root.join("src"), "cache", "should_keep.txt");
+    create_dir_with_file(root, ".turbo", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--kind=monorepo");
+    cmd.assert().success();
+    assert!(!root.join(".nx/cache").exists());
+    assert!(root.join(".nx").exists());
+    assert!(!root.join(".turbo").exists());
+    assert!(root.join("src/cache").exists());
+}
+
+/// Test that --exclude-newer-than protects a fresh build dir while removing a stale one.
+#[test]
+fn exclude_newer_than_protects_recent() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("fresh"), "target", "should_keep.txt");
+    create_dir_with_file(&root.join("stale"), "target", "should_delete.txt");
+    set_age(&root.join("stale/target"), Duration::from_secs(30 * 86400));
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--exclude-newer-than=7days");
+    cmd.assert().success();
+    assert!(root.join("fresh/target").exists());
+    assert!(!root.join("stale/target").exists());
+}
+
+/// Test that --explain reports the reason a directory was excluded or matched.
+#[test]
+fn explain_reports_reasons() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "target", "should_delete.txt");
+    create_dir_with_file(root, "build", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dry-run")
+        .arg("--relative")
+        .arg("--explain")
+        .arg("--dirs=target,build")
+        .arg("--exclude=buil*");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "explain: build: matched pattern 'build' but excluded by 'buil*'",
+        ))
+        .stdout(predicates::str::contains(
+            "explain: target: matched pattern 'target'",
+        ));
+}
+
+/// Test that `[general] case_insensitive` makes `target` match a `Target` directory.
+#[test]
+fn config_case_insensitive_matching() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "Target", "should_delete.txt");
+    let config_path = root.join("cleaner.toml");
+    std::fs::write(&config_path, "[general]\ncase_insensitive = true\n").unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--dirs=target");
+    cmd.assert().success();
+    assert!(root.join("Target").exists());
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--config")
+        .arg(&config_path);
+    cmd.assert().success();
+    assert!(!root.join("Target").exists());
+}
+
+/// Test that a matched directory containing a `.cleaner-keep` file is spared.
+#[test]
+fn keep_file_spares_dir() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("kept"), "target", ".cleaner-keep");
+    create_dir_with_file(&root.join("plain"), "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force");
+    cmd.assert().success();
+    assert!(root.join("kept/target").exists());
+    assert!(!root.join("plain/target").exists());
+
+    create_dir_with_file(&root.join("custom"), "target", "KEEP");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--keep-file=KEEP");
+    cmd.assert().success();
+    assert!(root.join("custom/target").exists());
+    assert!(!root.join("kept/target").exists());
+}
+
+/// Test that several roots can be cleaned concurrently with --parallel-roots and totals combine.
+#[test]
+fn parallel_roots_combine_totals() {
+    let first = tempdir().unwrap();
+    let second = tempdir().unwrap();
+    create_dir_with_file(first.path(), "target", "a.txt");
+    create_dir_with_file(&first.path().join("web"), "node_modules", "b.txt");
+    create_dir_with_file(second.path(), "build", "c.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(first.path())
+        .arg(second.path())
+        .arg("--ci")
+        .arg("--parallel-roots");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let summary: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(summary["directories"], 3);
+    assert!(!first.path().join("target").exists());
+    assert!(!first.path().join("web/node_modules").exists());
+    assert!(!second.path().join("build").exists());
+}
+
+/// Test that --workspace-members cleans the selected dirs inside each Cargo workspace member only.
+#[test]
+fn workspace_members_cleans_each_member() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/*\"]\n",
+    )
+    .unwrap();
+    create_dir_with_file(&root.join("crates/core"), "doc", "index.html");
+    create_dir_with_file(&root.join("crates/cli"), "criterion", "report.json");
+    create_dir_with_file(&root.join("notes"), "doc", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--workspace-members")
+        .arg("--dirs=doc,criterion");
+    cmd.assert().success();
+    assert!(!root.join("crates/core/doc").exists());
+    assert!(!root.join("crates/cli/criterion").exists());
+    assert!(root.join("notes/doc").exists());
+}
+
+/// Test that --review only removes the directories still listed after the file was edited.
+#[test]
+fn review_file_keeps_unlisted_dirs() {
+    use std::process::{Command as StdCommand, Stdio};
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("projects");
+    create_dir_with_file(&root.join("a"), "target", "should_delete.txt");
+    create_dir_with_file(&root.join("b"), "target", "should_keep.txt");
+    let review = temp.path().join("review.txt");
+    let mut child = StdCommand::new(assert_cmd::cargo::cargo_bin("cleaner"))
+        .arg(&root)
+        .arg("--dirs=target")
+        .arg("--review")
+        .arg(&review)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Wait for the preview to be written
+    let listed = |contents: &str| contents.lines().filter(|l| !l.starts_with('#')).count();
+    let mut contents = String::new();
+    for _ in 0..100 {
+        contents = fs::read_to_string(&review).unwrap_or_default();
+        if listed(&contents) == 2 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let keep = root.join("b/target").display().to_string();
+    let edited: Vec<&str> = contents
+        .lines()
+        .filter(|line| !line.starts_with(&keep))
+        .collect();
+    let edited = edited.join("\n");
+    assert_eq!(listed(&edited), 1);
+    fs::write(&review, edited).unwrap();
+    writeln!(child.stdin.take().unwrap()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert!(!root.join("a/target").exists());
+    assert!(root.join("b/target").exists());
+}
+
+/// Test that --one-file-system still cleans everything on the root's own filesystem.
+#[test]
+fn one_file_system_cleans_same_filesystem() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "app/target", "should_delete.txt");
+    create_dir_with_file(root, "lib/nested/target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dirs=target")
+        .arg("--one-file-system")
+        .arg("--force");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 2 directories"));
+    assert!(!root.join("app/target").exists());
+    assert!(!root.join("lib/nested/target").exists());
+}
+
+/// Test that --generate-completions prints a bash script covering the CLI flags.
+#[test]
+fn generate_bash_completions() {
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg("--generate-completions").arg("bash");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("--dry-run"))
+        .stdout(predicate::str::contains(
+            "complete -F _cleaner -o bashdefault -o default cleaner",
+        ));
+}
+
+/// Test that the completion scripts offer short flags too and keep whole help sentences.
+#[test]
+fn completions_cover_short_flags_and_full_help() {
+    let script = |shell| {
+        let mut cmd = Command::cargo_bin("cleaner").unwrap();
+        cmd.arg("--generate-completions").arg(shell);
+        let output = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(output).unwrap()
+    };
+    assert!(script("bash").contains(" -n "));
+    assert!(script("zsh").contains("'-n[Show what would be deleted"));
+    assert!(script("powershell").contains("('-n',"));
+    let fish = script("fish");
+    assert!(fish.contains("-s n -l dry-run"));
+    assert!(fish.contains("-l roots-stdin -d 'Read further root directories from stdin, one per line, e.g. from `find`. Blank lines"));
+}
+
+/// Test that --keep-recent only removes the older subdirectories of a matched directory.
+#[test]
+fn keep_recent_keeps_newest_subdirs() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    let cache = root.join("build/cache");
+    for (i, name) in ["a1", "b2", "c3", "d4", "e5"].iter().enumerate() {
+        create_dir_with_file(&cache, name, "entry.bin");
+        set_age(
+            &cache.join(name),
+            Duration::from_secs(3600 * (5 - i as u64)),
+        );
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--dirs=build/cache")
+        .arg("--keep-recent=2");
+    cmd.assert().success();
+    for old in ["a1", "b2", "c3"] {
+        assert!(!cache.join(old).exists(), "{} should be removed", old);
+    }
+    assert!(cache.join("d4").exists());
+    assert!(cache.join("e5").exists());
+}
+
+/// Test that a root path whose own name matches a pattern is kept, while matches inside it are removed.
+#[test]
+fn matching_root_is_not_removed() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("target");
+    create_dir_with_file(&root, "debug", "artifact.o");
+    create_dir_with_file(&root.join("nested"), "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root).arg("--force").arg("--kind=rust");
+    cmd.assert().success().stderr(predicate::str::contains(
+        "is the root path and is never removed",
+    ));
+    assert!(root.join("debug/artifact.o").exists());
+    assert!(!root.join("nested/target").exists());
+}
+
+/// Test that --literal matches a name containing glob metacharacters exactly.
+#[test]
+fn literal_matches_exact_name() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "build[1]", "literal.txt");
+    create_dir_with_file(root, "build1", "glob.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--dirs=build[1]");
+    cmd.assert().success();
+    assert!(root.join("build[1]").exists());
+    assert!(!root.join("build1").exists());
+
+    create_dir_with_file(root, "build1", "glob.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--literal")
+        .arg("--dirs=build[1]");
+    cmd.assert().success();
+    assert!(!root.join("build[1]").exists());
+    assert!(root.join("build1").exists());
+}
+
+/// Test that the end-of-run summary tallies skipped directories by reason.
+#[test]
+fn summary_tallies_skip_reasons() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("a"), "target", "should_delete.txt");
+    create_dir_with_file(&root.join("b"), "target", "excluded.txt");
+    create_dir_with_file(&root.join("c"), "target", "excluded.txt");
+    create_dir_with_file(&root.join("d"), "target", ".cleaner-keep");
+    create_dir_with_file(&root.join("e"), "target", "recent.txt");
+    for old in ["a", "b", "c", "d"] {
+        set_age(
+            &root.join(old).join("target"),
+            Duration::from_secs(48 * 3600),
+        );
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--exclude=b/target,c/target")
+        .arg("--exclude-newer-than=1h");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Skipped: 2 excluded, 1 keep file, 1 protected.",
+    ));
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--ci")
+        .arg("--dirs=target")
+        .arg("--exclude=b/target,c/target")
+        .arg("--exclude-newer-than=1h");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"excluded\":2"))
+        .stdout(predicate::str::contains("\"protected\":1"));
+}
+
+/// Test that --move-to relocates targets under the staging dir, keeping their relative paths.
+#[test]
+fn move_to_preserves_structure() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("projects");
+    let staging = temp.path().join("staging");
+    create_dir_with_file(&root.join("app"), "target", "binary");
+    create_dir_with_file(&root.join("libs/core"), "target", "lib.rlib");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root)
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--move-to")
+        .arg(&staging);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Moved 2 directories"));
+    assert!(!root.join("app/target").exists());
+    assert!(!root.join("libs/core/target").exists());
+    assert!(staging.join("app/target/binary").is_file());
+    assert!(staging.join("libs/core/target/lib.rlib").is_file());
+}
+
+/// Test that targets inside a package-manager cache below $HOME are skipped unless allowed.
+#[test]
+fn package_caches_are_protected() {
+    let temp = tempdir().unwrap();
+    let home = temp.path();
+    create_dir_with_file(
+        &home.join(".cargo/git/checkouts/dep"),
+        "target",
+        "cached.rlib",
+    );
+    create_dir_with_file(&home.join("work/app"), "target", "should_delete.txt");
+    create_dir_with_file(&home.join("conan/pkg"), "target", "cached.a");
+    let config = home.join("cleaner.toml");
+    fs::write(&config, "[general]\nalways_exclude = [\"~/conan\"]\n").unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.env("HOME", home)
+        .arg(home)
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--config")
+        .arg(&config);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2 package cache"));
+    assert!(!home.join("work/app/target").exists());
+    assert!(home.join(".cargo/git/checkouts/dep/target").exists());
+    assert!(home.join("conan/pkg/target").exists());
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.env("HOME", home)
+        .arg(home)
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--allow-package-caches");
+    cmd.assert().success();
+    assert!(!home.join(".cargo/git/checkouts/dep/target").exists());
+}
+
+/// Test that --prune-empty-parents removes parents emptied by the cleanup, but nothing else.
+#[test]
+fn prune_empty_parents_after_removal() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("packages/foo"), "node_modules", "dep.js");
+    create_dir_with_file(&root.join("packages/bar"), "node_modules", "dep.js");
+    create_dir_with_file(root, "packages/bar", "package.json");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--dirs=node_modules")
+        .arg("--prune-empty-parents");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Pruned 1 empty parent directories.",
+    ));
+    assert!(!root.join("packages/foo").exists());
+    assert!(root.join("packages/bar/package.json").exists());
+    assert!(!root.join("packages/bar/node_modules").exists());
+    assert!(root.join("packages").exists());
+}
+
+/// Test that --match-files removes matching files and directories in the same run.
+#[test]
+fn match_files_handles_files_and_dirs() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "src", "main.o");
+    create_dir_with_file(root, "src", "main.c");
+    create_dir_with_file(root, "build", "app");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--dirs=build,*.o");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 1 directories."));
+    assert!(root.join("src/main.o").exists());
+
+    create_dir_with_file(root, "build", "app");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--match-files")
+        .arg("--dirs=build,*.o");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Removed 1 directories and 1 files.",
+    ));
+    assert!(!root.join("build").exists());
+    assert!(!root.join("src/main.o").exists());
+    assert!(root.join("src/main.c").exists());
+}
+
+/// Test that a default run lists the matched paths with sizes and asks once before deleting.
+#[test]
+fn confirm_once_lists_real_paths_and_sizes() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    fs::create_dir_all(root.join("app/target")).unwrap();
+    fs::write(root.join("app/target/blob"), vec![0u8; 2048]).unwrap();
+    create_dir_with_file(&root.join("web"), "node_modules", "dep.js");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--relative").write_stdin("n\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("app/target (2.00 KiB)"))
+        .stdout(predicate::str::contains("web/node_modules (5 B)"))
+        .stdout(predicate::str::contains("2 entries, 2.00 KiB in total."))
+        .stdout(predicate::str::contains("Are you sure you want to proceed?").count(1))
+        .stdout(predicate::str::contains("Aborted by user."));
+    assert!(root.join("app/target").exists());
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).write_stdin("y\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 2 directories."));
+    assert!(!root.join("app/target").exists());
+}
+
+/// Test that --cache writes the size cache to the data dir and --clear-cache removes it.
+#[test]
+fn cache_is_written_and_cleared() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("projects");
+    let data = temp.path().join("data");
+    create_dir_with_file(&root, "target", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.env("XDG_DATA_HOME", &data)
+        .arg(&root)
+        .arg("--dry-run")
+        .arg("--cache");
+    cmd.assert().success();
+    let cache = data.join("cleaner/sizes.json");
+    assert!(fs::read_to_string(&cache).unwrap().contains("target"));
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.env("XDG_DATA_HOME", &data).arg("--clear-cache");
+    cmd.assert().success();
+    assert!(!cache.exists());
+}
+
+/// Test the three --on-conflict policies against a --move-to destination left by a prior run.
+#[test]
+fn on_conflict_policies() {
+    for policy in ["overwrite", "skip", "rename"] {
+        let temp = tempdir().unwrap();
+        let root = temp.path().join("projects");
+        let staging = temp.path().join("staging");
+        create_dir_with_file(&root.join("app"), "target", "new.bin");
+        create_dir_with_file(&staging.join("app"), "target", "old.bin");
+        let mut cmd = Command::cargo_bin("cleaner").unwrap();
+        cmd.arg(&root)
+            .arg("--force")
+            .arg("--dirs=target")
+            .arg("--move-to")
+            .arg(&staging)
+            .arg("--on-conflict")
+            .arg(policy);
+        cmd.assert().success();
+        let staged: Vec<String> = fs::read_dir(staging.join("app"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        match policy {
+            "overwrite" => {
+                assert!(!root.join("app/target").exists());
+                assert!(staging.join("app/target/new.bin").exists());
+                assert!(!staging.join("app/target/old.bin").exists());
+            }
+            "skip" => {
+                assert!(root.join("app/target/new.bin").exists());
+                assert!(staging.join("app/target/old.bin").exists());
+                assert_eq!(staged.len(), 1);
+            }
+            _ => {
+                assert!(!root.join("app/target").exists());
+                assert!(staging.join("app/target/old.bin").exists());
+                assert_eq!(staged.len(), 2);
+                let renamed = staged.iter().find(|n| n.starts_with("target.")).unwrap();
+                assert!(staging.join("app").join(renamed).join("new.bin").exists());
+            }
+        }
+    }
+}
+
+/// Test that a config dirs entry with max_depth only matches up to that depth.
+#[test]
+fn config_per_pattern_max_depth() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("projects");
+    create_dir_with_file(&root.join("app"), "node_modules", "dep.js");
+    create_dir_with_file(&root.join("app/packages/ui"), "node_modules", "dep.js");
+    create_dir_with_file(&root.join("app/packages/ui"), "dist", "bundle.js");
+    let config = temp.path().join("cleaner.toml");
+    fs::write(
+        &config,
+        "[kinds.node]\ndirs = [{ name = \"node_modules\", max_depth = 2 }, \"dist\"]\n",
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root)
+        .arg("--force")
+        .arg("--kind=node")
+        .arg("--config")
+        .arg(&config);
+    cmd.assert().success();
+    assert!(!root.join("app/node_modules").exists());
+    assert!(root.join("app/packages/ui/node_modules").exists());
+    assert!(!root.join("app/packages/ui/dist").exists());
+}
+
+/// Test that --metrics-file writes Prometheus metrics with the run's numbers.
+#[test]
+fn metrics_file_has_prometheus_metrics() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("projects");
+    create_dir_with_file(&root.join("a"), "target", "should_delete.txt");
+    create_dir_with_file(&root.join("b"), "target", "should_delete.txt");
+    let metrics = temp.path().join("cleaner.prom");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root)
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--metrics-file")
+        .arg(&metrics);
+    cmd.assert().success();
+    let text = fs::read_to_string(&metrics).unwrap();
+    assert!(text.contains("cleaner_directories_removed 2\n"));
+    assert!(text.contains("cleaner_bytes_freed 10\n"));
+    assert!(text.contains("cleaner_errors_total 0\n"));
+    let duration = text
+        .lines()
+        .find_map(|l| l.strip_prefix("cleaner_duration_seconds "))
+        .unwrap();
+    assert!(duration.parse::<f64>().unwrap() >= 0.0);
+}
+
+/// Test that --dirs-suffix matches names ending in the suffix and nothing else.
+#[test]
+fn dirs_suffix_matches_name_endings() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "foo-cache", "should_delete.txt");
+    create_dir_with_file(root, "bar-cache", "should_delete.txt");
+    create_dir_with_file(root, "cachebox", "should_keep.txt");
+    create_dir_with_file(root, "target", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--dirs-suffix")
+        .arg("-cache");
+    cmd.assert().success();
+    assert!(!root.join("foo-cache").exists());
+    assert!(!root.join("bar-cache").exists());
+    assert!(root.join("cachebox").exists());
+    assert!(root.join("target").exists());
+}
+
+/// Test that --dirs-prefix is combined with --dirs.
+#[test]
+fn dirs_prefix_is_combined_with_dirs() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "tmp-build", "should_delete.txt");
+    create_dir_with_file(root, "out", "should_delete.txt");
+    create_dir_with_file(root, "keep-tmp", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--dirs=out")
+        .arg("--dirs-prefix")
+        .arg("tmp");
+    cmd.assert().success();
+    assert!(!root.join("tmp-build").exists());
+    assert!(!root.join("out").exists());
+    assert!(root.join("keep-tmp").exists());
+}
+
+/// Test that the JSON confirmation protocol lists projects and removes only approved groups.
+#[test]
+fn json_confirmation_removes_approved_groups() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("app1"), "target", "should_delete.txt");
+    create_dir_with_file(&root.join("app2"), "target", "should_keep.txt");
+    let app1 = root.join("app1").display().to_string();
+    let decision = serde_json::json!({ "delete": [app1] }).to_string();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dirs=target")
+        .arg("--confirm-per-project-with-sizes")
+        .write_stdin(decision + "\n");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    let request: serde_json::Value = serde_json::from_str(stdout.lines().next().unwrap()).unwrap();
+    let groups = request["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0]["id"], app1.as_str());
+    assert_eq!(groups[0]["bytes"], 5);
+    assert_eq!(request["total_bytes"], 10);
+    assert!(!root.join("app1/target").exists());
+    assert!(root.join("app2/target").exists());
+}
+
+/// Test that --low-priority lowers the process priority before cleaning.
+#[cfg(target_os = "linux")]
+#[test]
+fn low_priority_is_applied() {
+    let temp = tempdir().unwrap();
+    create_dir_with_file(temp.path(), "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path())
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--low-priority")
+        .arg("--log=info");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("running with idle I/O priority"));
+    assert!(!temp.path().join("target").exists());
+}
+
+/// Test that --all-kinds cleans directories of every language, not just the `all` defaults.
+#[test]
+fn all_kinds_cleans_every_language() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("rust"), "target", "should_delete.txt");
+    create_dir_with_file(&root.join("web"), "node_modules", "should_delete.txt");
+    create_dir_with_file(&root.join("py"), "__pycache__", "should_delete.txt");
+    create_dir_with_file(&root.join("src"), "lib", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--all-kinds");
+    cmd.assert().success();
+    assert!(!root.join("rust/target").exists());
+    assert!(!root.join("web/node_modules").exists());
+    assert!(!root.join("py/__pycache__").exists());
+    assert!(root.join("src/lib").exists());
+}
+
+/// Test that --project-relative-depth limits matches per project, however deep the project is.
+#[test]
+fn project_relative_depth_applies_per_project() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    let shallow = root.join("app");
+    let deep = root.join("teams/platform/services/api");
+    for project in [&shallow, &deep] {
+        fs::create_dir_all(project).unwrap();
+        File::create(project.join("Cargo.toml")).unwrap();
+        create_dir_with_file(project, "target", "should_delete.txt");
+    }
+    create_dir_with_file(&shallow.join("crates/core/src"), "build", "should_keep.txt");
+    create_dir_with_file(&deep.join("tools"), "build", "should_delete.txt");
+    create_dir_with_file(&root.join("scratch"), "target", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--dirs=target,build")
+        .arg("--project-relative-depth")
+        .arg("2");
+    cmd.assert().success();
+    assert!(!shallow.join("target").exists());
+    assert!(!deep.join("target").exists());
+    assert!(!deep.join("tools/build").exists());
+    // Four levels below its project
+    assert!(shallow.join("crates/core/src/build").exists());
+    // Not inside any project
+    assert!(root.join("scratch/target").exists());
+}
+
+/// Test that --detected-only applies only the detected kind's patterns: `target` is not cleaned
+/// in a Node project.
+#[test]
+fn detected_only_narrows_to_project_kind() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    let web = root.join("web");
+    create_dir_with_file(root, "web", "package.json");
+    create_dir_with_file(&web, "node_modules", "should_delete.txt");
+    create_dir_with_file(&web, "target", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--kind")
+        .arg("all")
+        .arg("--detected-only");
+    cmd.assert().success();
+    assert!(!web.join("node_modules").exists());
+    assert!(web.join("target").exists());
+}
+
+/// Test that --project-boundaries attributes every match to its nearest project root.
+#[test]
+fn project_boundaries_attribute_to_nearest_root() {
+    let temp = tempdir().unwrap();
+    let repo = temp.path().join("repo");
+    fs::create_dir_all(repo.join(".git")).unwrap();
+    let api = repo.join("services/api");
+    let core = api.join("crates/core");
+    for project in [&api, &core] {
+        fs::create_dir_all(project).unwrap();
+        File::create(project.join("Cargo.toml")).unwrap();
+        create_dir_with_file(project, "target", "should_delete.txt");
+    }
+    let web = repo.join("web");
+    create_dir_with_file(&repo, "web", "package.json");
+    create_dir_with_file(
+        &web.join("packages/ui"),
+        "node_modules",
+        "should_delete.txt",
+    );
+    create_dir_with_file(&repo.join("docs"), "build", "should_delete.txt");
+    let report = temp.path().join("cleanup.md");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&repo)
+        .arg("--force")
+        .arg("--project-boundaries")
+        .arg("--report-md")
+        .arg(&report);
+    cmd.assert().success();
+    let markdown = fs::read_to_string(&report).unwrap();
+    let row = |project: &Path| format!("| `{}` | 5 B | 1 |", project.display());
+    assert!(markdown.contains(&row(&api)), "{}", markdown);
+    assert!(markdown.contains(&row(&core)), "{}", markdown);
+    assert!(markdown.contains(&row(&web)), "{}", markdown);
+    // Not inside a package, so it belongs to the repository
+    assert!(markdown.contains(&row(&repo)), "{}", markdown);
+    assert!(!markdown.contains("packages/ui`"), "{}", markdown);
+}
+
+/// Test that a --dirs pattern matching nothing is reported, while one that matched is not.
+#[test]
+fn unmatched_pattern_is_warned_about() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("app"), "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--force").arg("--dirs=target,targte");
+    cmd.assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "pattern 'targte' matched 0 directories",
+        ))
+        .stderr(predicates::str::contains("pattern 'target' matched").not());
+}
+
+/// Test that --except-kind keeps a kind's directories out of --all-kinds.
+#[test]
+fn except_kind_spares_that_kinds_dirs() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("rust"), "target", "should_delete.txt");
+    create_dir_with_file(&root.join("web"), "node_modules", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--all-kinds")
+        .arg("--except-kind")
+        .arg("node");
+    cmd.assert().success();
+    assert!(!root.join("rust/target").exists());
+    assert!(root.join("web/node_modules").exists());
+}
+
+/// Test that --exclude-if-contains spares matches holding one of the marker names.
+#[test]
+fn exclude_if_contains_spares_marked_dirs() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(&root.join("app"), "build", ".keep");
+    create_dir_with_file(&root.join("web"), "build", "package.json");
+    create_dir_with_file(&root.join("lib"), "build", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--force")
+        .arg("--dirs=build")
+        .arg("--exclude-if-contains")
+        .arg(".keep")
+        .arg("--exclude-if-contains")
+        .arg("package.json");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("Skipped: 2 exclude marker"));
+    assert!(root.join("app/build/.keep").exists());
+    assert!(root.join("web/build/package.json").exists());
+    assert!(!root.join("lib/build").exists());
+}
+
+/// Test that an `[exclude] from` file is found next to the config, whatever the working directory.
+#[test]
+fn config_exclude_from_is_relative_to_config() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("projects");
+    create_dir_with_file(&root, "target", "should_delete.txt");
+    create_dir_with_file(&root, "out", "should_keep.txt");
+    let conf = temp.path().join("conf");
+    fs::create_dir(&conf).unwrap();
+    fs::write(conf.join("ignore.txt"), "out\n").unwrap();
+    let config_path = conf.join("cleaner.toml");
+    fs::write(&config_path, "[exclude]\nfrom = \"ignore.txt\"\n").unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.current_dir(temp.path())
+        .arg(&root)
+        .arg("--force")
+        .arg("--dirs=target,out")
+        .arg("--config")
+        .arg(&config_path);
+    cmd.assert().success();
+    assert!(!root.join("target").exists());
+    assert!(root.join("out").exists());
+}
+
+/// Test that --quiet-below prints no summary for small results and still does for larger ones.
+#[test]
+fn quiet_below_suppresses_small_summaries() {
+    let temp = tempdir().unwrap();
+    create_dir_with_file(temp.path(), "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path())
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--quiet-below")
+        .arg("1KB");
+    cmd.assert().success().stdout(predicates::str::is_empty());
+    assert!(!temp.path().join("target").exists());
+
+    create_dir_with_file(temp.path(), "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path())
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--quiet-below")
+        .arg("4");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("Removed 1 directories"));
+}
+
+/// Test that a `.json` config cleans the same directories as the equivalent TOML config.
+#[test]
+fn json_config_cleans_like_toml() {
+    let configs = [
+        (
+            "cleaner.toml",
+            "[kinds.all]\ndirs = [\"custom\", \"gen\"]\n",
+        ),
+        (
+            "cleaner.json",
+            r#"{"kinds": {"all": {"dirs": ["custom", "gen"]}}}"#,
+        ),
+    ];
+    for (name, contents) in configs {
+        let temp = tempdir().unwrap();
+        let root = temp.path().join("project");
+        create_dir_with_file(&root, "custom", "should_delete.txt");
+        create_dir_with_file(&root, "gen", "should_delete.txt");
+        create_dir_with_file(&root, "target", "should_keep.txt");
+        let config_path = temp.path().join(name);
+        fs::write(&config_path, contents).unwrap();
+        let mut cmd = Command::cargo_bin("cleaner").unwrap();
+        cmd.arg(&root)
+            .arg("--force")
+            .arg("--config")
+            .arg(&config_path);
+        cmd.assert().success();
+        assert!(!root.join("custom").exists(), "{}", name);
+        assert!(!root.join("gen").exists(), "{}", name);
+        assert!(root.join("target").exists(), "{}", name);
+    }
+}
+
+/// Test that --fail-on-nothing turns an empty scan into a failure.
+#[test]
+fn fail_on_nothing_fails_empty_scan() {
+    let temp = tempdir().unwrap();
+    fs::create_dir(temp.path().join("src")).unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path()).arg("--force").arg("--dirs=target");
+    cmd.assert().success();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path())
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--fail-on-nothing");
+    cmd.assert()
+        .failure()
+        .stderr(predicates::str::contains("nothing matched"));
+
+    create_dir_with_file(temp.path(), "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path())
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--fail-on-nothing");
+    cmd.assert().success();
+}
+
+/// Test that a symlinked root is resolved and cleaned at its real location.
+#[cfg(unix)]
+#[test]
+fn symlinked_root_is_resolved() {
+    let temp = tempdir().unwrap();
+    let real = temp.path().join("projects/app");
+    create_dir_with_file(&real, "target", "should_delete.txt");
+    let link = temp.path().join("current");
+    std::os::unix::fs::symlink(&real, &link).unwrap();
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&link)
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--log=info");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("Removed 1 directories"))
+        .stderr(predicates::str::contains("is a symlink to"));
+    assert!(!real.join("target").exists());
+    assert!(fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+}
+
+/// Test that --webhook POSTs the run summary as JSON.
+#[cfg(feature = "webhook")]
+#[test]
+fn webhook_posts_summary() {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        while !String::from_utf8_lossy(&request).ends_with('}') {
+            let n = stream.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        String::from_utf8(request).unwrap()
+    });
+    let temp = tempdir().unwrap();
+    create_dir_with_file(&temp.path().join("a"), "target", "should_delete.txt");
+    create_dir_with_file(&temp.path().join("b"), "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path())
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--webhook")
+        .arg(&url);
+    cmd.assert().success();
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST /hook HTTP/1.1"));
+    let body = request.split("\r\n\r\n").nth(1).unwrap();
+    let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(payload["directories"], 2);
+    assert_eq!(payload["total_bytes"], 10);
+    assert_eq!(payload["errors"], 0);
+    assert_eq!(payload["dry_run"], false);
+    assert!(payload["duration_seconds"].as_f64().unwrap() >= 0.0);
+    assert!(payload["text"]
+        .as_str()
+        .unwrap()
+        .contains("Removed 2 directories"));
+}
+
+/// Test that the parallel deletion removes every nested match across several roots and nothing
+/// else.
+#[test]
+fn parallel_deletion_removes_all_matches() {
+    let temp = tempdir().unwrap();
+    let roots: Vec<_> = (0..3)
+        .map(|r| temp.path().join(format!("root{}", r)))
+        .collect();
+    for root in &roots {
+        for p in 0..20 {
+            let project = root.join(format!("project{}", p));
+            create_dir_with_file(&project.join("target/debug"), "deps", "should_delete.txt");
+            create_dir_with_file(&project, "src", "should_keep.txt");
+        }
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.args(&roots)
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--parallel-roots");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("Removed 60 directories"));
+    for root in &roots {
+        for p in 0..20 {
+            let project = root.join(format!("project{}", p));
+            assert!(!project.join("target").exists());
+            assert!(project.join("src/should_keep.txt").exists());
+        }
+    }
+}
+
+/// Test that --age-histogram sums the sizes of matches per age bucket without deleting them.
+#[test]
+fn age_histogram_buckets_sizes_by_age() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    let day = Duration::from_secs(86_400);
+    for (project, days) in [("a", 2), ("b", 10), ("c", 40), ("d", 90)] {
+        create_dir_with_file(&root.join(project), "target", "should_keep.txt");
+        set_age(&root.join(project).join("target"), day * days);
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dirs=target")
+        .arg("--age-histogram")
+        .arg("--ci");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let buckets: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let sums: Vec<(u64, u64)> = buckets
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| {
+            (
+                b["directories"].as_u64().unwrap(),
+                b["bytes"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(sums, vec![(1, 5), (1, 5), (2, 10)]);
+    assert_eq!(buckets[2]["label"], "> 4 weeks");
+    assert!(root.join("d/target").exists());
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--dirs=target").arg("--age-histogram");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("1-4 weeks"))
+        .stdout(predicates::str::contains("##############################"));
+}
+
+/// Test that a match deleted by someone else between scan and removal is reported as already
+/// gone, not as a failure. The --review pause is the hook between the two phases.
+#[test]
+fn vanished_target_is_reported_as_gone() {
+    use std::process::Stdio;
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("projects");
+    create_dir_with_file(&root.join("a"), "target", "should_delete.txt");
+    create_dir_with_file(&root.join("b"), "target", "should_delete.txt");
+    let review = temp.path().join("review.txt");
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("cleaner"))
+        .arg(&root)
+        .arg("--dirs=target")
+        .arg("--review")
+        .arg(&review)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let waited = std::time::Instant::now();
+    while !review.exists() {
+        assert!(
+            waited.elapsed() < Duration::from_secs(10),
+            "scan never finished"
+        );
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    fs::remove_dir_all(root.join("a/target")).unwrap();
+    child.stdin.take().unwrap().write_all(b"\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Removed 1 directories. (Total size: 5 B)"));
+    assert!(stdout.contains("Already gone: 1 matches"));
+    assert!(!String::from_utf8(output.stderr).unwrap().contains("ERROR"));
+    assert!(!root.join("b/target").exists());
+}
+
+/// Test that --skip-submodules leaves build output inside git submodules alone.
+#[test]
+fn skip_submodules_spares_submodule_builds() {
+    let temp = tempdir().unwrap();
+    let repo = temp.path();
+    fs::write(
+        repo.join(".gitmodules"),
+        "[submodule \"libs/engine\"]\n\tpath = libs/engine\n\turl = ../engine.git\n",
+    )
+    .unwrap();
+    create_dir_with_file(&repo.join("libs/engine"), "target", "should_keep.txt");
+    create_dir_with_file(&repo.join("libs/other"), "target", "should_delete.txt");
+    create_dir_with_file(repo, "target", "should_delete.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(repo)
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--skip-submodules");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("Skipped: 1 submodules."));
+    assert!(repo.join("libs/engine/target").exists());
+    assert!(!repo.join("libs/other/target").exists());
+    assert!(!repo.join("target").exists());
+}
+
+/// Test that --preview-limit truncates the dry-run listing and summarizes the rest.
+#[test]
+fn preview_limit_truncates_dry_run() {
+    let temp = tempdir().unwrap();
+    for p in 0..5 {
+        create_dir_with_file(
+            &temp.path().join(format!("p{}", p)),
+            "target",
+            "should_keep.txt",
+        );
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path())
+        .arg("--dry-run")
+        .arg("--dirs=target")
+        .arg("--preview-limit")
+        .arg("2");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert_eq!(stdout.matches("Would remove:").count(), 2);
+    assert!(stdout.contains("... and 3 more (total 15 B)"));
+    assert!(stdout.contains("Dry run: 5 directories would be removed. (Total size: 25 B)"));
+}
+
+/// Test that --sorted lists scan output and matches in path order.
+#[test]
+fn sorted_output_is_ordered() {
+    let temp = tempdir().unwrap();
+    for project in ["delta", "alpha", "charlie", "bravo", "echo"] {
+        create_dir_with_file(&temp.path().join(project), "target", "should_keep.txt");
+        create_dir_with_file(&temp.path().join(project), "src", "should_keep.txt");
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path())
+        .arg("--dry-run")
+        .arg("--dirs=target")
+        .arg("--relative")
+        .arg("--explain")
+        .arg("--sorted");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    for prefix in ["explain: ", "Would remove: "] {
+        let paths: Vec<&str> = stdout
+            .lines()
+            .filter_map(|line| line.strip_prefix(prefix))
+            .map(|rest| rest.split(": ").next().unwrap())
+            .collect();
+        assert!(paths.len() >= 5, "{}", stdout);
+        let mut sorted = paths.clone();
+        sorted.sort();
+        assert_eq!(paths, sorted);
+    }
+}
+
+/// Test that --move-to onto another filesystem is refused unless --allow-cross-device is given,
+/// and then warns before copying. Skipped when /dev/shm is not a separate filesystem.
+#[cfg(unix)]
+#[test]
+fn cross_device_move_requires_opt_in() {
+    use std::os::unix::fs::MetadataExt;
+    let temp = tempdir().unwrap();
+    let Ok(shm) = tempfile::tempdir_in("/dev/shm") else {
+        return;
+    };
+    if fs::metadata(temp.path()).unwrap().dev() == fs::metadata(shm.path()).unwrap().dev() {
+        return;
+    }
+    create_dir_with_file(&temp.path().join("app"), "target", "should_move.txt");
+    let staging = shm.path().join("staging");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path())
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--move-to")
+        .arg(&staging);
+    cmd.assert()
+        .failure()
+        .stderr(predicates::str::contains("--allow-cross-device"));
+    assert!(temp.path().join("app/target").exists());
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path())
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--move-to")
+        .arg(&staging)
+        .arg("--allow-cross-device");
+    cmd.assert()
+        .success()
+        .stderr(predicates::str::contains("is on another filesystem"));
+    assert!(!temp.path().join("app/target").exists());
+    assert!(staging.join("app/target/should_move.txt").exists());
+}
+
+/// Test that --depth-report counts matches per depth without deleting them.
+#[test]
+fn depth_report_counts_matches_per_depth() {
+    let temp = tempdir().unwrap();
+    let root = temp.path();
+    create_dir_with_file(root, "target", "should_keep.txt");
+    for project in ["a", "b", "c"] {
+        create_dir_with_file(&root.join(project), "target", "should_keep.txt");
+    }
+    create_dir_with_file(&root.join("group/d"), "target", "should_keep.txt");
+    create_dir_with_file(&root.join("group/e"), "target", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root).arg("--dirs=target").arg("--depth-report");
+    cmd.assert().success().stdout(predicates::str::contains(
+        "depth 1: 1\ndepth 2: 3\ndepth 3: 2\n",
+    ));
+    assert!(root.join("group/e/target").exists());
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(root)
+        .arg("--dirs=target")
+        .arg("--depth-report")
+        .arg("--ci");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains(r#"{"1":1,"2":3,"3":2}"#));
+}
+
+/// Test that a kind's `post_command` runs in each cleaned project after removal, and not in a
+/// dry run.
+#[cfg(unix)]
+#[test]
+fn config_post_command_runs_after_removal() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("projects");
+    create_dir_with_file(&root.join("app"), "node_modules", "should_delete.txt");
+    create_dir_with_file(&root.join("web"), "node_modules", "should_delete.txt");
+    fs::create_dir_all(root.join("docs")).unwrap();
+    let config_path = temp.path().join("cleaner.toml");
+    fs::write(
+        &config_path,
+        "[kinds.node]\ndirs = [\"node_modules\"]\n\
+         post_command = \"test -d node_modules || touch rebuilt.txt\"\n",
+    )
+    .unwrap();
+    let run = |dry_run: bool| {
+        let mut cmd = Command::cargo_bin("cleaner").unwrap();
+        cmd.arg(&root)
+            .arg("--force")
+            .arg("--kind=node")
+            .arg("--config")
+            .arg(&config_path);
+        if dry_run {
+            cmd.arg("--dry-run");
+        }
+        cmd.assert().success();
+    };
+    run(true);
+    assert!(!root.join("app/rebuilt.txt").exists());
+    run(false);
+    assert!(root.join("app/rebuilt.txt").exists());
+    assert!(root.join("web/rebuilt.txt").exists());
+    assert!(!root.join("docs/rebuilt.txt").exists());
+    assert!(!root.join("app/node_modules").exists());
+}
+
+/// Test that --free-target removes nothing once the goal is met and everything when it cannot
+/// be reached.
+#[cfg(unix)]
+#[test]
+fn free_target_stops_at_goal() {
+    let temp = tempdir().unwrap();
+    create_dir_with_file(&temp.path().join("a"), "target", "should_keep.txt");
+    create_dir_with_file(&temp.path().join("b"), "target", "should_keep.txt");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path())
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--free-target")
+        .arg("1");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "removing the 0 largest of 2 matches",
+        ))
+        .stdout(predicates::str::contains("met."));
+    assert!(temp.path().join("a/target").exists());
+    assert!(temp.path().join("b/target").exists());
+
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(temp.path())
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--free-target")
+        .arg("1000000TB");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "removing the 2 largest of 2 matches",
+        ))
+        .stdout(predicates::str::contains("not met."));
+    assert!(!temp.path().join("a/target").exists());
+    assert!(!temp.path().join("b/target").exists());
+}
+
+/// Test that --summary-locale also formats the counts of the secondary summary lines and the
+/// --report-md table.
+#[test]
+fn summary_locale_formats_secondary_counts() {
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("work");
+    for i in 0..1002 {
+        fs::create_dir_all(root.join(format!("p{}/target", i))).unwrap();
+    }
+    let report = temp.path().join("report.md");
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root)
+        .arg("--dirs=target")
+        .arg("--dry-run")
+        .arg("--preview-limit")
+        .arg("1")
+        .arg("--report-md")
+        .arg(&report)
+        .arg("--summary-locale")
+        .arg("de");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("... and 1.001 more"))
+        .stdout(predicate::str::contains("By pattern: target: 1.002 dirs"))
+        .stdout(predicate::str::contains(
+            "1.002 directories would be removed",
+        ));
+    let markdown = fs::read_to_string(&report).unwrap();
+    assert!(markdown.contains("| **1.002** |"), "{}", markdown);
+}
+
+/// Test that --free-target with an empty --roots-stdin list has nothing to do instead of
+/// crashing.
+#[test]
+fn free_target_without_roots_does_nothing() {
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg("--roots-stdin")
+        .arg("--force")
+        .arg("--free-target")
+        .arg("1GB")
+        .write_stdin("");
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("Removed 0 directories"));
+}
+
+/// Test that --exit-nonzero-if-found fails a dry run only when something matched.
+#[test]
+fn exit_nonzero_if_found_gates_dry_run() {
+    let temp = tempdir().unwrap();
+    fs::create_dir(temp.path().join("src")).unwrap();
+    let dry_run = || {
+        let mut cmd = Command::cargo_bin("cleaner").unwrap();
+        cmd.arg(temp.path())
+            .arg("--dry-run")
+            .arg("--dirs=target")
+            .arg("--exit-nonzero-if-found");
+        cmd
+    };
+    dry_run().assert().success();
+    create_dir_with_file(temp.path(), "target", "should_keep.txt");
+    dry_run()
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("Would remove"))
+        .stderr(predicates::str::contains("1 matches found"));
+    assert!(temp.path().join("target").exists());
+}
+
+/// Test that a second run on a root that is being cleaned stops with "already running", and
+/// that --wait waits for the first run instead.
+#[test]
+fn concurrent_runs_on_same_root_are_locked() {
+    use std::process::Stdio;
+    let temp = tempdir().unwrap();
+    let root = temp.path().join("projects");
+    create_dir_with_file(&root.join("a"), "target", "should_delete.txt");
+    let review = temp.path().join("review.txt");
+    // The first run holds the lock while it waits for the review
+    let mut first = std::process::Command::new(assert_cmd::cargo::cargo_bin!("cleaner"))
+        .arg(&root)
+        .arg("--dirs=target")
+        .arg("--review")
+        .arg(&review)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let waited = std::time::Instant::now();
+    while !review.exists() {
+        assert!(
+            waited.elapsed() < Duration::from_secs(10),
+            "first run never paused"
+        );
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let mut cmd = Command::cargo_bin("cleaner").unwrap();
+    cmd.arg(&root).arg("--force").arg("--dirs=target");
+    cmd.assert()
+        .failure()
+        .stderr(predicates::str::contains("already cleaning"));
+    let second = std::process::Command::new(assert_cmd::cargo::cargo_bin!("cleaner"))
+        .arg(&root)
+        .arg("--force")
+        .arg("--dirs=target")
+        .arg("--wait")
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+    first.stdin.take().unwrap().write_all(b"\n").unwrap();
+    assert!(first.wait().unwrap().success());
+    let output = second.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Removed 0 directories"));
+}